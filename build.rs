@@ -0,0 +1,23 @@
+use std::process::Command;
+
+// 把构建时的 git 提交写入 PUMPKIN_MONITOR_GIT_COMMIT，供 /api/version 返回
+fn main() {
+    let commit = git(&["rev-parse", "HEAD"]).unwrap_or_else(|| "unknown".to_string());
+    // 工作区有未提交的修改时加上 -dirty 后缀
+    let dirty = git(&["status", "--porcelain", "--untracked-files=no"]).is_some_and(|status| !status.is_empty());
+    let commit = if dirty { format!("{}-dirty", commit) } else { commit };
+    println!("cargo:rustc-env=PUMPKIN_MONITOR_GIT_COMMIT={}", commit);
+
+    // 切换分支或提交后重新运行
+    println!("cargo:rerun-if-changed=.git/HEAD");
+    println!("cargo:rerun-if-changed=.git/refs");
+    println!("cargo:rerun-if-changed=.git/index");
+}
+
+fn git(args: &[&str]) -> Option<String> {
+    let output = Command::new("git").args(args).output().ok()?;
+    output
+        .status
+        .success()
+        .then(|| String::from_utf8_lossy(&output.stdout).trim().to_string())
+}
@@ -1,34 +1,364 @@
-use anyhow::Result;
-use std::path::PathBuf;
+use anyhow::{Context, Result};
+use std::collections::VecDeque;
+use std::path::{Path, PathBuf};
 use std::process::{Child, Command, Stdio};
-use std::sync::Arc;
+use std::sync::{Arc, Mutex};
 use std::time::Duration;
 use tokio::fs;
 use tokio::process::Command as TokioCommand;
-use tokio::sync::RwLock;
+use tokio::sync::{broadcast, Mutex as AsyncMutex, OwnedMutexGuard};
 use tokio::time::timeout;
-use tokio::io::{AsyncBufReadExt, BufReader};
-use tracing::{info, warn, error};
+use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader, BufWriter};
+use tokio_util::sync::CancellationToken;
+use tracing::{debug, info, warn, error};
 
-use crate::types::{Config, BuildStatus, BuildStatusType, GitHubCommit};
+use crate::console::Console;
+use crate::github::CommitStatusReporter;
+use crate::health::{query_server_status, HealthChecker};
+use crate::process::{match_process, ProcessMatch, ProcessTable, Signal, SystemProcessTable};
+use crate::diagnostics::{parse_cargo_message, strip_ansi, CargoMessage, MAX_DIAGNOSTICS};
+use crate::types::{Config, BuildLogLine, BuildStatus, BuildStatusType, Diagnostic, GitHubCommit, GitHubConfig, TargetCleanup, short_sha};
 
+// 正在进行、可以取消的构建
+struct ActiveBuild {
+    build_id: uuid::Uuid,
+    sha: String,
+    token: CancellationToken,
+    // 取消者和取消时间
+    cancelled: Option<(String, chrono::DateTime<chrono::Utc>)>,
+}
+
+// 从构建开始到准备替换服务之前可以取消构建，之后旧进程已经停止，不再接受取消
+#[derive(Clone, Default)]
+pub struct BuildCanceller {
+    active: Arc<Mutex<Option<ActiveBuild>>>,
+}
+
+impl BuildCanceller {
+    // 取消正在进行的构建，返回其构建 ID 和提交；没有可以取消的构建时返回 None
+    pub fn cancel(&self, by: &str) -> Option<(uuid::Uuid, String)> {
+        let mut active = self.active.lock().unwrap();
+        let build = active.as_mut()?;
+        if build.cancelled.is_none() {
+            build.cancelled = Some((by.to_string(), chrono::Utc::now()));
+            build.token.cancel();
+        }
+        Some((build.build_id, build.sha.clone()))
+    }
+
+    fn start(&self, build_id: uuid::Uuid, sha: &str) -> CancellationToken {
+        let token = CancellationToken::new();
+        *self.active.lock().unwrap() = Some(ActiveBuild {
+            build_id,
+            sha: sha.to_string(),
+            token: token.clone(),
+            cancelled: None,
+        });
+        token
+    }
+
+    fn finish(&self) {
+        self.active.lock().unwrap().take();
+    }
+
+    fn token(&self) -> Option<CancellationToken> {
+        self.active.lock().unwrap().as_ref().map(|build| build.token.clone())
+    }
+
+    fn cancellation(&self) -> Option<(String, chrono::DateTime<chrono::Utc>)> {
+        self.active.lock().unwrap().as_ref().and_then(|build| build.cancelled.clone())
+    }
+}
+
+// 已构建、等待服务器上没有玩家后再部署的提交
+struct WaitingDeploy {
+    sha: String,
+    // 通过 POST /api/deploy/now 要求立即部署
+    deploy_now: bool,
+}
+
+// restart_service 在替换服务前等待玩家离开，Web 接口可以查看并跳过等待
+#[derive(Clone, Default)]
+pub struct DeployGate {
+    waiting: Arc<Mutex<Option<WaitingDeploy>>>,
+}
+
+impl DeployGate {
+    pub fn waiting_commit(&self) -> Option<String> {
+        self.waiting.lock().unwrap().as_ref().map(|deploy| deploy.sha.clone())
+    }
+
+    // 跳过等待立即部署，返回等待中的提交；没有等待中的部署时返回 None
+    pub fn deploy_now(&self) -> Option<String> {
+        let mut waiting = self.waiting.lock().unwrap();
+        let deploy = waiting.as_mut()?;
+        deploy.deploy_now = true;
+        Some(deploy.sha.clone())
+    }
+
+    fn start(&self, sha: &str) {
+        *self.waiting.lock().unwrap() = Some(WaitingDeploy { sha: sha.to_string(), deploy_now: false });
+    }
+
+    fn deploy_now_requested(&self) -> bool {
+        self.waiting.lock().unwrap().as_ref().is_some_and(|deploy| deploy.deploy_now)
+    }
+
+    fn finish(&self) {
+        self.waiting.lock().unwrap().take();
+    }
+}
+
+// 等待结束或部署提前返回时清除等待中的提交
+struct DeployGateGuard(DeployGate);
+
+impl Drop for DeployGateGuard {
+    fn drop(&mut self) {
+        self.0.finish();
+    }
+}
+
+// 一次定期清理的结果
+pub struct MaintenanceReport {
+    pub reclaimed_bytes: u64,
+    pub workspace_size_bytes: u64,
+}
+
+// 一次部署的结果
+pub struct RestartResult {
+    pub build_status: BuildStatus,
+    // 正在运行的服务进程
+    pub pid: Option<u32>,
+    // 新版本启动失败时回滚到的提交（短 SHA）
+    pub rolled_back_to: Option<String>,
+}
+
+// 一次 git 命令的结果
+struct GitRun {
+    success: bool,
+    output: Vec<String>,
+}
+
+// 根据 git 输出识别的常见失败原因
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum GitFailure {
+    LocalChanges,
+    UntrackedFiles,
+    UnresolvedConflict,
+    IndexLocked,
+    UnknownRevision,
+    NotARepository,
+    Network,
+    Authentication,
+}
+
+impl GitFailure {
+    // git 输出中的特征文字，按顺序匹配，未跟踪文件的提示也包含 "would be overwritten by checkout"
+    const PATTERNS: [(&'static str, GitFailure); 12] = [
+        ("untracked working tree files would be", GitFailure::UntrackedFiles),
+        ("would be overwritten by checkout", GitFailure::LocalChanges),
+        ("please commit your changes or stash them", GitFailure::LocalChanges),
+        ("resolve your current index first", GitFailure::UnresolvedConflict),
+        ("you have unmerged paths", GitFailure::UnresolvedConflict),
+        ("index.lock", GitFailure::IndexLocked),
+        ("reference is not a tree", GitFailure::UnknownRevision),
+        ("did not match any file(s) known to git", GitFailure::UnknownRevision),
+        ("does not take a path argument", GitFailure::UnknownRevision),
+        ("not a git repository", GitFailure::NotARepository),
+        ("could not resolve host", GitFailure::Network),
+        ("authentication failed", GitFailure::Authentication),
+    ];
+
+    fn describe(self) -> &'static str {
+        match self {
+            GitFailure::LocalChanges => "the workspace repository has local modifications",
+            GitFailure::UntrackedFiles => "untracked files in the workspace repository would be overwritten",
+            GitFailure::UnresolvedConflict => "the workspace repository has an unresolved merge conflict",
+            GitFailure::IndexLocked => "another git process is running or left a stale index.lock",
+            GitFailure::UnknownRevision => "the commit does not exist locally",
+            GitFailure::NotARepository => "the workspace repository is not a git repository, delete it to clone again",
+            GitFailure::Network => "could not reach the git remote",
+            GitFailure::Authentication => "authentication with the git remote failed",
+        }
+    }
+
+    // 可以通过丢弃本地修改恢复的失败
+    fn is_dirty_worktree(self) -> bool {
+        matches!(self, GitFailure::LocalChanges | GitFailure::UntrackedFiles | GitFailure::UnresolvedConflict)
+    }
+}
+
+impl GitRun {
+    fn failure(&self) -> Option<GitFailure> {
+        if self.success {
+            return None;
+        }
+        self.output.iter().find_map(|line| {
+            let line = line.to_lowercase();
+            GitFailure::PATTERNS
+                .iter()
+                .find(|(pattern, _)| line.contains(pattern))
+                .map(|(_, failure)| *failure)
+        })
+    }
+
+    // 失败时返回说明原因的错误，无法识别原因时附上 git 输出的最后一行
+    fn check(&self, action: &str) -> Result<()> {
+        if self.success {
+            return Ok(());
+        }
+        let detail = match self.failure() {
+            Some(failure) if failure.is_dirty_worktree() => {
+                format!("{} (set runtime.force_reset = true to discard them automatically)", failure.describe())
+            }
+            Some(failure) => failure.describe().to_string(),
+            None => self.output.last().cloned().unwrap_or_else(|| "no output".to_string()),
+        };
+        Err(GitError { failure: self.failure(), message: format!("{} failed: {}", action, detail) }.into())
+    }
+}
+
+// git 命令失败，保留识别出的原因，用于判断删除仓库重新克隆能否解决
+#[derive(Debug)]
+struct GitError {
+    failure: Option<GitFailure>,
+    message: String,
+}
+
+impl std::fmt::Display for GitError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&self.message)
+    }
+}
+
+impl std::error::Error for GitError {}
+
+impl GitError {
+    // 网络和认证问题重新克隆也无法解决；本地修改按 runtime.force_reset 处理，不在这里删除
+    fn fixed_by_reclone(&self) -> bool {
+        !matches!(
+            self.failure,
+            Some(GitFailure::Network | GitFailure::Authentication)
+        ) && !self.failure.is_some_and(GitFailure::is_dirty_worktree)
+    }
+}
+
+// 更新或检出连续失败多少次后删除仓库目录重新克隆
+const WORKSPACE_SYNC_ATTEMPTS: u32 = 2;
+
+// 服务进程：本程序启动的子进程，或监控程序重启后按 PID 重新接管的进程
+enum ServerProcess {
+    Spawned(Child),
+    // 接管的进程不是本程序的子进程，没有 stdio 管道，控制台不可用
+    Adopted(u32),
+}
+
+impl ServerProcess {
+    fn id(&self) -> u32 {
+        match self {
+            ServerProcess::Spawned(child) => child.id(),
+            ServerProcess::Adopted(pid) => *pid,
+        }
+    }
+
+    fn has_exited(&mut self, processes: &dyn ProcessTable) -> std::io::Result<bool> {
+        match self {
+            ServerProcess::Spawned(child) => Ok(child.try_wait()?.is_some()),
+            ServerProcess::Adopted(pid) => Ok(!processes.is_alive(*pid)),
+        }
+    }
+
+    fn kill(&mut self, processes: &dyn ProcessTable) -> std::io::Result<()> {
+        match self {
+            ServerProcess::Spawned(child) => {
+                child.kill()?;
+                let _ = child.wait();
+                Ok(())
+            }
+            ServerProcess::Adopted(pid) => processes.signal(*pid, Signal::Kill),
+        }
+    }
+}
+
+// 克隆出的 BuildManager 共享同一个服务进程句柄，保证所有任务看到的进程状态一致
+#[derive(Clone)]
 pub struct BuildManager {
     config: Config,
-    current_process: Option<Child>,
+    repo: GitHubConfig,
+    current_process: Arc<Mutex<Option<ServerProcess>>>,
+    last_stop_graceful: Arc<Mutex<Option<bool>>>,
     workspace_path: PathBuf,
+    console: Console,
+    build_output: broadcast::Sender<BuildLogLine>,
+    status_reporter: CommitStatusReporter,
+    processes: Arc<dyn ProcessTable>,
+    // 同一工作空间同时只进行一次构建或部署，状态监控自动重启服务时也要持有
+    build_lock: Arc<AsyncMutex<()>>,
+    canceller: BuildCanceller,
+    deploy_gate: DeployGate,
 }
 
 impl BuildManager {
-    pub fn new(config: Config) -> Self {
-        let workspace_path = PathBuf::from(&config.build.workspace_dir);
+    pub fn new(config: Config, repo: GitHubConfig, console: Console) -> Self {
+        let workspace_path = config.repo_workspace(&repo);
+        let (build_output, _) = broadcast::channel(1024);
+        let status_reporter = CommitStatusReporter::new(&repo, config.notifications.dashboard_url.as_deref());
         
         Self {
             config,
-            current_process: None,
+            repo,
+            current_process: Arc::new(Mutex::new(None)),
+            last_stop_graceful: Arc::new(Mutex::new(None)),
             workspace_path,
+            console,
+            build_output,
+            status_reporter,
+            processes: Arc::new(SystemProcessTable),
+            build_lock: Arc::new(AsyncMutex::new(())),
+            canceller: BuildCanceller::default(),
+            deploy_gate: DeployGate::default(),
         }
     }
 
+    // 已有构建在进行时等待它结束，两次构建按顺序进行
+    async fn lock_build(&self) -> OwnedMutexGuard<()> {
+        if let Ok(guard) = self.build_lock.clone().try_lock_owned() {
+            return guard;
+        }
+        info!("Another build is in progress, waiting for it to finish");
+        self.build_lock.clone().lock_owned().await
+    }
+
+    // 没有正在进行的构建时返回守卫，持有期间不会开始新的构建
+    pub fn try_lock_build(&self) -> Option<OwnedMutexGuard<()>> {
+        self.build_lock.clone().try_lock_owned().ok()
+    }
+
+    pub fn build_canceller(&self) -> BuildCanceller {
+        self.canceller.clone()
+    }
+
+    pub fn deploy_gate(&self) -> DeployGate {
+        self.deploy_gate.clone()
+    }
+
+    // 构建被取消后记录取消者和时间，旧版本服务保持运行
+    fn mark_cancelled(&self, build_status: &mut BuildStatus) {
+        let (by, at) = self.canceller
+            .cancellation()
+            .unwrap_or_else(|| ("unknown".to_string(), chrono::Utc::now()));
+        warn!("Build {} of {} was cancelled by {}", build_status.id, build_status.commit_sha, by);
+        build_status.status = BuildStatusType::Cancelled;
+        build_status.error_message = Some(format!("Cancelled by {} at {}", by, at.to_rfc3339()));
+        build_status.cancelled_by = Some(by);
+        build_status.cancelled_at = Some(at);
+    }
+
+    // 构建输出的广播端，网页端通过它订阅实时日志
+    pub fn build_output(&self) -> broadcast::Sender<BuildLogLine> {
+        self.build_output.clone()
+    }
+
     pub async fn ensure_workspace(&self) -> Result<()> {
         if !self.workspace_path.exists() {
             info!("Creating workspace directory: {:?}", self.workspace_path);
@@ -37,144 +367,581 @@ impl BuildManager {
         Ok(())
     }
 
-    pub async fn clone_or_update_repo(&self) -> Result<()> {
-        let repo_url = format!(
-            "https://github.com/{}/{}.git",
-            self.config.github.repo_owner,
-            self.config.github.repo_name
-        );
+    // 克隆私有仓库所需的凭据，通过环境变量传给 git，不出现在命令行参数和工作区的 .git/config 中
+    fn git_env(&self) -> Vec<(String, String)> {
+        let mut env = Vec::new();
+        if let Some(key_path) = &self.config.build.ssh_key_path {
+            // git 在仓库目录中执行 ssh，相对路径需要先转为绝对路径
+            let key_path = std::path::absolute(key_path).unwrap_or_else(|_| PathBuf::from(key_path));
+            env.push((
+                "GIT_SSH_COMMAND".to_string(),
+                format!("ssh -i {} -o IdentitiesOnly=yes", shell_quote(&key_path.to_string_lossy())),
+            ));
+        }
+        if let Some(token) = self.git_token() {
+            let credentials = base64_encode(format!("x-access-token:{}", token).as_bytes());
+            env.extend([
+                ("GIT_CONFIG_COUNT".to_string(), "1".to_string()),
+                ("GIT_CONFIG_KEY_0".to_string(), "http.extraHeader".to_string()),
+                ("GIT_CONFIG_VALUE_0".to_string(), format!("Authorization: Basic {}", credentials)),
+            ]);
+        }
+        env
+    }
 
-        let repo_path = self.workspace_path.join(&self.config.github.repo_name);
+    fn git_token(&self) -> Option<String> {
+        self.config.build.https_token_auth
+            .then(|| self.repo.resolved_token())
+            .flatten()
+    }
 
-        if repo_path.exists() {
-            info!("Updating existing repository");
-            
-            let mut child = TokioCommand::new("git")
-                .args(&["pull", "origin", &self.config.github.branch])
-                .current_dir(&repo_path)
+    // 运行 git 命令并实时输出结果，返回是否成功和输出，输出用于判断失败原因
+    async fn run_git(&self, args: &[&str], dir: &Path) -> Result<GitRun> {
+        let mut child = spawn_in_own_group(
+            TokioCommand::new("git")
+                .args(args)
+                .envs(self.git_env())
+                .current_dir(dir)
                 .stdout(Stdio::piped())
-                .stderr(Stdio::piped())
-                .spawn()?;
+                .stderr(Stdio::piped()),
+        )?;
+        let token = self.git_token();
 
-            // 实时输出 git pull 的结果
-            let stdout = child.stdout.take().unwrap();
-            let stderr = child.stderr.take().unwrap();
-            
-            let stdout_reader = BufReader::new(stdout);
-            let stderr_reader = BufReader::new(stderr);
-            
-            let mut stdout_lines = stdout_reader.lines();
-            let mut stderr_lines = stderr_reader.lines();
-            
-            let output_task = async {
-                loop {
-                    tokio::select! {
-                        line = stdout_lines.next_line() => {
-                            match line {
-                                Ok(Some(line)) => {
-                                    info!("[GIT] {}", line);
-                                }
-                                Ok(None) => break,
-                                Err(_) => break,
-                            }
-                        }
-                        line = stderr_lines.next_line() => {
-                            match line {
-                                Ok(Some(line)) => {
-                                    info!("[GIT] {}", line);
-                                }
-                                Ok(None) => break,
-                                Err(_) => break,
-                            }
-                        }
+        let mut stdout_lines = BufReader::new(child.stdout.take().unwrap()).lines();
+        let mut stderr_lines = BufReader::new(child.stderr.take().unwrap()).lines();
+
+        let mut output = Vec::new();
+        let result = timeout(Duration::from_secs(self.config.build.git_timeout), async {
+            let mut stdout_open = true;
+            let mut stderr_open = true;
+
+            while stdout_open || stderr_open {
+                let line = tokio::select! {
+                    line = stdout_lines.next_line(), if stdout_open => {
+                        line.ok().flatten().or_else(|| { stdout_open = false; None })
+                    }
+                    line = stderr_lines.next_line(), if stderr_open => {
+                        line.ok().flatten().or_else(|| { stderr_open = false; None })
                     }
+                };
+                if let Some(mut line) = line {
+                    // 令牌不应出现在 git 输出中，以防万一仍然替换掉再记录
+                    if let Some(token) = token.as_deref() {
+                        line = line.replace(token, "***");
+                    }
+                    info!("[GIT] {}", line);
+                    output.push(line);
                 }
-            };
+            }
 
-            let (_, exit_status) = tokio::join!(output_task, child.wait());
-            
-            if !exit_status?.success() {
-                return Err(anyhow::anyhow!("Git pull failed"));
+            child.wait().await
+        }).await;
+
+        match result {
+            Ok(exit_status) => Ok(GitRun { success: exit_status?.success(), output }),
+            Err(_) => {
+                kill_process_group(&mut child).await;
+                Err(anyhow::anyhow!("git {} timed out after {}s", args.join(" "), self.config.build.git_timeout))
+            }
+        }
+    }
+
+    pub async fn clone_or_update_repo(&self) -> Result<()> {
+        let repo_url = self.repo.resolved_clone_url();
+
+        let repo_path = self.workspace_path.join(&self.repo.repo_name);
+        let branch = self.repo.branch.as_str();
+
+        if repo_path.exists() {
+            info!("Fetching latest changes from origin");
+
+            // clone_url 修改后让已有的工作区改用新地址
+            let origin = self.git_output(&["remote", "get-url", "origin"], &repo_path).await?;
+            if origin != repo_url {
+                info!("Updating origin URL from {} to {}", origin, repo_url);
+                self.run_git(&["remote", "set-url", "origin", &repo_url], &repo_path).await?.check("Git remote set-url")?;
             }
+
+            // 只获取远端提交，具体构建哪个提交由 checkout_commit 决定；跟踪标签时同时获取不在分支上的标签
+            let mut fetch = vec!["fetch", "origin"];
+            if self.repo.tag.is_some() {
+                fetch.push("--tags");
+            }
+            let depth = self.config.build.clone_depth.map(|depth| format!("--depth={}", depth));
+            fetch.extend(depth.as_deref());
+            self.run_git(&fetch, &repo_path).await?.check("Git fetch")?;
         } else {
             info!("Cloning repository");
-            
-            let mut child = TokioCommand::new("git")
-                .args(&["clone", "--branch", &self.config.github.branch, &repo_url])
-                .current_dir(&self.workspace_path)
-                .stdout(Stdio::piped())
-                .stderr(Stdio::piped())
-                .spawn()?;
 
-            // 实时输出 git clone 的结果
-            let stdout = child.stdout.take().unwrap();
-            let stderr = child.stderr.take().unwrap();
-            
-            let stdout_reader = BufReader::new(stdout);
-            let stderr_reader = BufReader::new(stderr);
-            
-            let mut stdout_lines = stdout_reader.lines();
-            let mut stderr_lines = stderr_reader.lines();
-            
-            let output_task = async {
-                loop {
-                    tokio::select! {
-                        line = stdout_lines.next_line() => {
-                            match line {
-                                Ok(Some(line)) => {
-                                    info!("[GIT] {}", line);
-                                }
-                                Ok(None) => break,
-                                Err(_) => break,
-                            }
-                        }
-                        line = stderr_lines.next_line() => {
-                            match line {
-                                Ok(Some(line)) => {
-                                    info!("[GIT] {}", line);
-                                }
-                                Ok(None) => break,
-                                Err(_) => break,
-                            }
-                        }
-                    }
+            // 显式指定目录，clone_url 中的仓库名与 repo_name 不同时也克隆到 repo_name 下
+            let mut clone = vec!["clone", "--branch", branch];
+            let depth = self.config.build.clone_depth.map(|depth| format!("--depth={}", depth));
+            if let Some(depth) = depth.as_deref() {
+                clone.extend([depth, "--single-branch"]);
+            }
+            if self.config.build.filter_blobs {
+                clone.push("--filter=blob:none");
+            }
+            if self.config.build.submodules {
+                clone.push("--recurse-submodules");
+            }
+            clone.extend([repo_url.as_str(), self.repo.repo_name.as_str()]);
+            self.run_git(&clone, &self.workspace_path).await?.check("Git clone")?;
+        }
+
+        Ok(())
+    }
+
+    // 获取远端更新并检出提交；连续失败时视为工作区仓库损坏，删除后重新克隆。
+    // 执行过的恢复操作记录在 recovery 中，最终失败时附加到构建记录的错误信息
+    async fn sync_workspace(&self, sha: &str, recovery: &mut Vec<String>) -> Result<()> {
+        let mut attempt = 1;
+        let error = loop {
+            let result = match self.clone_or_update_repo().await {
+                Ok(()) => self.checkout_commit(sha).await.context("Failed to checkout commit"),
+                Err(e) => Err(e.context("Failed to update repository")),
+            };
+            let e = match result {
+                Ok(()) => return Ok(()),
+                Err(e) => e,
+            };
+            let fixed_by_reclone = e.downcast_ref::<GitError>().is_some_and(GitError::fixed_by_reclone);
+            if !fixed_by_reclone || attempt >= WORKSPACE_SYNC_ATTEMPTS {
+                break e;
+            }
+            warn!("Updating the workspace failed (attempt {}): {:#}, retrying", attempt, e);
+            recovery.push(format!("attempt {} failed: {:#}", attempt, e));
+            attempt += 1;
+        };
+
+        let reclone = error.downcast_ref::<GitError>().is_some_and(GitError::fixed_by_reclone);
+        if !reclone {
+            return Err(error);
+        }
+        let repo_path = self.workspace_path.join(&self.repo.repo_name);
+        warn!("Workspace repository {:?} looks corrupted ({:#}), deleting it and cloning again", repo_path, error);
+        recovery.push(format!("attempt {} failed: {:#}", attempt, error));
+        recovery.push(format!("deleted {} and cloned again", repo_path.display()));
+        if repo_path.exists() {
+            fs::remove_dir_all(&repo_path).await
+                .with_context(|| format!("Failed to delete corrupted repository {:?}", repo_path))?;
+        }
+        self.clone_or_update_repo().await.context("Failed to clone repository")?;
+        self.checkout_commit(sha).await.context("Failed to checkout commit")?;
+        info!("Workspace repository recovered by cloning again");
+        Ok(())
+    }
+
+    // 切换到指定提交，本地不存在时先从远端获取，并确认工作区确实位于该提交
+    pub async fn checkout_commit(&self, sha: &str) -> Result<()> {
+        let repo_path = self.workspace_path.join(&self.repo.repo_name);
+
+        let mut checkout = self.run_git(&["checkout", "--detach", sha], &repo_path).await?;
+        // 无法识别失败原因时也尝试获取，和之前的行为保持一致
+        if !checkout.success && matches!(checkout.failure(), None | Some(GitFailure::UnknownRevision)) {
+            info!("Commit {} not found locally, fetching from origin", sha);
+            let depth = self.config.build.clone_depth.map(|depth| format!("--depth={}", depth));
+            let mut fetch = vec!["fetch", "origin", sha];
+            fetch.extend(depth.as_deref());
+            let fetched = self.run_git(&fetch, &repo_path).await?.success;
+            if fetched {
+                checkout = self.run_git(&["checkout", "--detach", sha], &repo_path).await?;
+            }
+
+            // 浅克隆中没有该提交时获取完整历史后再试，不直接报告 git 的错误
+            if !checkout.success && self.is_shallow(&repo_path).await {
+                info!("Commit {} is not in the shallow history, deepening the clone", sha);
+                self.run_git(&["fetch", "--unshallow", "origin"], &repo_path).await?.check("Git fetch --unshallow")?;
+                checkout = self.run_git(&["checkout", "--detach", sha], &repo_path).await?;
+            } else if !fetched {
+                return Err(anyhow::anyhow!(
+                    "Commit {} is not reachable from origin (it may have been force-pushed away)",
+                    sha
+                ));
+            }
+            if checkout.failure() == Some(GitFailure::UnknownRevision) {
+                return Err(anyhow::anyhow!(
+                    "Commit {} is not reachable from origin (it may have been force-pushed away)",
+                    sha
+                ));
+            }
+        }
+
+        // 工作区有本地修改或未完成的合并时，按配置丢弃这些修改后重试
+        if checkout.failure().is_some_and(GitFailure::is_dirty_worktree) && self.config.runtime.force_reset {
+            warn!("Discarding local changes in {:?} (runtime.force_reset)", repo_path);
+            self.run_git(&["reset", "--hard"], &repo_path).await?.check("Git reset")?;
+            // 不加 -x，保留 target 等被忽略的文件，避免重新完整编译
+            self.run_git(&["clean", "-fd"], &repo_path).await?.check("Git clean")?;
+            checkout = self.run_git(&["checkout", "--detach", sha], &repo_path).await?;
+        }
+        checkout.check(&format!("Git checkout of {}", sha))?;
+
+        let head = self.git_output(&["rev-parse", "HEAD"], &repo_path).await?;
+        if !head.starts_with(sha) {
+            return Err(anyhow::anyhow!(
+                "Workspace is at {} after checkout, expected {}",
+                head, sha
+            ));
+        }
+
+        info!("Workspace checked out at {}", head);
+
+        if self.config.build.submodules {
+            self.update_submodules(&repo_path).await?;
+        }
+        Ok(())
+    }
+
+    // 按检出的提交更新子模块，失败时在错误中指出是哪个子模块，而不是之后由 cargo 报出缺少文件
+    async fn update_submodules(&self, repo_path: &Path) -> Result<()> {
+        info!("Updating submodules");
+        let update = self.run_git(&["submodule", "update", "--init", "--recursive"], repo_path).await?;
+        if update.success {
+            return Ok(());
+        }
+        match failed_submodule(&update.output) {
+            Some(name) => update.check(&format!("Git submodule update of '{}'", name)),
+            None => update.check("Git submodule update"),
+        }
+    }
+
+    // 工作区仓库是否为浅克隆
+    async fn is_shallow(&self, repo_path: &Path) -> bool {
+        self.git_output(&["rev-parse", "--is-shallow-repository"], repo_path)
+            .await
+            .is_ok_and(|output| output == "true")
+    }
+
+    // 执行 git 命令并返回去掉首尾空白的 stdout
+    async fn git_output(&self, args: &[&str], dir: &Path) -> Result<String> {
+        let output = TokioCommand::new("git")
+            .args(args)
+            .current_dir(dir)
+            .output()
+            .await?;
+
+        if !output.status.success() {
+            return Err(anyhow::anyhow!(
+                "git {} failed: {}",
+                args.join(" "),
+                String::from_utf8_lossy(&output.stderr).trim()
+            ));
+        }
+
+        Ok(String::from_utf8_lossy(&output.stdout).trim().to_string())
+    }
+
+    pub fn build_log_path(&self, build_id: uuid::Uuid) -> PathBuf {
+        self.workspace_path.join("build-logs").join(format!("{}.log", build_id))
+    }
+
+    #[tracing::instrument(name = "build", skip_all, fields(commit = %sha, build_id = %build_id))]
+    pub async fn build_project(&self, sha: &str, build_id: uuid::Uuid) -> Result<BuildStatus> {
+        let mut build_status = BuildStatus::new(build_id, sha.to_string());
+
+        info!("Starting build for commit: {}", sha);
+        self.status_reporter.build_started(sha).await;
+
+        let repo_path = self.workspace_path.join(&self.repo.repo_name);
+        let log_path = self.build_log_path(build_id);
+        let mut build_log = BuildLog::create(&log_path, self.config.build.max_log_bytes).await;
+        if build_log.is_open() {
+            build_status.log_path = Some(log_path.to_string_lossy().to_string());
+        }
+
+        // 安装仓库固定的工具链，失败时不再构建，避免报出令人困惑的编译错误
+        match self.ensure_toolchain(&repo_path, build_id, &mut build_log).await? {
+            Ok(toolchain) => build_status.toolchain = toolchain,
+            Err(e) => {
+                if self.canceller.cancellation().is_some() {
+                    self.mark_cancelled(&mut build_status);
+                } else {
+                    error!("{} (commit {})", e, sha);
+                    build_status.status = BuildStatusType::Failed;
+                    build_status.error_message = Some(e);
                 }
+                return Ok(self.finish_build(sha, build_status, build_log).await);
+            }
+        }
+
+        // 构建项目，使用实时输出
+        let command_line = self.config.build.command_line();
+        info!("Running: {}", command_line);
+        build_status.command = Some(command_line);
+        let max_attempts = self.config.build.max_build_retries + 1;
+        let mut attempt = 1;
+        let outcome = loop {
+            build_status.diagnostics.clear();
+            let outcome = self.run_logged(
+                &self.config.build.build_command,
+                &self.config.build.command_args(),
+                &repo_path,
+                self.config.build.build_timeout,
+                build_id,
+                &mut build_log,
+                OutputCapture::Stderr,
+                &mut build_status.diagnostics,
+            ).await?;
+
+            // 只重试非编译错误导致的失败，超时和编译错误重试也不会成功
+            let retryable = match &outcome {
+                CommandOutcome::Failed(output) => !is_compile_error(output, &build_status.diagnostics),
+                _ => false,
             };
+            if !retryable || attempt >= max_attempts {
+                break outcome;
+            }
 
-            let (_, exit_status) = tokio::join!(output_task, child.wait());
-            
-            if !exit_status?.success() {
-                return Err(anyhow::anyhow!("Git clone failed"));
+            let delay = self.config.build.build_retry_delay.saturating_mul(1 << (attempt - 1).min(10));
+            let line = format!("Build attempt {} of {} failed, retrying in {}s", attempt, max_attempts, delay);
+            warn!("{}", line);
+            build_log.write_line("monitor", &line).await;
+            let _ = self.build_output.send(BuildLogLine { build_id, line });
+            let retry_delay = tokio::time::sleep(Duration::from_secs(delay));
+            match self.canceller.token() {
+                Some(token) => tokio::select! {
+                    _ = retry_delay => {}
+                    _ = token.cancelled() => break CommandOutcome::Cancelled,
+                },
+                None => retry_delay.await,
             }
+            attempt += 1;
+        };
+        if max_attempts > 1 {
+            build_status.attempt = Some(attempt);
+            build_status.max_attempts = Some(max_attempts);
         }
 
+        match outcome {
+            CommandOutcome::Success => {
+                info!("Build successful for commit: {}", sha);
+                build_status.status = BuildStatusType::Success;
+            }
+            CommandOutcome::Failed(error_output) => {
+                error!("Build failed for commit {}", sha);
+                if !error_output.is_empty() {
+                    error!("Build errors:\n{}", error_output);
+                }
+                build_status.status = BuildStatusType::Failed;
+                build_status.error_message = Some(error_output);
+            }
+            CommandOutcome::Error(e) => {
+                error!("Build process error for commit {}: {}", sha, e);
+                build_status.status = BuildStatusType::Failed;
+                build_status.error_message = Some(e);
+            }
+            CommandOutcome::Cancelled => self.mark_cancelled(&mut build_status),
+            CommandOutcome::Timeout => {
+                error!("Build timeout for commit: {}", sha);
+                build_status.status = BuildStatusType::Failed;
+                build_status.error_message = Some("Build timeout".to_string());
+            }
+        }
+
+        if build_status.status == BuildStatusType::Success && self.config.build.run_tests {
+            self.run_tests(sha, build_id, &repo_path, &mut build_status, &mut build_log).await?;
+        }
+        if build_status.status == BuildStatusType::Success {
+            self.run_post_build_command(sha, build_id, &repo_path, &mut build_status, &mut build_log).await?;
+        }
+
+        Ok(self.finish_build(sha, build_status, build_log).await)
+    }
+
+    // 执行 build.post_build_command，输出与构建一样写入构建日志并广播
+    async fn run_post_build_command(
+        &self,
+        sha: &str,
+        build_id: uuid::Uuid,
+        repo_path: &Path,
+        build_status: &mut BuildStatus,
+        build_log: &mut BuildLog,
+    ) -> Result<()> {
+        let Some(command) = self.config.build.post_build_command.as_deref().filter(|command| !command.trim().is_empty()) else {
+            return Ok(());
+        };
+        info!("Running post-build command: {}", command);
+        build_status.command = Some(match build_status.command.take() {
+            Some(previous) => format!("{} && {}", previous, command),
+            None => command.to_string(),
+        });
+
+        let outcome = self.run_logged(
+            "sh",
+            &["-c".to_string(), command.to_string()],
+            repo_path,
+            self.config.build.build_timeout,
+            build_id,
+            build_log,
+            OutputCapture::All,
+            &mut Vec::new(),
+        ).await?;
+
+        let error_message = match outcome {
+            CommandOutcome::Success => return Ok(()),
+            CommandOutcome::Failed(output) => format!("Post-build command failed:\n{}", output),
+            CommandOutcome::Error(e) => format!("Post-build command failed: {}", e),
+            CommandOutcome::Timeout => "Post-build command timed out".to_string(),
+            CommandOutcome::Cancelled => {
+                self.mark_cancelled(build_status);
+                return Ok(());
+            }
+        };
+        error!("Post-build command failed for commit {}, not starting the new build", sha);
+        build_status.status = BuildStatusType::Failed;
+        build_status.error_message = Some(error_message);
         Ok(())
     }
 
-    pub async fn build_project(&self, commit: &GitHubCommit) -> Result<BuildStatus> {
-        let mut build_status = BuildStatus {
-            id: uuid::Uuid::new_v4(),
-            commit_sha: commit.sha.clone(),
-            status: BuildStatusType::Building,
-            started_at: chrono::Utc::now(),
-            finished_at: None,
-            error_message: None,
+    async fn finish_build(&self, sha: &str, mut build_status: BuildStatus, mut build_log: BuildLog) -> BuildStatus {
+        build_log.finish().await;
+        if build_status.log_path.is_some() {
+            build_status.log_size = Some(build_log.written());
+        }
+        build_status.finish();
+        self.status_reporter.build_finished(sha, &build_status.status).await;
+        build_status
+    }
+
+    // 读取仓库的 rust-toolchain.toml（或旧式的 rust-toolchain），启用 auto_install_toolchain 且尚未安装时
+    // 通过 rustup 安装，输出和构建输出一样实时显示；返回工具链名称，没有固定工具链时为 None
+    async fn ensure_toolchain(
+        &self,
+        repo_path: &Path,
+        build_id: uuid::Uuid,
+        build_log: &mut BuildLog,
+    ) -> Result<std::result::Result<Option<String>, String>> {
+        let Some(toolchain) = read_toolchain_file(repo_path).await else {
+            return Ok(Ok(None));
+        };
+        if !self.config.build.auto_install_toolchain {
+            return Ok(Ok(Some(toolchain.channel)));
+        }
+
+        let installed = match TokioCommand::new("rustup").args(["toolchain", "list"]).output().await {
+            Ok(output) if output.status.success() => String::from_utf8_lossy(&output.stdout).into_owned(),
+            Ok(output) => {
+                return Ok(Err(format!(
+                    "Failed to install Rust toolchain {}: rustup toolchain list failed: {}",
+                    toolchain.channel,
+                    String::from_utf8_lossy(&output.stderr).trim()
+                )));
+            }
+            Err(e) => {
+                return Ok(Err(format!("Failed to install Rust toolchain {}: cannot run rustup: {}", toolchain.channel, e)));
+            }
         };
+        if installed.lines().any(|line| toolchain_matches(line, &toolchain.channel)) {
+            return Ok(Ok(Some(toolchain.channel)));
+        }
 
-        info!("Starting build for commit: {}", commit.sha);
+        let line = format!("Installing Rust toolchain {} pinned by the repository", toolchain.channel);
+        info!("{}", line);
+        build_log.write_line("monitor", &line).await;
+        let _ = self.build_output.send(BuildLogLine { build_id, line });
 
-        let repo_path = self.workspace_path.join(&self.config.github.repo_name);
+        let mut args = vec![
+            "toolchain".to_string(),
+            "install".to_string(),
+            toolchain.channel.clone(),
+            "--profile".to_string(),
+            "minimal".to_string(),
+        ];
+        for component in &toolchain.components {
+            args.extend(["--component".to_string(), component.clone()]);
+        }
+        let outcome = self.run_logged(
+            "rustup",
+            &args,
+            repo_path,
+            self.config.build.build_timeout,
+            build_id,
+            build_log,
+            OutputCapture::All,
+            &mut Vec::new(),
+        ).await?;
 
-        // 构建项目，使用实时输出
-        let mut child = TokioCommand::new("cargo")
-            .args(&["build", "--release"])
-            .current_dir(&repo_path)
-            .stdout(Stdio::piped())
-            .stderr(Stdio::piped())
-            .spawn()?;
+        let error = match outcome {
+            CommandOutcome::Success => return Ok(Ok(Some(toolchain.channel))),
+            CommandOutcome::Failed(output) => output,
+            CommandOutcome::Error(e) => e,
+            CommandOutcome::Timeout => "rustup timed out".to_string(),
+            CommandOutcome::Cancelled => "cancelled".to_string(),
+        };
+        Ok(Err(format!("Failed to install Rust toolchain {}: {}", toolchain.channel, error)))
+    }
+
+    // 运行测试，失败时将状态设为 TestsFailed 并保存测试输出的末尾部分
+    async fn run_tests(
+        &self,
+        sha: &str,
+        build_id: uuid::Uuid,
+        repo_path: &Path,
+        build_status: &mut BuildStatus,
+        build_log: &mut BuildLog,
+    ) -> Result<()> {
+        let test_line = self.config.build.test_command_line();
+        info!("Running tests: {}", test_line);
+        build_status.command = Some(match build_status.command.take() {
+            Some(command) => format!("{} && {}", command, test_line),
+            None => test_line,
+        });
+
+        let outcome = self.run_logged(
+            &self.config.build.test_command,
+            &self.config.build.test_command_args(),
+            repo_path,
+            Duration::from_secs(self.config.build.test_timeout),
+            build_id,
+            build_log,
+            OutputCapture::All,
+            &mut Vec::new(),
+        ).await?;
+
+        let error_message = match outcome {
+            CommandOutcome::Success => {
+                info!("Tests passed for commit: {}", sha);
+                return Ok(());
+            }
+            CommandOutcome::Failed(output) => output,
+            CommandOutcome::Error(e) => e,
+            CommandOutcome::Timeout => "Test timeout".to_string(),
+            CommandOutcome::Cancelled => {
+                self.mark_cancelled(build_status);
+                return Ok(());
+            }
+        };
+        error!("Tests failed for commit {}, keeping the current server running", sha);
+        build_status.status = BuildStatusType::TestsFailed;
+        build_status.error_message = Some(error_message);
+        Ok(())
+    }
+
+    // 在仓库目录中执行命令，输出实时写入构建日志并广播
+    // 失败时返回按 capture 收集的最后若干行输出
+    #[allow(clippy::too_many_arguments)]
+    async fn run_logged(
+        &self,
+        program: &str,
+        args: &[String],
+        dir: &Path,
+        timeout_duration: Duration,
+        build_id: uuid::Uuid,
+        build_log: &mut BuildLog,
+        capture: OutputCapture,
+        diagnostics: &mut Vec<Diagnostic>,
+    ) -> Result<CommandOutcome> {
+        let mut child = spawn_in_own_group(
+            TokioCommand::new(program)
+                .args(args)
+                .current_dir(dir)
+                .stdout(Stdio::piped())
+                .stderr(Stdio::piped()),
+        )?;
+
+        let mut group_guard = ProcessGroupGuard(child.id());
 
-        let timeout_duration = Duration::from_secs(self.config.build.build_timeout);
-        
         // 创建输出读取任务
         let stdout = child.stdout.take().unwrap();
         let stderr = child.stderr.take().unwrap();
@@ -185,35 +952,69 @@ impl BuildManager {
         let mut stdout_lines = stdout_reader.lines();
         let mut stderr_lines = stderr_reader.lines();
         
-        let mut error_output = String::new();
+        let mut error_output = VecDeque::new();
+        let max_lines = capture.max_lines();
+        let mut capture_line = |line: &str| {
+            if error_output.len() == max_lines {
+                error_output.pop_front();
+            }
+            error_output.push_back(line.to_string());
+        };
         
-        // 实时读取输出
+        // 实时读取输出，直到 stdout 和 stderr 都关闭
         let output_task = async {
-            loop {
+            let mut stdout_open = true;
+            let mut stderr_open = true;
+
+            while stdout_open || stderr_open {
                 tokio::select! {
-                    line = stdout_lines.next_line() => {
+                    line = stdout_lines.next_line(), if stdout_open => {
                         match line {
-                            Ok(Some(line)) => {
-                                info!("[CARGO] {}", line);
-                            }
-                            Ok(None) => break,
+                            Ok(Some(line)) => match parse_cargo_message(&line) {
+                                // cargo 的 JSON 消息只把诊断文本写入日志，其他消息忽略
+                                Some(CargoMessage::CompilerMessage { diagnostic, rendered }) => {
+                                    if let Some(diagnostic) = diagnostic {
+                                        if diagnostics.len() < MAX_DIAGNOSTICS {
+                                            diagnostics.push(diagnostic);
+                                        }
+                                    }
+                                    for colored in rendered.lines() {
+                                        info!("[BUILD] {}", colored);
+                                        let line = strip_ansi(colored);
+                                        build_log.write_line("stdout", &line).await;
+                                        capture_line(&line);
+                                        let _ = self.build_output.send(BuildLogLine { build_id, line });
+                                    }
+                                }
+                                Some(CargoMessage::Other) => {}
+                                None => {
+                                    info!("[BUILD] {}", line);
+                                    build_log.write_line("stdout", &line).await;
+                                    if capture == OutputCapture::All {
+                                        capture_line(&line);
+                                    }
+                                    let _ = self.build_output.send(BuildLogLine { build_id, line });
+                                }
+                            },
+                            Ok(None) => stdout_open = false,
                             Err(e) => {
                                 warn!("Error reading stdout: {}", e);
-                                break;
+                                stdout_open = false;
                             }
                         }
                     }
-                    line = stderr_lines.next_line() => {
+                    line = stderr_lines.next_line(), if stderr_open => {
                         match line {
                             Ok(Some(line)) => {
-                                warn!("[CARGO] {}", line);
-                                error_output.push_str(&line);
-                                error_output.push('\n');
+                                warn!("[BUILD] {}", line);
+                                build_log.write_line("stderr", &line).await;
+                                capture_line(&line);
+                                let _ = self.build_output.send(BuildLogLine { build_id, line });
                             }
-                            Ok(None) => break,
+                            Ok(None) => stderr_open = false,
                             Err(e) => {
                                 warn!("Error reading stderr: {}", e);
-                                break;
+                                stderr_open = false;
                             }
                         }
                     }
@@ -221,180 +1022,736 @@ impl BuildManager {
             }
         };
         
-        // 等待构建完成或超时
-        let build_result = timeout(timeout_duration, async {
-            tokio::join!(output_task, child.wait())
-        }).await;
+        // 等待命令完成、超时或被取消
+        let cancelled = async {
+            match self.canceller.token() {
+                Some(token) => token.cancelled_owned().await,
+                None => std::future::pending().await,
+            }
+        };
+        let result = tokio::select! {
+            result = timeout(timeout_duration, async { tokio::join!(output_task, child.wait()) }) => Some(result),
+            _ = cancelled => None,
+        };
+        group_guard.0 = None;
+        let Some(result) = result else {
+            kill_process_group(&mut child).await;
+            return Ok(CommandOutcome::Cancelled);
+        };
+        
+        Ok(match result {
+            Ok((_, Ok(exit_status))) if exit_status.success() => CommandOutcome::Success,
+            Ok((_, Ok(_))) => CommandOutcome::Failed(Vec::from(error_output).join("\n")),
+            Ok((_, Err(e))) => CommandOutcome::Error(e.to_string()),
+            Err(_) => {
+                kill_process_group(&mut child).await;
+                CommandOutcome::Timeout
+            }
+        })
+    }
+
+    // 先发送 SIGTERM 让服务保存数据并正常退出，超时后再强制结束
+    // 返回是否正常退出，没有运行中的进程时返回 None
+    pub async fn stop_current_process(&mut self) -> Result<Option<bool>> {
+        let process = self.current_process.lock().unwrap().take();
+        let Some(mut process) = process else {
+            return Ok(None);
+        };
+
+        info!("Stopping current process with PID: {}", process.id());
+        self.console.detach();
+
+        let graceful = if self.terminate(process.id()) {
+            let deadline = tokio::time::Instant::now() + Duration::from_secs(self.config.runtime.stop_timeout);
+            loop {
+                match process.has_exited(self.processes.as_ref()) {
+                    Ok(true) => break true,
+                    Ok(false) if tokio::time::Instant::now() < deadline => {
+                        tokio::time::sleep(Duration::from_millis(200)).await;
+                    }
+                    Ok(false) => {
+                        warn!("Process did not exit within {}s after SIGTERM", self.config.runtime.stop_timeout);
+                        break false;
+                    }
+                    Err(e) => {
+                        warn!("Failed to check process status: {}", e);
+                        break false;
+                    }
+                }
+            }
+        } else {
+            false
+        };
+
+        if graceful {
+            info!("Process exited gracefully");
+        } else {
+            warn!("Killing process with SIGKILL");
+            match process.kill(self.processes.as_ref()) {
+                Ok(_) => {
+                    info!("Process killed");
+                }
+                Err(e) => {
+                    warn!("Failed to kill process: {}", e);
+                }
+            }
+        }
+
+        *self.last_stop_graceful.lock().unwrap() = Some(graceful);
+        Ok(Some(graceful))
+    }
+
+    fn terminate(&self, pid: u32) -> bool {
+        match self.processes.signal(pid, Signal::Terminate) {
+            Ok(()) => true,
+            Err(e) => {
+                warn!("Failed to send SIGTERM to process {}: {}", pid, e);
+                false
+            }
+        }
+    }
+
+    pub fn auto_rollback(&self) -> bool {
+        self.config.runtime.auto_rollback
+    }
+
+    pub fn restart_delay(&self) -> Duration {
+        self.config.runtime.restart_delay
+    }
+
+    pub fn last_stop_graceful(&self) -> Option<bool> {
+        *self.last_stop_graceful.lock().unwrap()
+    }
+
+    // 构建产物，每次构建都会被覆盖
+    fn binary_path(&self) -> PathBuf {
+        self.workspace_path
+            .join(&self.repo.repo_name)
+            .join(self.config.build.binary_path())
+    }
+
+    // 服务实际运行的二进制文件，与构建产物分开，构建期间旧版本可以继续运行
+    fn deployed_binary_path(&self) -> PathBuf {
+        self.workspace_path.join("bin").join(&self.config.build.binary_name)
+    }
+
+    fn staged_binary_path(&self) -> PathBuf {
+        self.workspace_path.join("bin").join(format!("{}.new", self.config.build.binary_name))
+    }
+
+    // 在停止旧进程之前把新版本复制到暂存位置
+    async fn stage_binary(&self, source: &Path) -> Result<()> {
+        let staged = self.staged_binary_path();
+        if let Some(dir) = staged.parent() {
+            fs::create_dir_all(dir).await?;
+        }
+        fs::copy(source, &staged).await?;
+        Ok(())
+    }
+
+    // 用暂存的版本替换运行的二进制文件，rename 是原子操作
+    async fn install_staged_binary(&self) -> Result<()> {
+        fs::rename(self.staged_binary_path(), self.deployed_binary_path()).await?;
+        Ok(())
+    }
+
+    pub fn start_new_process(&mut self) -> Result<u32> {
+        let binary_path = self.deployed_binary_path();
+
+        // 旧版本直接运行构建产物，第一次启动时从构建产物安装
+        if !binary_path.exists() {
+            let built = self.binary_path();
+            if !built.exists() {
+                return Err(anyhow::anyhow!("Binary not found: {:?}", built));
+            }
+            std::fs::create_dir_all(binary_path.parent().unwrap())?;
+            std::fs::copy(&built, &binary_path)?;
+        }
+
+        // 检查和启动在同一把锁内完成，避免两个任务同时启动服务进程
+        let mut current_process = self.current_process.lock().unwrap();
+        if self.check_process(&mut current_process) {
+            return Err(anyhow::anyhow!("Server process is already running"));
+        }
+
+        let working_dir = match &self.config.service.working_dir {
+            Some(dir) => self.workspace_path.join(dir),
+            None => self.workspace_path.clone(),
+        };
+        info!("Starting new process: {:?}", binary_path);
+        info!("Working directory: {:?}", working_dir);
+
+        // 在workspace目录中运行二进制文件
+        // stdio 交给控制台接管，由后台线程持续读取，避免管道阻塞
+        // 检查之后文件或目录仍可能被移走，这里返回错误而不是 panic
+        let binary = binary_path.canonicalize()
+            .with_context(|| format!("Failed to canonicalize binary path {:?}", binary_path))?;
+        let working_dir = working_dir.canonicalize()
+            .with_context(|| format!("Failed to canonicalize working directory {:?}", working_dir))?;
+        let mut child = Command::new(&binary)
+            .current_dir(&working_dir)  // 默认为 workspace，可通过 service.working_dir 修改
+            .envs(&self.config.service.env)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .spawn()
+            .with_context(|| format!("Failed to spawn {:?}", binary))?;
+
+        self.console.attach(&mut child);
+        let pid = child.id();
+        *current_process = Some(ServerProcess::Spawned(child));
+        
+        info!("New process started successfully with PID: {}", pid);
+        
+        Ok(pid)
+    }
+
+    // 进程的启动时间，与 PID 一起保存，用于之后确认 PID 没有被其他进程复用
+    pub fn process_start_time(&self, pid: u32) -> Option<u64> {
+        self.processes.start_time(pid)
+    }
+
+    // 当前跟踪的服务进程 PID
+    pub fn process_id(&self) -> Option<u32> {
+        self.current_process.lock().unwrap().as_ref().map(ServerProcess::id)
+    }
+
+    pub fn is_process_running(&mut self) -> bool {
+        let mut current_process = self.current_process.lock().unwrap();
+        self.check_process(&mut current_process)
+    }
+
+    // 检查进程是否仍在运行，已退出的进程会被清理
+    fn check_process(&self, current_process: &mut Option<ServerProcess>) -> bool {
+        if let Some(process) = current_process {
+            match process.has_exited(self.processes.as_ref()) {
+                Ok(true) => {
+                    // 进程已结束
+                    *current_process = None;
+                    self.console.detach();
+                    false
+                }
+                Ok(false) => {
+                    // 进程仍在运行
+                    true
+                }
+                Err(_) => {
+                    // 检查状态失败，假设进程已结束
+                    *current_process = None;
+                    false
+                }
+            }
+        } else {
+            false
+        }
+    }
+
+    // 工作区所在文件系统的可用空间，MiB；无法获取时为 None
+    pub fn free_space_mb(&self) -> Option<u64> {
+        free_space_bytes(&self.workspace_path).map(|bytes| bytes / (1024 * 1024))
+    }
+
+    // 可用空间低于 min_free_space_mb 时返回说明，避免构建到一半才因磁盘写满报出难以理解的链接错误；
+    // 低于 clean_below_free_space_mb 时先执行 cargo clean 清理累积的 target 目录再检查
+    async fn check_free_space(&self) -> std::result::Result<(), String> {
+        let required = self.config.build.min_free_space_mb;
+        if required == 0 {
+            return Ok(());
+        }
+        let Some(mut free) = self.free_space_mb() else {
+            warn!("Cannot determine free disk space of {:?}, skipping the check", self.workspace_path);
+            return Ok(());
+        };
+        if free < self.config.build.clean_below_free_space_mb && self.is_repo_cloned() {
+            warn!("Only {} MB free in {:?}, running cargo clean", free, self.workspace_path);
+            self.cargo_clean().await;
+            free = self.free_space_mb().unwrap_or(free);
+        }
+        if free < required {
+            return Err(format!(
+                "Not enough free disk space in {}: {} MB free, {} MB required (build.min_free_space_mb)",
+                self.workspace_path.display(),
+                free,
+                required
+            ));
+        }
+        Ok(())
+    }
+
+    // runtime.wait_for_empty 开启且服务正在运行时，等到服务器连续 empty_grace_period 没有玩家，
+    // 或超过 max_wait、收到 POST /api/deploy/now 后返回 true；等待期间构建被取消时返回 false
+    async fn wait_for_empty_server(&mut self, sha: &str, cancel_token: &CancellationToken) -> bool {
+        let runtime = &self.config.runtime;
+        let (Some(port), true) = (self.config.healthcheck.tcp_port, runtime.wait_for_empty) else {
+            return true;
+        };
+        let (grace_period, max_wait) = (runtime.empty_grace_period, runtime.max_wait);
+        if !self.is_process_running() {
+            return true;
+        }
+        let query_interval = self.config.healthcheck.status_interval;
+        let query_timeout = Duration::from_secs(self.config.healthcheck.timeout);
+
+        self.deploy_gate.start(sha);
+        let _waiting = DeployGateGuard(self.deploy_gate.clone());
+        let started = tokio::time::Instant::now();
+        let mut next_query = started;
+        let mut empty_since: Option<tokio::time::Instant> = None;
+        let mut players_online = None;
+        loop {
+            if self.deploy_gate.deploy_now_requested() {
+                info!("Deploying {} now as requested, {} players online", short_sha(sha), players_online.unwrap_or(0));
+                return true;
+            }
+            if started.elapsed() >= max_wait {
+                warn!(
+                    "Players still online after waiting {}, deploying {} anyway",
+                    humantime::format_duration(max_wait),
+                    short_sha(sha)
+                );
+                return true;
+            }
+
+            let now = tokio::time::Instant::now();
+            if now >= next_query {
+                next_query = now + query_interval;
+                // 没有响应的服务器上不会有玩家在玩，按空服处理
+                let online = match query_server_status(port, query_timeout).await {
+                    Ok(info) => info.players_online,
+                    Err(e) => {
+                        debug!("Server List Ping failed while waiting for players to leave: {:#}", e);
+                        0
+                    }
+                };
+                if online > 0 && players_online != Some(online) {
+                    info!("{} players online, waiting before deploying {}", online, short_sha(sha));
+                }
+                players_online = Some(online);
+                empty_since = if online == 0 { Some(empty_since.unwrap_or(now)) } else { None };
+            }
+            if empty_since.is_some_and(|since| since.elapsed() >= grace_period) {
+                info!("Server has had no players for {}, deploying {}", humantime::format_duration(grace_period), short_sha(sha));
+                return true;
+            }
+
+            tokio::select! {
+                _ = tokio::time::sleep(Duration::from_secs(1)) => {}
+                _ = cancel_token.cancelled() => return false,
+            }
+        }
+    }
+
+    // 工作区仓库当前检出的提交，尚未克隆时为 None
+    async fn workspace_head(&self) -> Option<String> {
+        if !self.is_repo_cloned() {
+            return None;
+        }
+        let repo_path = self.workspace_path.join(&self.repo.repo_name);
+        self.git_output(&["rev-parse", "HEAD"], &repo_path).await.ok()
+    }
+
+    // 与上次检出的提交相比 Cargo.lock 或工具链文件有变化时先执行 cargo clean，避免旧的编译结果导致奇怪的失败；
+    // 返回本次是否为完整构建
+    async fn clean_if_dependencies_changed(&self, previous_head: Option<&str>, sha: &str) -> bool {
+        let repo_path = self.workspace_path.join(&self.repo.repo_name);
+        if !repo_path.join("target").exists() {
+            return true;
+        }
+        let Some(previous_head) = previous_head.filter(|head| !head.starts_with(sha)) else {
+            return false;
+        };
+
+        let diff = self
+            .git_output(
+                &["diff", "--name-only", previous_head, sha, "--", "Cargo.lock", "rust-toolchain.toml", "rust-toolchain"],
+                &repo_path,
+            )
+            .await;
+        match diff {
+            Ok(changed) if changed.is_empty() => return false,
+            Ok(changed) => info!(
+                "{} changed since {}, running cargo clean before building",
+                changed.lines().collect::<Vec<_>>().join(", "),
+                short_sha(previous_head)
+            ),
+            // 浅克隆中可能没有上次的提交，无法比较时按依赖已变化处理
+            Err(e) => warn!("Cannot compare dependencies with {}: {:#}, running cargo clean", short_sha(previous_head), e),
+        }
+        self.cargo_clean().await;
+        true
+    }
+
+    async fn cargo_clean(&self) {
+        let repo_path = self.workspace_path.join(&self.repo.repo_name);
+        let output = TokioCommand::new("cargo")
+            .arg("clean")
+            .current_dir(&repo_path)
+            .kill_on_drop(true)
+            .output();
+        match timeout(self.config.build.build_timeout, output).await {
+            Ok(Ok(output)) if output.status.success() => info!("cargo clean finished"),
+            Ok(Ok(output)) => warn!("cargo clean failed: {}", String::from_utf8_lossy(&output.stderr).trim()),
+            Ok(Err(e)) => warn!("Failed to run cargo clean: {}", e),
+            Err(_) => warn!("cargo clean timed out"),
+        }
+    }
+
+    pub fn is_repo_cloned(&self) -> bool {
+        let repo_path = self.workspace_path.join(&self.repo.repo_name);
+        repo_path.exists() && repo_path.join(".git").exists()
+    }
+
+    pub fn is_binary_built(&self) -> bool {
+        self.deployed_binary_path().exists() || self.binary_path().exists()
+    }
+
+    pub async fn restart_service(&mut self, commit: &GitHubCommit, build_id: uuid::Uuid) -> Result<RestartResult> {
+        let _build_guard = self.lock_build().await;
+        let mut build_status = BuildStatus::for_commit(build_id, commit);
+        let cancel_token = self.canceller.start(build_id, &commit.sha);
+        let _active_build = ActiveBuildGuard(self.canceller.clone());
+
+        if let Err(e) = self.check_free_space().await {
+            error!("{} (commit {})", e, commit.sha);
+            build_status.status = BuildStatusType::Failed;
+            build_status.error_message = Some(e);
+            build_status.finish();
+            return Ok(RestartResult { build_status, pid: None, rolled_back_to: None });
+        }
+
+        // 更新代码，构建和测试期间旧版本服务继续运行
+        let previous_head = self.workspace_head().await;
+        let mut recovery = Vec::new();
+        if let Err(e) = self.sync_workspace(&commit.sha, &mut recovery).await {
+            build_status.status = BuildStatusType::Failed;
+            build_status.error_message = Some(if recovery.is_empty() {
+                format!("{:#}", e)
+            } else {
+                format!("{:#}\nRecovery attempted:\n- {}", e, recovery.join("\n- "))
+            });
+            build_status.finish();
+            return Ok(RestartResult { build_status, pid: None, rolled_back_to: None });
+        }
+        if cancel_token.is_cancelled() {
+            self.mark_cancelled(&mut build_status);
+            build_status.finish();
+            return Ok(RestartResult { build_status, pid: None, rolled_back_to: None });
+        }
+
+        let clean_build = self.clean_if_dependencies_changed(previous_head.as_deref(), &commit.sha).await;
+
+        // 构建项目
+        let mut built = self.build_project(&commit.sha, build_id).await?;
+        built.clean_build = Some(clean_build);
+        built.copy_commit_info(&build_status);
+        build_status = built;
         
-        match build_result {
-            Ok((_, Ok(exit_status))) => {
-                if exit_status.success() {
-                    info!("Build successful for commit: {}", commit.sha);
-                    build_status.status = BuildStatusType::Success;
+        if build_status.status != BuildStatusType::Success {
+            return Ok(RestartResult { build_status, pid: None, rolled_back_to: None });
+        }
+
+        // 构建成功但找不到预期的二进制文件，通常是 binary_name 或 binary_path 配置错误
+        if !self.binary_path().exists() {
+            let binary_path = self.config.build.binary_path();
+            error!("Build succeeded but binary {:?} was not produced", binary_path);
+            build_status.status = BuildStatusType::Failed;
+            build_status.error_message = Some(format!(
+                "Build succeeded but expected binary '{}' is missing — check binary_name / binary_path",
+                binary_path.display()
+            ));
+            return Ok(RestartResult { build_status, pid: None, rolled_back_to: None });
+        }
+
+        // 有玩家在线时推迟替换，等待期间仍然可以取消
+        if !self.wait_for_empty_server(&commit.sha, &cancel_token).await {
+            self.mark_cancelled(&mut build_status);
+            return Ok(RestartResult { build_status, pid: None, rolled_back_to: None });
+        }
+
+        // 接下来会停止旧进程，之后不再接受取消
+        self.canceller.finish();
+
+        // 先暂存新版本，旧进程一直运行到这里才停止，停机时间只有 restart_delay 加上启动时间
+        if let Err(e) = self.stage_binary(&self.binary_path()).await {
+            build_status.status = BuildStatusType::Failed;
+            build_status.error_message = Some(format!("Failed to stage new binary: {}", e));
+            build_status.finish();
+            return Ok(RestartResult { build_status, pid: None, rolled_back_to: None });
+        }
+
+        info!("Stopping current server to swap in commit {}", commit.sha);
+        self.stop_current_process().await?;
+
+        // 等待一段时间
+        tokio::time::sleep(self.config.runtime.restart_delay).await;
+
+        // 准备workspace配置
+        if let Err(e) = self.prepare_workspace_config().await {
+            warn!("Failed to prepare workspace config: {}", e);
+        }
+
+        // 启动新进程，启动失败或在观察期内退出时回滚到之前的可用版本
+        let started = match self.install_staged_binary().await {
+            Ok(()) => self.start_new_process(),
+            Err(e) => Err(e.context("Failed to install new binary")),
+        };
+        let start_error = match started {
+            Ok(pid) => {
+                info!("Service started with PID: {}", pid);
+                if !self.survives_grace_period().await {
+                    format!(
+                        "New process exited within {}s of starting",
+                        self.config.runtime.startup_grace_period
+                    )
+                } else if let Err(e) = self.wait_until_ready().await {
+                    format!("New process is not responding: {:#}", e)
                 } else {
-                    error!("Build failed for commit {}", commit.sha);
-                    if !error_output.is_empty() {
-                        error!("Build errors:\n{}", error_output);
+                    build_status.finish();
+                    if let Err(e) = self.save_artifact(&commit.sha).await {
+                        warn!("Failed to save build artifact: {}", e);
                     }
-                    build_status.status = BuildStatusType::Failed;
-                    build_status.error_message = Some(error_output);
+                    return Ok(RestartResult { build_status, pid: Some(pid), rolled_back_to: None });
                 }
             }
-            Ok((_, Err(e))) => {
-                error!("Build process error for commit {}: {}", commit.sha, e);
+            Err(e) => format!("Failed to start new process: {}", e),
+        };
+
+        error!("{} (commit {})", start_error, commit.sha);
+        build_status.finish();
+        match self.rollback(&commit.sha).await {
+            Ok(Some((short_sha, pid))) => {
+                warn!("Rolled back to previous build {} with PID: {}", short_sha, pid);
+                build_status.status = BuildStatusType::RolledBack;
+                build_status.error_message = Some(format!("{}; rolled back to {}", start_error, short_sha));
+                Ok(RestartResult { build_status, pid: Some(pid), rolled_back_to: Some(short_sha) })
+            }
+            Ok(None) => {
                 build_status.status = BuildStatusType::Failed;
-                build_status.error_message = Some(e.to_string());
+                build_status.error_message = Some(start_error);
+                Ok(RestartResult { build_status, pid: None, rolled_back_to: None })
             }
-            Err(_) => {
-                error!("Build timeout for commit: {}", commit.sha);
+            Err(e) => {
+                error!("Rollback failed: {}", e);
                 build_status.status = BuildStatusType::Failed;
-                build_status.error_message = Some("Build timeout".to_string());
-                
-                // 尝试杀死超时的进程
-                let _ = child.kill().await;
+                build_status.error_message = Some(format!("{}; rollback failed: {}", start_error, e));
+                Ok(RestartResult { build_status, pid: None, rolled_back_to: None })
             }
         }
+    }
+
+    // 重新检出并构建之前成功的提交，使构建产物恢复为可用版本；服务未运行时启动它
+    pub async fn rebuild_commit(&mut self, sha: &str, build_id: uuid::Uuid) -> Result<(BuildStatus, Option<u32>)> {
+        let _build_guard = self.lock_build().await;
+        if let Err(e) = self.check_free_space().await {
+            error!("{} (commit {})", e, sha);
+            let mut build_status = BuildStatus::new(build_id, sha.to_string());
+            build_status.status = BuildStatusType::Failed;
+            build_status.error_message = Some(e);
+            build_status.finish();
+            return Ok((build_status, None));
+        }
+        self.checkout_commit(sha).await?;
+
+        let mut build_status = self.build_project(sha, build_id).await?;
+        if build_status.status != BuildStatusType::Success {
+            return Ok((build_status, None));
+        }
+
+        // 正在运行的进程不受影响，之后的重启会使用恢复的版本
+        self.stage_binary(&self.binary_path()).await?;
+        self.install_staged_binary().await?;
+        if self.is_process_running() {
+            return Ok((build_status, None));
+        }
 
-        build_status.finished_at = Some(chrono::Utc::now());
-        Ok(build_status)
+        match self.start_new_process() {
+            Ok(pid) => Ok((build_status, Some(pid))),
+            Err(e) => {
+                build_status.status = BuildStatusType::Failed;
+                build_status.error_message = Some(format!("Failed to start process: {}", e));
+                Ok((build_status, None))
+            }
+        }
     }
 
-    pub fn stop_current_process(&mut self) -> Result<()> {
-        if let Some(mut process) = self.current_process.take() {
-            info!("Stopping current process");
-            match process.kill() {
-                Ok(_) => {
-                    let _ = process.wait();
-                    info!("Process stopped successfully");
-                }
-                Err(e) => {
-                    warn!("Failed to kill process: {}", e);
-                }
+    // 在观察期内每秒检查一次新进程是否仍在运行
+    async fn survives_grace_period(&mut self) -> bool {
+        let deadline = tokio::time::Instant::now() + Duration::from_secs(self.config.runtime.startup_grace_period);
+        while tokio::time::Instant::now() < deadline {
+            if !self.is_process_running() {
+                return false;
             }
+            tokio::time::sleep(Duration::from_secs(1)).await;
+        }
+        self.is_process_running()
+    }
+
+    // 配置了 [healthcheck] 时等待服务端口响应后才认为启动成功
+    async fn wait_until_ready(&mut self) -> Result<()> {
+        let checker = HealthChecker::new(self.config.healthcheck.clone(), 0);
+        checker.wait_until_ready(|| self.is_process_running()).await?;
+        if self.config.healthcheck.is_enabled() {
+            info!("Service is accepting connections");
         }
         Ok(())
     }
 
-    pub fn start_new_process(&mut self) -> Result<u32> {
-        let binary_path = self.workspace_path
-            .join(&self.config.github.repo_name)
-            .join("target")
-            .join("release")
-            .join(&self.config.build.binary_name);
+    fn artifacts_dir(&self) -> PathBuf {
+        self.workspace_path.join("artifacts")
+    }
 
-        if !binary_path.exists() {
-            return Err(anyhow::anyhow!("Binary not found: {:?}", binary_path));
-        }
+    fn artifact_prefix(&self) -> String {
+        format!("{}.", self.config.build.binary_name)
+    }
 
-        info!("Starting new process: {:?}", binary_path);
-        info!("Working directory: {:?}", self.workspace_path);
+    // 将通过观察期的构建产物保存为可用版本，并清理超出数量的旧版本
+    async fn save_artifact(&self, sha: &str) -> Result<()> {
+        if self.config.build.max_artifacts == 0 {
+            return Ok(());
+        }
 
-        // 在workspace目录中运行二进制文件
-        // 让子进程继承父进程的stdio，避免终端状态问题
-        let child = Command::new(&binary_path.canonicalize().unwrap())
-            .current_dir(&self.workspace_path.canonicalize().unwrap())  // 设置工作目录为workspace
-            .stdin(Stdio::null())   // 禁用stdin
-            .stdout(Stdio::null()) // 继承stdout，避免管道阻塞
-            .stderr(Stdio::null()) // 继承stderr，避免管道阻塞
-            .spawn()?;
+        let dir = self.artifacts_dir();
+        fs::create_dir_all(&dir).await?;
+        let artifact = dir.join(format!("{}{}", self.artifact_prefix(), short_sha(sha)));
+        fs::copy(self.deployed_binary_path(), &artifact).await?;
+        info!("Saved build artifact {:?}", artifact);
 
-        let pid = child.id();
-        self.current_process = Some(child);
-        
-        info!("New process started successfully in workspace with PID: {}", pid);
-        
-        Ok(pid)
+        self.trim_artifacts().await?;
+        Ok(())
     }
 
-    pub fn is_process_running(&mut self) -> bool {
-        if let Some(process) = &mut self.current_process {
-            match process.try_wait() {
-                Ok(Some(_)) => {
-                    // 进程已结束
-                    self.current_process = None;
-                    false
-                }
-                Ok(None) => {
-                    // 进程仍在运行
-                    true
-                }
-                Err(_) => {
-                    // 检查状态失败，假设进程已结束
-                    self.current_process = None;
-                    false
-                }
+    // 删除超出 max_artifacts 的旧版本，返回释放的字节数
+    async fn trim_artifacts(&self) -> Result<u64> {
+        let mut reclaimed = 0;
+        for (_, path) in self.list_artifacts().await?.into_iter().skip(self.config.build.max_artifacts) {
+            let size = fs::metadata(&path).await.map(|metadata| metadata.len()).unwrap_or(0);
+            match fs::remove_file(&path).await {
+                Ok(()) => reclaimed += size,
+                Err(e) => warn!("Failed to remove old artifact {:?}: {}", path, e),
             }
-        } else {
-            false
         }
+        Ok(reclaimed)
     }
 
-    pub fn is_repo_cloned(&self) -> bool {
-        let repo_path = self.workspace_path.join(&self.config.github.repo_name);
-        repo_path.exists() && repo_path.join(".git").exists()
+    // 定期清理工作区：过期的构建日志、超出数量的构建产物和过大的 target 目录；
+    // 持有构建锁，不会删掉正在构建的文件
+    pub async fn run_maintenance(&self) -> Result<MaintenanceReport> {
+        let _build_guard = self.lock_build().await;
+        let maintenance = &self.config.maintenance;
+        let mut reclaimed_bytes = 0;
+
+        if let Some(days) = maintenance.log_max_age_days {
+            reclaimed_bytes += self.prune_build_logs(Duration::from_secs(days * 24 * 3600)).await?;
+        }
+        reclaimed_bytes += self.trim_artifacts().await?;
+        if let Some(max_mb) = maintenance.max_target_size_mb {
+            reclaimed_bytes += self.clean_target(max_mb * 1024 * 1024).await?;
+        }
+
+        Ok(MaintenanceReport {
+            reclaimed_bytes,
+            workspace_size_bytes: dir_size(&self.workspace_path).await,
+        })
     }
 
-    pub fn is_binary_built(&self) -> bool {
-        let binary_path = self.workspace_path
-            .join(&self.config.github.repo_name)
-            .join("target")
-            .join("release")
-            .join(&self.config.build.binary_name);
-        binary_path.exists()
-    }
-
-    pub async fn restart_service(&mut self, commit: &GitHubCommit) -> Result<(BuildStatus, Option<u32>)> {
-        let mut build_status = BuildStatus {
-            id: uuid::Uuid::new_v4(),
-            commit_sha: commit.sha.clone(),
-            status: BuildStatusType::Building,
-            started_at: chrono::Utc::now(),
-            finished_at: None,
-            error_message: None,
-        };
+    // 删除修改时间早于 max_age 的构建日志，返回释放的字节数
+    async fn prune_build_logs(&self, max_age: Duration) -> Result<u64> {
+        let dir = self.workspace_path.join("build-logs");
+        if !dir.exists() {
+            return Ok(0);
+        }
 
-        // 停止当前进程
-        self.stop_current_process()?;
+        let mut reclaimed = 0;
+        let mut entries = fs::read_dir(&dir).await?;
+        while let Some(entry) = entries.next_entry().await? {
+            let metadata = entry.metadata().await?;
+            let expired = metadata.modified()?.elapsed().is_ok_and(|age| age > max_age);
+            if !metadata.is_file() || !expired {
+                continue;
+            }
+            match fs::remove_file(entry.path()).await {
+                Ok(()) => reclaimed += metadata.len(),
+                Err(e) => warn!("Failed to remove old build log {:?}: {}", entry.path(), e),
+            }
+        }
+        Ok(reclaimed)
+    }
 
-        // 等待一段时间
-        tokio::time::sleep(Duration::from_secs(self.config.runtime.restart_delay)).await;
+    // target 目录超过 max_bytes 时按 target_cleanup 清理，返回释放的字节数
+    async fn clean_target(&self, max_bytes: u64) -> Result<u64> {
+        let repo_path = self.workspace_path.join(&self.repo.repo_name);
+        let target = repo_path.join("target");
+        let before = dir_size(&target).await;
+        if before <= max_bytes {
+            return Ok(0);
+        }
 
-        // 更新代码
-        if let Err(e) = self.clone_or_update_repo().await {
-            build_status.status = BuildStatusType::Failed;
-            build_status.error_message = Some(format!("Failed to update repository: {}", e));
-            build_status.finished_at = Some(chrono::Utc::now());
-            return Ok((build_status, None));
+        info!("Target directory {:?} uses {} bytes, cleaning it", target, before);
+        match self.config.maintenance.target_cleanup {
+            TargetCleanup::All => fs::remove_dir_all(&target).await?,
+            TargetCleanup::WorkspaceCrates => {
+                let mut args = vec!["clean".to_string()];
+                for package in workspace_packages(&repo_path).await? {
+                    args.extend(["-p".to_string(), package]);
+                }
+                let output = TokioCommand::new("cargo")
+                    .args(&args)
+                    .current_dir(&repo_path)
+                    .kill_on_drop(true)
+                    .output();
+                let output = timeout(self.config.build.build_timeout, output)
+                    .await
+                    .context("cargo clean timed out")??;
+                if !output.status.success() {
+                    anyhow::bail!("cargo clean failed: {}", String::from_utf8_lossy(&output.stderr).trim());
+                }
+            }
         }
+        Ok(before.saturating_sub(dir_size(&target).await))
+    }
 
-        // 构建项目
-        build_status = self.build_project(commit).await?;
-        
-        if build_status.status != BuildStatusType::Success {
-            return Ok((build_status, None));
+    // 已保存的可用版本（短 SHA 和路径），最新的在前面
+    async fn list_artifacts(&self) -> Result<Vec<(String, PathBuf)>> {
+        let dir = self.artifacts_dir();
+        if !dir.exists() {
+            return Ok(Vec::new());
         }
 
-        // 准备workspace配置
-        if let Err(e) = self.prepare_workspace_config().await {
-            warn!("Failed to prepare workspace config: {}", e);
+        let prefix = self.artifact_prefix();
+        let mut artifacts = Vec::new();
+        let mut entries = fs::read_dir(&dir).await?;
+        while let Some(entry) = entries.next_entry().await? {
+            let name = entry.file_name().to_string_lossy().to_string();
+            let Some(sha) = name.strip_prefix(&prefix) else {
+                continue;
+            };
+            let modified = entry.metadata().await?.modified()?;
+            artifacts.push((modified, sha.to_string(), entry.path()));
         }
+        artifacts.sort_by_key(|(modified, _, _)| std::cmp::Reverse(*modified));
 
-        // 启动新进程
-        let pid = match self.start_new_process() {
-            Ok(pid) => {
-                build_status.finished_at = Some(chrono::Utc::now());
-                info!("Service started with PID: {}", pid);
-                Some(pid)
-            }
-            Err(e) => {
-                build_status.status = BuildStatusType::Failed;
-                build_status.error_message = Some(format!("Failed to start new process: {}", e));
-                build_status.finished_at = Some(chrono::Utc::now());
-                None
-            }
+        Ok(artifacts.into_iter().map(|(_, sha, path)| (sha, path)).collect())
+    }
+
+    // 恢复最近的可用版本（不包括启动失败的提交）并启动，没有可用版本时返回 None
+    async fn rollback(&mut self, failed_sha: &str) -> Result<Option<(String, u32)>> {
+        let failed = short_sha(failed_sha);
+        let Some((sha, artifact)) = self.list_artifacts().await?
+            .into_iter()
+            .find(|(sha, _)| sha != failed)
+        else {
+            warn!("No previous build artifact available for rollback");
+            return Ok(None);
         };
 
-        Ok((build_status, pid))
+        // 停止可能仍在运行的新进程，再换回之前的版本，之后的自动重启也会使用它
+        self.stage_binary(&artifact).await?;
+        self.stop_current_process().await?;
+        self.install_staged_binary().await?;
+        info!("Restored {:?} from {:?}", self.deployed_binary_path(), artifact);
+
+        let pid = self.start_new_process()?;
+        Ok(Some((sha, pid)))
     }
 
     pub async fn prepare_workspace_config(&self) -> Result<()> {
@@ -404,85 +1761,438 @@ impl BuildManager {
         if !workspace_config_path.exists() {
             info!("Creating config.toml in workspace");
             
-            // 从当前目录复制config.toml到workspace
-            if let Ok(config_content) = tokio::fs::read_to_string("config.toml").await {
+            // 复制启动时使用的配置文件到workspace
+            if let Ok(config_content) = tokio::fs::read_to_string(&self.config.source_path).await {
                 tokio::fs::write(&workspace_config_path, config_content).await?;
-                info!("Config file copied to workspace: {:?}", workspace_config_path);
+                info!("Config file {:?} copied to workspace: {:?}", self.config.source_path, workspace_config_path);
             } else {
-                warn!("Could not find config.toml in current directory, process may need manual configuration");
+                warn!("Could not read config file {:?}, process may need manual configuration", self.config.source_path);
             }
         }
         
         Ok(())
     }
 
-    // 检查并清理可能存在的旧进程
-    pub async fn cleanup_old_process(&self, pid: u32) -> Result<()> {
-        info!("Checking for old process with PID: {}", pid);
-        
-        // 检查进程是否还存在
-        let output = TokioCommand::new("ps")
-            .args(&["-p", &pid.to_string()])
-            .output()
-            .await;
-            
-        match output {
-            Ok(output) if output.status.success() => {
-                // 进程还存在，尝试杀死它
-                warn!("Found running process with PID {}, attempting to kill it", pid);
-                
-                let kill_output = TokioCommand::new("kill")
-                    .args(&["-15", &pid.to_string()]) // 使用SIGTERM先尝试优雅关闭
-                    .output()
-                    .await;
-                    
-                match kill_output {
-                    Ok(kill_output) if kill_output.status.success() => {
-                        info!("Successfully sent SIGTERM to process {}", pid);
-                        
-                        // 等待3秒后检查进程是否还存在
-                        tokio::time::sleep(Duration::from_secs(3)).await;
-                        
-                        let check_output = TokioCommand::new("ps")
-                            .args(&["-p", &pid.to_string()])
-                            .output()
-                            .await;
-                            
-                        if let Ok(check_output) = check_output {
-                            if check_output.status.success() {
-                                // 进程仍然存在，使用SIGKILL强制杀死
-                                warn!("Process {} still running, using SIGKILL", pid);
-                                let _ = TokioCommand::new("kill")
-                                    .args(&["-9", &pid.to_string()])
-                                    .output()
-                                    .await;
-                            }
-                        }
-                    }
-                    _ => {
-                        warn!("Failed to kill process {}", pid);
-                    }
-                }
+    // 结束上次运行留下的服务进程；每次发送信号前都确认 PID 仍属于部署的二进制文件，避免误杀复用了该 PID 的进程
+    async fn cleanup_old_process(&self, pid: u32, started_at: Option<u64>) -> Result<()> {
+        let deployed = self.deployed_binary_path();
+        if !match_process(self.processes.as_ref(), pid, started_at, &deployed).is_ours() {
+            return Ok(());
+        }
+
+        warn!("Stopping old server process with PID {}", pid);
+        if let Err(e) = self.processes.signal(pid, Signal::Terminate) {
+            warn!("Failed to send SIGTERM to process {}: {}", pid, e);
+        }
+
+        let deadline = tokio::time::Instant::now() + Duration::from_secs(self.config.runtime.stop_timeout);
+        while tokio::time::Instant::now() < deadline {
+            if !self.processes.is_alive(pid) {
+                info!("Old process {} exited", pid);
+                return Ok(());
             }
-            _ => {
-                info!("No process found with PID {}", pid);
+            tokio::time::sleep(Duration::from_millis(200)).await;
+        }
+
+        if match_process(self.processes.as_ref(), pid, started_at, &deployed).is_ours() {
+            warn!("Process {} still running after {}s, using SIGKILL", pid, self.config.runtime.stop_timeout);
+            if let Err(e) = self.processes.signal(pid, Signal::Kill) {
+                warn!("Failed to kill process {}: {}", pid, e);
             }
         }
-        
+
         Ok(())
     }
 
-    // 在启动前检查并清理旧进程
-    pub async fn prepare_for_start(&self, storage: &Arc<RwLock<crate::storage::Storage>>) -> Result<()> {
-        let current_status = {
-            let storage_guard = storage.read().await;
-            storage_guard.get_system_status()
+    // 在启动前检查上次的服务进程：仍在运行当前版本时直接接管，版本过旧时才结束它
+    pub async fn prepare_for_start(&self, storage: &crate::storage::SharedStore) -> Result<()> {
+        let mut status = storage.read().await.get_system_status();
+        let Some(old_pid) = status.process_pid else {
+            return Ok(());
         };
-        
-        if let Some(old_pid) = current_status.process_pid {
-            self.cleanup_old_process(old_pid).await?;
+
+        match match_process(self.processes.as_ref(), old_pid, status.process_started_at, &self.deployed_binary_path()) {
+            ProcessMatch::Current => {
+                info!("Adopting running server process with PID {}", old_pid);
+                *self.current_process.lock().unwrap() = Some(ServerProcess::Adopted(old_pid));
+                // 旧数据没有记录启动时间，接管时补上
+                let started_at = status.process_started_at.or_else(|| self.processes.start_time(old_pid));
+                if !status.is_running || status.process_started_at != started_at {
+                    status.is_running = true;
+                    status.process_started_at = started_at;
+                    storage.write().await.update_system_status(status).await?;
+                }
+            }
+            ProcessMatch::Stale => {
+                warn!("Process {} is running an outdated binary, stopping it", old_pid);
+                self.cleanup_old_process(old_pid, status.process_started_at).await?;
+            }
+            ProcessMatch::Foreign => {
+                warn!("PID {} now belongs to another program or was started by someone else, leaving it alone", old_pid);
+            }
+            ProcessMatch::Gone => info!("No process found with PID {}", old_pid),
+            ProcessMatch::Unknown => {
+                warn!("Cannot determine the executable of process {}, leaving it alone", old_pid);
+            }
         }
         
         Ok(())
     }
 }
+
+// 仓库工作区中各 crate 的名称，来自 cargo metadata
+async fn workspace_packages(repo_path: &Path) -> Result<Vec<String>> {
+    let output = TokioCommand::new("cargo")
+        .args(["metadata", "--no-deps", "--format-version", "1"])
+        .current_dir(repo_path)
+        .kill_on_drop(true)
+        .output()
+        .await
+        .context("Failed to run cargo metadata")?;
+    if !output.status.success() {
+        anyhow::bail!("cargo metadata failed: {}", String::from_utf8_lossy(&output.stderr).trim());
+    }
+
+    let metadata: serde_json::Value = serde_json::from_slice(&output.stdout).context("Invalid cargo metadata output")?;
+    Ok(metadata["packages"]
+        .as_array()
+        .into_iter()
+        .flatten()
+        .filter_map(|package| package["name"].as_str().map(str::to_string))
+        .collect())
+}
+
+// 目录中所有文件的总大小，不跟随符号链接；无法读取的条目按 0 计算
+async fn dir_size(path: &Path) -> u64 {
+    let path = path.to_path_buf();
+    tokio::task::spawn_blocking(move || dir_size_blocking(&path)).await.unwrap_or(0)
+}
+
+fn dir_size_blocking(path: &Path) -> u64 {
+    let Ok(entries) = std::fs::read_dir(path) else {
+        return 0;
+    };
+    entries
+        .flatten()
+        .map(|entry| match entry.file_type() {
+            Ok(file_type) if file_type.is_dir() => dir_size_blocking(&entry.path()),
+            Ok(file_type) if file_type.is_file() => entry.metadata().map(|metadata| metadata.len()).unwrap_or(0),
+            _ => 0,
+        })
+        .sum()
+}
+
+// path 所在文件系统中非特权用户可用的空间，字节
+#[cfg(unix)]
+fn free_space_bytes(path: &Path) -> Option<u64> {
+    let stat = nix::sys::statvfs::statvfs(path).ok()?;
+    Some(stat.blocks_available() as u64 * stat.fragment_size() as u64)
+}
+
+#[cfg(not(unix))]
+fn free_space_bytes(_path: &Path) -> Option<u64> {
+    None
+}
+
+// rust-toolchain.toml 中声明的工具链
+struct PinnedToolchain {
+    channel: String,
+    components: Vec<String>,
+}
+
+async fn read_toolchain_file(repo_path: &Path) -> Option<PinnedToolchain> {
+    #[derive(serde::Deserialize)]
+    struct ToolchainFile {
+        toolchain: ToolchainSection,
+    }
+
+    #[derive(serde::Deserialize)]
+    struct ToolchainSection {
+        channel: Option<String>,
+        #[serde(default)]
+        components: Vec<String>,
+    }
+
+    for name in ["rust-toolchain.toml", "rust-toolchain"] {
+        let Ok(content) = fs::read_to_string(repo_path.join(name)).await else {
+            continue;
+        };
+        // 旧式的 rust-toolchain 文件可以只包含一行工具链名称
+        let toolchain = match toml::from_str::<ToolchainFile>(&content) {
+            Ok(file) => file.toolchain.channel.map(|channel| PinnedToolchain {
+                channel,
+                components: file.toolchain.components,
+            }),
+            Err(_) => Some(content.trim().to_string())
+                .filter(|channel| !channel.is_empty() && !channel.contains(char::is_whitespace))
+                .map(|channel| PinnedToolchain { channel, components: Vec::new() }),
+        };
+        if toolchain.is_none() {
+            warn!("Cannot read the toolchain channel from {}", name);
+        }
+        return toolchain;
+    }
+    None
+}
+
+// rustup toolchain list 的一行（如 "nightly-2024-05-01-x86_64-unknown-linux-gnu (default)"）是否为该工具链；
+// 名称后面是主机三元组，而 nightly 后面接数字时是另一个带日期的工具链
+fn toolchain_matches(line: &str, channel: &str) -> bool {
+    let name = line.split_whitespace().next().unwrap_or_default();
+    name == channel
+        || name
+            .strip_prefix(channel)
+            .and_then(|rest| rest.strip_prefix('-'))
+            .is_some_and(|host| !host.starts_with(|c: char| c.is_ascii_digit()))
+}
+
+// 从 git submodule update 的输出中找出失败的子模块路径，例如
+// "fatal: clone of '...' into submodule path '/ws/Pumpkin/proto' failed" 或
+// "fatal: Fetched in submodule path 'proto', but it did not contain abc123"
+fn failed_submodule(output: &[String]) -> Option<String> {
+    output.iter().rev().find_map(|line| {
+        let (_, rest) = line.split_once("submodule path '")?;
+        let (path, _) = rest.split_once('\'')?;
+        Some(path.to_string())
+    })
+}
+
+// GIT_SSH_COMMAND 由 shell 解析，路径用单引号包起来
+fn shell_quote(value: &str) -> String {
+    format!("'{}'", value.replace('\'', r"'\''"))
+}
+
+// git 的 Basic 认证请求头需要 base64 编码
+fn base64_encode(input: &[u8]) -> String {
+    const ALPHABET: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+    let mut output = String::with_capacity(input.len().div_ceil(3) * 4);
+    for chunk in input.chunks(3) {
+        let bytes = [chunk[0], *chunk.get(1).unwrap_or(&0), *chunk.get(2).unwrap_or(&0)];
+        let n = (bytes[0] as u32) << 16 | (bytes[1] as u32) << 8 | bytes[2] as u32;
+        for i in 0..4 {
+            if i <= chunk.len() {
+                output.push(ALPHABET[(n >> (18 - 6 * i) & 0x3f) as usize] as char);
+            } else {
+                output.push('=');
+            }
+        }
+    }
+    output
+}
+
+// 在独立的进程组中启动命令，超时时可以连同 rustc、构建脚本等子进程一起结束
+fn spawn_in_own_group(command: &mut TokioCommand) -> std::io::Result<tokio::process::Child> {
+    #[cfg(unix)]
+    command.process_group(0);
+    // 监控程序退出时任务被取消，不留下仍在运行的构建
+    command.kill_on_drop(true);
+    command.spawn()
+}
+
+// 部署提前返回时清除可以取消的构建
+struct ActiveBuildGuard(BuildCanceller);
+
+impl Drop for ActiveBuildGuard {
+    fn drop(&mut self) {
+        self.0.finish();
+    }
+}
+
+// 收到 Ctrl+C 或 SIGTERM 时构建任务被取消，kill_on_drop 只结束 cargo 本身，
+// 由它在析构时结束整个进程组，不留下仍在运行的 rustc；命令正常结束后置为 None
+struct ProcessGroupGuard(Option<u32>);
+
+impl Drop for ProcessGroupGuard {
+    fn drop(&mut self) {
+        #[cfg(unix)]
+        if let Some(pid) = self.0 {
+            use nix::sys::signal::{killpg, Signal};
+            use nix::unistd::Pid;
+
+            let _ = killpg(Pid::from_raw(pid as i32), Signal::SIGKILL);
+        }
+    }
+}
+
+// 强制结束整个进程组，避免残留的子进程继续占用 CPU 和 target 目录锁
+async fn kill_process_group(child: &mut tokio::process::Child) {
+    #[cfg(unix)]
+    if let Some(pid) = child.id() {
+        use nix::sys::signal::{killpg, Signal};
+        use nix::unistd::Pid;
+
+        if let Err(e) = killpg(Pid::from_raw(pid as i32), Signal::SIGKILL) {
+            warn!("Failed to kill process group {}: {}", pid, e);
+        }
+    }
+    let _ = child.kill().await;
+}
+
+// 编译错误不是偶发问题，不需要重试
+fn is_compile_error(output: &str, diagnostics: &[Diagnostic]) -> bool {
+    diagnostics.iter().any(|d| d.level == "error")
+        || output.contains("error[")
+        || output.contains("could not compile")
+}
+
+// 保存到 error_message 的输出行数，完整输出见构建日志
+const ERROR_MESSAGE_LINES: usize = 50;
+const TEST_OUTPUT_LINES: usize = 200;
+
+// 命令失败时收集哪些输出作为错误信息
+#[derive(Clone, Copy, PartialEq)]
+enum OutputCapture {
+    // 只收集 stderr，用于编译错误
+    Stderr,
+    // 同时收集 stdout，测试失败的详情输出在 stdout 中
+    All,
+}
+
+impl OutputCapture {
+    fn max_lines(self) -> usize {
+        match self {
+            OutputCapture::Stderr => ERROR_MESSAGE_LINES,
+            OutputCapture::All => TEST_OUTPUT_LINES,
+        }
+    }
+}
+
+enum CommandOutcome {
+    Success,
+    // 非零退出，附带收集到的输出
+    Failed(String),
+    Error(String),
+    Timeout,
+    // 通过 POST /api/builds/current/cancel 取消，进程组已被结束
+    Cancelled,
+}
+
+// 单次构建的日志文件，超过大小上限后不再写入
+struct BuildLog {
+    writer: Option<BufWriter<fs::File>>,
+    written: u64,
+    max_bytes: u64,
+}
+
+impl BuildLog {
+    async fn create(path: &Path, max_bytes: u64) -> Self {
+        let file = match path.parent() {
+            Some(dir) => fs::create_dir_all(dir).await.map(|_| ()),
+            None => Ok(()),
+        };
+        let writer = match file {
+            Ok(()) => fs::File::create(path).await,
+            Err(e) => Err(e),
+        };
+
+        match writer {
+            Ok(file) => Self {
+                writer: Some(BufWriter::new(file)),
+                written: 0,
+                max_bytes,
+            },
+            Err(e) => {
+                warn!("Failed to create build log {:?}: {}", path, e);
+                Self {
+                    writer: None,
+                    written: 0,
+                    max_bytes,
+                }
+            }
+        }
+    }
+
+    fn is_open(&self) -> bool {
+        self.writer.is_some()
+    }
+
+    fn written(&self) -> u64 {
+        self.written
+    }
+
+    // 每行带上时间戳和来源（stdout/stderr）
+    async fn write_line(&mut self, stream: &str, line: &str) {
+        let Some(writer) = self.writer.as_mut() else {
+            return;
+        };
+        let line = format!(
+            "{} [{}] {}",
+            chrono::Utc::now().to_rfc3339_opts(chrono::SecondsFormat::Millis, true),
+            stream,
+            line
+        );
+
+        let len = line.len() as u64 + 1;
+        if self.written + len > self.max_bytes {
+            let _ = writer.write_all(b"[log truncated: size limit reached]\n").await;
+            self.finish().await;
+            return;
+        }
+
+        let result = async {
+            writer.write_all(line.as_bytes()).await?;
+            writer.write_all(b"\n").await
+        }.await;
+
+        match result {
+            Ok(()) => self.written += len,
+            Err(e) => {
+                warn!("Failed to write build log: {}", e);
+                self.writer = None;
+            }
+        }
+    }
+
+    async fn finish(&mut self) {
+        if let Some(mut writer) = self.writer.take() {
+            if let Err(e) = writer.flush().await {
+                warn!("Failed to flush build log: {}", e);
+            }
+        }
+    }
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+    use nix::sys::signal::kill;
+    use nix::unistd::Pid;
+
+    #[tokio::test]
+    async fn timeout_kills_whole_process_group() {
+        // 后台的 sleep 是 sh 的子进程，只结束 sh 时它会继续运行
+        let mut child = spawn_in_own_group(
+            TokioCommand::new("sh")
+                .args(["-c", "sleep 60 & echo $!; sleep 60"])
+                .stdout(Stdio::piped()),
+        )
+        .unwrap();
+        let shell_pid = Pid::from_raw(child.id().unwrap() as i32);
+        let mut stdout = BufReader::new(child.stdout.take().unwrap()).lines();
+        let background_pid: i32 = stdout.next_line().await.unwrap().unwrap().trim().parse().unwrap();
+        let background_pid = Pid::from_raw(background_pid);
+
+        assert!(timeout(Duration::from_secs(1), child.wait()).await.is_err());
+        kill_process_group(&mut child).await;
+
+        // SIGKILL 送达后进程退出仍需要一点时间
+        for _ in 0..50 {
+            if !is_alive(shell_pid) && !is_alive(background_pid) {
+                break;
+            }
+            tokio::time::sleep(Duration::from_millis(20)).await;
+        }
+        assert!(!is_alive(shell_pid), "shell is still running");
+        assert!(!is_alive(background_pid), "background child is still running");
+    }
+
+    // 孤儿进程由 init 回收，容器中的 init 未必及时回收，僵尸进程视为已结束
+    fn is_alive(pid: Pid) -> bool {
+        if kill(pid, None).is_err() {
+            return false;
+        }
+        let stat = std::fs::read_to_string(format!("/proc/{}/stat", pid)).unwrap_or_default();
+        let state = stat.rsplit_once(')').and_then(|(_, rest)| rest.split_whitespace().next());
+        state != Some("Z")
+    }
+}
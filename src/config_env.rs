@@ -0,0 +1,122 @@
+use anyhow::Context;
+use toml::{Table, Value};
+
+// 环境变量覆盖配置项的前缀
+//
+// 变量名去掉前缀后按 `__` 拆分为配置路径，每一段转为小写，段内的单下划线保留：
+//   PUMPKIN_MONITOR_SERVER__PORT=9000          -> server.port
+//   PUMPKIN_MONITOR_GITHUB__REPO_OWNER=foo     -> github.repo_owner
+//   PUMPKIN_MONITOR_GITHUB__1__BRANCH=dev      -> 第二个 [[github]] 的 branch
+//   PUMPKIN_MONITOR_BUILD__FEATURES=a,b        -> build.features = ["a", "b"]
+// 只有一个 [[github]] 时可以省略下标；有多个时必须写下标。
+// 值按配置文件中原有值的类型解析，原来没有该项时按 TOML 值解析，失败则作为字符串。
+pub const ENV_PREFIX: &str = "PUMPKIN_MONITOR_";
+
+// 把环境变量中的覆盖项写入解析后的配置表，在反序列化为 Config 之前调用；返回是否有覆盖项
+pub fn apply_env_overrides(table: &mut Table) -> anyhow::Result<bool> {
+    let mut overrides: Vec<(String, String)> = std::env::vars()
+        .filter(|(name, _)| name.starts_with(ENV_PREFIX) && name.len() > ENV_PREFIX.len())
+        .collect();
+    overrides.sort();
+    let applied = !overrides.is_empty();
+
+    for (name, raw) in overrides {
+        let path: Vec<String> = name[ENV_PREFIX.len()..]
+            .split("__")
+            .map(|segment| segment.to_lowercase())
+            .collect();
+        if path.iter().any(|segment| segment.is_empty()) {
+            anyhow::bail!("Invalid config override {}: empty key segment", name);
+        }
+        apply_override(table, &path, &raw)
+            .with_context(|| format!("Invalid config override {}", name))?;
+        tracing::info!("Config value {} overridden by {}", path.join("."), name);
+    }
+    Ok(applied)
+}
+
+fn apply_override(table: &mut Table, path: &[String], raw: &str) -> anyhow::Result<()> {
+    let (key, rest) = path.split_first().expect("path is not empty");
+    if rest.is_empty() {
+        let value = parse_value(table.get(key), raw)?;
+        table.insert(key.clone(), value);
+        return Ok(());
+    }
+
+    let entry = table
+        .entry(key.clone())
+        .or_insert_with(|| Value::Table(Table::new()));
+    match entry {
+        Value::Table(child) => apply_override(child, rest, raw),
+        Value::Array(items) => {
+            // 数组表（[[github]]）：第一段是数字时作为下标，否则要求只有一项
+            let (index, rest) = match rest[0].parse::<usize>() {
+                Ok(index) => (index, &rest[1..]),
+                Err(_) if items.len() == 1 => (0, rest),
+                Err(_) => anyhow::bail!("{} has {} entries, add an index like {}__0__...", key, items.len(), key.to_uppercase()),
+            };
+            if rest.is_empty() {
+                anyhow::bail!("missing key after index {} of {}", index, key);
+            }
+            match items.get_mut(index) {
+                Some(Value::Table(child)) => apply_override(child, rest, raw),
+                Some(_) => anyhow::bail!("{}[{}] is not a table", key, index),
+                None => anyhow::bail!("{} has no entry with index {}", key, index),
+            }
+        }
+        _ => anyhow::bail!("{} is not a table", key),
+    }
+}
+
+// 按已有值的类型解析，保证 port 等字段仍然得到整数
+// 时长字段既可以写秒数也可以写 "5m"，整数解析失败时保留为字符串，交给反序列化检查
+fn parse_value(existing: Option<&Value>, raw: &str) -> anyhow::Result<Value> {
+    Ok(match existing {
+        Some(Value::String(_)) => Value::String(raw.to_string()),
+        Some(Value::Integer(_)) => match raw.trim().parse() {
+            Ok(value) => Value::Integer(value),
+            Err(_) => Value::String(raw.to_string()),
+        },
+        Some(Value::Float(_)) => Value::Float(
+            raw.trim().parse().with_context(|| format!("expected a number, got '{}'", raw))?,
+        ),
+        Some(Value::Boolean(_)) => Value::Boolean(
+            raw.trim().parse().with_context(|| format!("expected true or false, got '{}'", raw))?,
+        ),
+        Some(Value::Array(_)) => parse_array(raw),
+        Some(Value::Table(_)) => anyhow::bail!("cannot replace a table with a single value"),
+        Some(Value::Datetime(_)) | None => parse_toml_value(raw).unwrap_or_else(|| Value::String(raw.to_string())),
+    })
+}
+
+// 支持 TOML 数组写法 ["a", "b"]，也支持逗号分隔的 a,b
+fn parse_array(raw: &str) -> Value {
+    if let Some(value @ Value::Array(_)) = parse_toml_value(raw) {
+        return value;
+    }
+    Value::Array(
+        raw.split(',')
+            .map(str::trim)
+            .filter(|item| !item.is_empty())
+            .map(|item| Value::String(item.to_string()))
+            .collect(),
+    )
+}
+
+fn parse_toml_value(raw: &str) -> Option<Value> {
+    toml::from_str::<Table>(&format!("value = {}", raw))
+        .ok()
+        .and_then(|mut table| table.remove("value"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn integer_value_accepts_duration_string() {
+        let existing = Value::Integer(30);
+        assert_eq!(parse_value(Some(&existing), "60").unwrap(), Value::Integer(60));
+        assert_eq!(parse_value(Some(&existing), "5m").unwrap(), Value::String("5m".to_string()));
+    }
+}
@@ -0,0 +1,194 @@
+use anyhow::Result;
+use std::fs::{File, OpenOptions};
+use std::io::{BufRead, BufReader, Read, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+use std::process::{Child, ChildStdin};
+use std::sync::{Arc, Mutex};
+use tokio::sync::broadcast;
+use tracing::warn;
+
+// 控制台输出缓冲的行数，慢速客户端落后超过这个数量会丢失部分行
+const CONSOLE_CHANNEL_CAPACITY: usize = 1024;
+
+// 从日志末尾向前读取时每次读取的字节数
+const TAIL_CHUNK_SIZE: u64 = 64 * 1024;
+
+#[derive(Clone)]
+pub struct Console {
+    output_tx: broadcast::Sender<String>,
+    input: Arc<Mutex<Option<ChildStdin>>>,
+    log_path: PathBuf,
+    // 日志文件超过该大小后轮换为 <log_path>.1，0 表示不写日志文件
+    max_log_bytes: u64,
+}
+
+impl Console {
+    pub fn new(log_path: PathBuf, max_log_bytes: u64) -> Self {
+        let (output_tx, _) = broadcast::channel(CONSOLE_CHANNEL_CAPACITY);
+        Self {
+            output_tx,
+            input: Arc::new(Mutex::new(None)),
+            log_path,
+            max_log_bytes,
+        }
+    }
+
+    pub fn log_path(&self) -> &Path {
+        &self.log_path
+    }
+
+    pub fn subscribe(&self) -> broadcast::Receiver<String> {
+        self.output_tx.subscribe()
+    }
+
+    // 接管子进程的 stdin/stdout/stderr，后台线程持续读取输出，避免管道阻塞
+    pub fn attach(&self, child: &mut Child) {
+        // 服务器输出追加写入日志文件，stdout 与 stderr 共用同一个文件；不写日志文件时仍然读取输出，避免管道写满后阻塞服务
+        let log_file = if self.max_log_bytes == 0 {
+            None
+        } else {
+            match RotatingLog::open(self.log_path.clone(), self.max_log_bytes) {
+                Ok(log) => Some(Arc::new(Mutex::new(log))),
+                Err(e) => {
+                    warn!("Failed to open server log {:?}: {}", self.log_path, e);
+                    None
+                }
+            }
+        };
+
+        if let Some(stdout) = child.stdout.take() {
+            self.spawn_reader(stdout, log_file.clone());
+        }
+        if let Some(stderr) = child.stderr.take() {
+            self.spawn_reader(stderr, log_file);
+        }
+        *self.input.lock().unwrap() = child.stdin.take();
+    }
+
+    pub fn detach(&self) {
+        *self.input.lock().unwrap() = None;
+    }
+
+    pub fn send_command(&self, command: &str) -> Result<()> {
+        let mut input = self.input.lock().unwrap();
+        let stdin = input
+            .as_mut()
+            .ok_or_else(|| anyhow::anyhow!("Server process is not running"))?;
+        writeln!(stdin, "{}", command.trim_end())?;
+        stdin.flush()?;
+        Ok(())
+    }
+
+    fn spawn_reader<R: Read + Send + 'static>(&self, reader: R, log_file: Option<Arc<Mutex<RotatingLog>>>) {
+        let output_tx = self.output_tx.clone();
+        // 按字节读取，服务器输出不是 UTF-8 时替换无效字节而不是停止读取，否则管道写满后服务器会阻塞
+        std::thread::spawn(move || {
+            let mut reader = BufReader::new(reader);
+            let mut buf = Vec::new();
+            loop {
+                buf.clear();
+                match reader.read_until(b'\n', &mut buf) {
+                    Ok(0) => break,
+                    Ok(_) => {
+                        // 与 lines() 一样去掉行尾的 \n 或 \r\n
+                        let text = String::from_utf8_lossy(&buf);
+                        let text = text.strip_suffix('\n').unwrap_or(&text);
+                        let line = text.strip_suffix('\r').unwrap_or(text).to_string();
+                        if let Some(ref log_file) = log_file {
+                            if let Err(e) = log_file.lock().unwrap().write_line(&line) {
+                                warn!("Failed to write server log: {}", e);
+                            }
+                        }
+                        // 没有订阅者时发送失败是正常的
+                        let _ = output_tx.send(line);
+                    }
+                    Err(e) if e.kind() == std::io::ErrorKind::Interrupted => {}
+                    Err(e) => {
+                        warn!("Error reading server output: {}", e);
+                        break;
+                    }
+                }
+            }
+        });
+    }
+}
+
+// 服务器日志文件，超过大小上限时把当前文件改名为 <path>.1（覆盖上一个）并重新开始写入
+struct RotatingLog {
+    file: File,
+    path: PathBuf,
+    written: u64,
+    max_bytes: u64,
+}
+
+impl RotatingLog {
+    fn open(path: PathBuf, max_bytes: u64) -> std::io::Result<Self> {
+        let file = OpenOptions::new().create(true).append(true).open(&path)?;
+        let written = file.metadata()?.len();
+        Ok(Self { file, path, written, max_bytes })
+    }
+
+    fn write_line(&mut self, line: &str) -> std::io::Result<()> {
+        let len = line.len() as u64 + 1;
+        if self.written > 0 && self.written + len > self.max_bytes {
+            self.rotate()?;
+        }
+        writeln!(self.file, "{}", line)?;
+        self.written += len;
+        Ok(())
+    }
+
+    fn rotate(&mut self) -> std::io::Result<()> {
+        let mut rotated = self.path.clone().into_os_string();
+        rotated.push(".1");
+        std::fs::rename(&self.path, rotated)?;
+        self.file = OpenOptions::new().create(true).append(true).open(&self.path)?;
+        self.written = 0;
+        Ok(())
+    }
+}
+
+// 从文件末尾向前按块读取，只读出最后 count 行，避免把大日志整个读入内存
+pub fn read_tail(path: &Path, count: usize) -> Result<Vec<String>> {
+    let mut file = File::open(path)?;
+    let len = file.metadata()?.len();
+
+    let mut position = len;
+    let mut buffer = Vec::new();
+    while position > 0 {
+        let chunk_size = TAIL_CHUNK_SIZE.min(position);
+        position -= chunk_size;
+
+        let mut chunk = vec![0; chunk_size as usize];
+        file.seek(SeekFrom::Start(position))?;
+        file.read_exact(&mut chunk)?;
+        chunk.extend_from_slice(&buffer);
+        buffer = chunk;
+
+        // 多读一个换行，保证最前面的一行是完整的
+        if buffer.iter().filter(|&&b| b == b'\n').count() > count {
+            break;
+        }
+    }
+
+    let text = String::from_utf8_lossy(&buffer);
+    let lines: Vec<&str> = text.lines().collect();
+    let skip = lines.len().saturating_sub(count);
+    Ok(lines[skip..].iter().map(|line| line.to_string()).collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn reads_past_invalid_utf8() {
+        let console = Console::new(PathBuf::new(), 0);
+        let mut output_rx = console.subscribe();
+        console.spawn_reader(std::io::Cursor::new(b"first\n\xff\xfebad\r\nlast".to_vec()), None);
+
+        assert_eq!(output_rx.recv().await.unwrap(), "first");
+        assert_eq!(output_rx.recv().await.unwrap(), "\u{fffd}\u{fffd}bad");
+        assert_eq!(output_rx.recv().await.unwrap(), "last");
+    }
+}
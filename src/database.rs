@@ -1,18 +1,25 @@
 use anyhow::Result;
-use sqlx::{SqlitePool, Row};
-use chrono::{DateTime, Utc};
+use async_trait::async_trait;
+use sqlx::sqlite::{SqliteConnectOptions, SqliteJournalMode, SqlitePool, SqliteRow};
+use sqlx::Row;
+use chrono::{DateTime, SecondsFormat, Utc};
 use uuid::Uuid;
 
-use crate::types::{BuildStatus, BuildStatusType, SystemStatus};
+use crate::storage::{StorageBackend, StorageData};
+use crate::types::{BuildStatus, BuildStatusType, BuildTrigger, HealthState, StatusTransition, SystemStatus};
 
 pub struct Database {
     pool: SqlitePool,
 }
 
 impl Database {
-    pub async fn new(database_url: &str) -> Result<Self> {
-        let pool = SqlitePool::connect(database_url).await?;
-        
+    pub async fn new(path: &str) -> Result<Self> {
+        let options = SqliteConnectOptions::new()
+            .filename(path)
+            .create_if_missing(true)
+            .journal_mode(SqliteJournalMode::Wal);
+        let pool = SqlitePool::connect_with(options).await?;
+
         // 创建表
         sqlx::query(
             r#"
@@ -22,12 +29,42 @@ impl Database {
                 status TEXT NOT NULL,
                 started_at TEXT NOT NULL,
                 finished_at TEXT,
-                error_message TEXT
+                error_message TEXT,
+                log_path TEXT,
+                log_size INTEGER,
+                command TEXT,
+                rollback_of TEXT,
+                commit_message TEXT,
+                commit_author TEXT,
+                diagnostics TEXT NOT NULL DEFAULT '[]',
+                attempt INTEGER,
+                max_attempts INTEGER,
+                build_trigger TEXT NOT NULL DEFAULT 'poll',
+                changed_files TEXT NOT NULL DEFAULT '[]',
+                commit_url TEXT,
+                toolchain TEXT,
+                clean_build BOOLEAN,
+                cancelled_by TEXT,
+                cancelled_at TEXT
             )
             "#,
         )
         .execute(&pool)
         .await?;
+        add_column_if_missing(&pool, "builds", "log_size", "INTEGER").await?;
+        add_column_if_missing(&pool, "builds", "rollback_of", "TEXT").await?;
+        add_column_if_missing(&pool, "builds", "commit_message", "TEXT").await?;
+        add_column_if_missing(&pool, "builds", "commit_author", "TEXT").await?;
+        add_column_if_missing(&pool, "builds", "diagnostics", "TEXT NOT NULL DEFAULT '[]'").await?;
+        add_column_if_missing(&pool, "builds", "attempt", "INTEGER").await?;
+        add_column_if_missing(&pool, "builds", "max_attempts", "INTEGER").await?;
+        add_column_if_missing(&pool, "builds", "build_trigger", "TEXT NOT NULL DEFAULT 'poll'").await?;
+        add_column_if_missing(&pool, "builds", "changed_files", "TEXT NOT NULL DEFAULT '[]'").await?;
+        add_column_if_missing(&pool, "builds", "commit_url", "TEXT").await?;
+        add_column_if_missing(&pool, "builds", "toolchain", "TEXT").await?;
+        add_column_if_missing(&pool, "builds", "cancelled_by", "TEXT").await?;
+        add_column_if_missing(&pool, "builds", "cancelled_at", "TEXT").await?;
+        add_column_if_missing(&pool, "builds", "clean_build", "BOOLEAN").await?;
 
         sqlx::query(
             r#"
@@ -37,168 +74,387 @@ impl Database {
                 build_status TEXT NOT NULL,
                 is_running BOOLEAN NOT NULL,
                 last_check TEXT NOT NULL,
-                started_at TEXT
+                uptime_secs INTEGER,
+                started_at TEXT,
+                process_pid INTEGER,
+                github_rate_limit TEXT,
+                last_stop_graceful BOOLEAN,
+                queue_depth INTEGER NOT NULL DEFAULT 0,
+                queued_commits TEXT NOT NULL DEFAULT '[]',
+                last_built_commit TEXT,
+                health TEXT,
+                github_error TEXT,
+                process_started_at INTEGER,
+                paused BOOLEAN NOT NULL DEFAULT 0,
+                workspace_size_bytes INTEGER,
+                cancelled_commit TEXT,
+                pause_until TEXT,
+                last_seen_commit TEXT
             )
             "#,
         )
         .execute(&pool)
         .await?;
+        add_column_if_missing(&pool, "system_status", "health", "TEXT").await?;
+        add_column_if_missing(&pool, "system_status", "github_error", "TEXT").await?;
+        add_column_if_missing(&pool, "system_status", "process_started_at", "INTEGER").await?;
+        add_column_if_missing(&pool, "system_status", "paused", "BOOLEAN NOT NULL DEFAULT 0").await?;
+        add_column_if_missing(&pool, "system_status", "workspace_size_bytes", "INTEGER").await?;
+        add_column_if_missing(&pool, "system_status", "cancelled_commit", "TEXT").await?;
+        add_column_if_missing(&pool, "system_status", "pause_until", "TEXT").await?;
+        add_column_if_missing(&pool, "system_status", "last_seen_commit", "TEXT").await?;
 
-        // 插入默认状态
         sqlx::query(
             r#"
-            INSERT OR IGNORE INTO system_status (id, build_status, is_running, last_check)
-            VALUES (1, 'pending', false, ?)
+            CREATE TABLE IF NOT EXISTS status_history (
+                at TEXT NOT NULL,
+                is_running BOOLEAN NOT NULL,
+                build_status TEXT NOT NULL,
+                current_commit TEXT,
+                health TEXT NOT NULL,
+                paused BOOLEAN NOT NULL
+            )
             "#,
         )
-        .bind(Utc::now().to_rfc3339())
         .execute(&pool)
         .await?;
 
         Ok(Self { pool })
     }
 
-    pub async fn save_build_status(&self, build: &BuildStatus) -> Result<()> {
-        sqlx::query(
-            r#"
-            INSERT OR REPLACE INTO builds (id, commit_sha, status, started_at, finished_at, error_message)
-            VALUES (?, ?, ?, ?, ?, ?)
-            "#,
-        )
-        .bind(build.id.to_string())
-        .bind(&build.commit_sha)
-        .bind(format!("{:?}", build.status).to_lowercase())
-        .bind(build.started_at.to_rfc3339())
-        .bind(build.finished_at.map(|dt| dt.to_rfc3339()))
-        .bind(&build.error_message)
-        .execute(&self.pool)
-        .await?;
-
-        Ok(())
-    }
-
-    pub async fn get_latest_builds(&self, limit: i32) -> Result<Vec<BuildStatus>> {
+    async fn load_builds(&self) -> Result<Vec<BuildStatus>> {
         let rows = sqlx::query(
             r#"
-            SELECT id, commit_sha, status, started_at, finished_at, error_message
+            SELECT id, commit_sha, status, started_at, finished_at, error_message, log_path, log_size, command, rollback_of,
+                commit_message, commit_author, diagnostics, attempt, max_attempts, build_trigger, changed_files, commit_url,
+                toolchain, cancelled_by, cancelled_at, clean_build
             FROM builds
             ORDER BY started_at DESC
-            LIMIT ?
             "#,
         )
-        .bind(limit)
         .fetch_all(&self.pool)
         .await?;
 
         let mut builds = Vec::new();
         for row in rows {
-            let status_str: String = row.get("status");
-            let status = match status_str.as_str() {
-                "pending" => BuildStatusType::Pending,
-                "building" => BuildStatusType::Building,
-                "success" => BuildStatusType::Success,
-                "failed" => BuildStatusType::Failed,
-                "stopped" => BuildStatusType::Stopped,
-                _ => BuildStatusType::Pending,
-            };
-
-            builds.push(BuildStatus {
-                id: Uuid::parse_str(&row.get::<String, _>("id"))?,
-                commit_sha: row.get("commit_sha"),
-                status,
-                started_at: DateTime::parse_from_rfc3339(&row.get::<String, _>("started_at"))?.with_timezone(&Utc),
-                finished_at: row.get::<Option<String>, _>("finished_at")
-                    .map(|s| DateTime::parse_from_rfc3339(&s).map(|dt| dt.with_timezone(&Utc)))
-                    .transpose()?,
-                error_message: row.get("error_message"),
-            });
+            let mut build = BuildStatus::new(
+                Uuid::parse_str(&row.get::<String, _>("id"))?,
+                row.get("commit_sha"),
+            );
+            build.status = parse_build_status(&row.get::<String, _>("status"));
+            build.started_at = parse_time(&row.get::<String, _>("started_at"))?;
+            if let Some(finished_at) = row.get::<Option<String>, _>("finished_at") {
+                build.finish_at(parse_time(&finished_at)?);
+            }
+            build.error_message = row.get("error_message");
+            build.log_path = row.get("log_path");
+            build.log_size = row.get::<Option<i64>, _>("log_size").map(|size| size as u64);
+            build.command = row.get("command");
+            build.rollback_of = row.get("rollback_of");
+            build.commit_message = row.get("commit_message");
+            build.commit_author = row.get("commit_author");
+            build.diagnostics = serde_json::from_str(&row.get::<String, _>("diagnostics"))?;
+            build.attempt = row.get::<Option<i64>, _>("attempt").map(|attempt| attempt as u32);
+            build.max_attempts = row.get::<Option<i64>, _>("max_attempts").map(|max| max as u32);
+            build.trigger = BuildTrigger::from_name(&row.get::<String, _>("build_trigger")).unwrap_or_default();
+            build.changed_files = serde_json::from_str(&row.get::<String, _>("changed_files"))?;
+            build.commit_url = row.get("commit_url");
+            build.toolchain = row.get("toolchain");
+            // 旧版本写入的记录没有这一列时视为未知
+            build.clean_build = row.try_get("clean_build").ok().flatten();
+            build.cancelled_by = row.get("cancelled_by");
+            build.cancelled_at = row.get::<Option<String>, _>("cancelled_at")
+                .map(|s| parse_time(&s))
+                .transpose()?;
+            builds.push(build);
         }
+        // 时间戳格式不一致时 ORDER BY 不可靠，按时间重新排序
+        builds.sort_by_key(|b| std::cmp::Reverse(b.started_at));
 
         Ok(builds)
     }
 
-    pub async fn update_system_status(&self, status: &SystemStatus) -> Result<()> {
-        sqlx::query(
-            r#"
-            UPDATE system_status
-            SET current_commit = ?, build_status = ?, is_running = ?, last_check = ?
-            WHERE id = 1
-            "#,
+    async fn load_status_history(&self) -> Result<Vec<StatusTransition>> {
+        let rows = sqlx::query(
+            "SELECT at, is_running, build_status, current_commit, health, paused FROM status_history ORDER BY at",
         )
-        .bind(&status.current_commit)
-        .bind(format!("{:?}", status.build_status).to_lowercase())
-        .bind(status.is_running)
-        .bind(status.last_check.to_rfc3339())
-        .execute(&self.pool)
+        .fetch_all(&self.pool)
         .await?;
 
-        Ok(())
+        let mut history = Vec::new();
+        for row in rows {
+            history.push(StatusTransition {
+                at: parse_time(&row.get::<String, _>("at"))?,
+                is_running: row.get("is_running"),
+                build_status: parse_build_status(&row.get::<String, _>("build_status")),
+                current_commit: row.get("current_commit"),
+                health: parse_health(Some(&row.get::<String, _>("health"))),
+                paused: row.get("paused"),
+            });
+        }
+        history.sort_by_key(|t| t.at);
+        Ok(history)
+    }
+
+    fn parse_system_status(row: &SqliteRow) -> Result<SystemStatus> {
+        Ok(SystemStatus {
+            current_commit: row.get("current_commit"),
+            build_status: parse_build_status(&row.get::<String, _>("build_status")),
+            is_running: row.get("is_running"),
+            last_check: parse_time(&row.get::<String, _>("last_check"))?,
+            uptime_seconds: row.get("uptime_secs"),
+            started_at: row.get::<Option<String>, _>("started_at")
+                .map(|s| parse_time(&s))
+                .transpose()?,
+            process_pid: row.get::<Option<i64>, _>("process_pid").map(|pid| pid as u32),
+            process_started_at: row.get::<Option<i64>, _>("process_started_at").map(|time| time as u64),
+            paused: row.get("paused"),
+            pause_until: row.get::<Option<String>, _>("pause_until")
+                .map(|s| parse_time(&s))
+                .transpose()?,
+            github_rate_limit: row.get::<Option<String>, _>("github_rate_limit")
+                .map(|s| serde_json::from_str(&s))
+                .transpose()?,
+            github_error: row.get::<Option<String>, _>("github_error")
+                .map(|s| serde_json::from_str(&s))
+                .transpose()?,
+            last_stop_graceful: row.get("last_stop_graceful"),
+            queue_depth: row.get::<i64, _>("queue_depth") as usize,
+            queued_commits: serde_json::from_str(&row.get::<String, _>("queued_commits"))?,
+            avg_build_duration_secs: None,
+            health: parse_health(row.get::<Option<String>, _>("health").as_deref()),
+            // 资源占用是实时采样值，不保存到数据库
+            memory_bytes: None,
+            cpu_percent: None,
+            free_space_mb: None,
+            server_info: None,
+            pending_commit: None,
+            workspace_size_bytes: row.get::<Option<i64>, _>("workspace_size_bytes").map(|size| size as u64),
+            cancelled_commit: row.get("cancelled_commit"),
+        })
     }
+}
 
-    pub async fn get_system_status(&self) -> Result<SystemStatus> {
-        let row = sqlx::query(
+#[async_trait]
+impl StorageBackend for Database {
+    async fn load(&self) -> Result<Option<StorageData>> {
+        let row = sqlx::query("SELECT * FROM system_status WHERE id = 1")
+            .fetch_optional(&self.pool)
+            .await?;
+        let Some(row) = row else {
+            return Ok(None);
+        };
+
+        Ok(Some(StorageData {
+            builds: self.load_builds().await?,
+            system_status: Self::parse_system_status(&row)?,
+            last_built_commit: row.get("last_built_commit"),
+            last_seen_commit: row.get("last_seen_commit"),
+            status_history: self.load_status_history().await?,
+        }))
+    }
+
+    async fn save_build(&self, build: &BuildStatus, pruned: &[Uuid], _data: &StorageData) -> Result<()> {
+        let mut tx = self.pool.begin().await?;
+
+        sqlx::query(
             r#"
-            SELECT current_commit, build_status, is_running, last_check, started_at
-            FROM system_status
-            WHERE id = 1
+            INSERT OR REPLACE INTO builds (
+                id, commit_sha, status, started_at, finished_at, error_message, log_path, log_size, command, rollback_of,
+                commit_message, commit_author, diagnostics, attempt, max_attempts, build_trigger, changed_files, commit_url,
+                toolchain, cancelled_by, cancelled_at, clean_build
+            )
+            VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)
             "#,
         )
-        .fetch_one(&self.pool)
+        .bind(build.id.to_string())
+        .bind(&build.commit_sha)
+        .bind(build.status.as_str())
+        .bind(format_time(build.started_at))
+        .bind(build.finished_at.map(format_time))
+        .bind(&build.error_message)
+        .bind(&build.log_path)
+        .bind(build.log_size.map(|size| size as i64))
+        .bind(&build.command)
+        .bind(&build.rollback_of)
+        .bind(&build.commit_message)
+        .bind(&build.commit_author)
+        .bind(serde_json::to_string(&build.diagnostics)?)
+        .bind(build.attempt.map(i64::from))
+        .bind(build.max_attempts.map(i64::from))
+        .bind(build.trigger.as_str())
+        .bind(serde_json::to_string(&build.changed_files)?)
+        .bind(&build.commit_url)
+        .bind(&build.toolchain)
+        .bind(&build.cancelled_by)
+        .bind(build.cancelled_at.map(format_time))
+        .bind(build.clean_build)
+        .execute(&mut *tx)
         .await?;
 
-        let status_str: String = row.get("build_status");
-        let status = match status_str.as_str() {
-            "pending" => BuildStatusType::Pending,
-            "building" => BuildStatusType::Building,
-            "success" => BuildStatusType::Success,
-            "failed" => BuildStatusType::Failed,
-            "stopped" => BuildStatusType::Stopped,
-            _ => BuildStatusType::Pending,
-        };
-
-        let uptime = row.get::<Option<String>, _>("started_at")
-            .map(|s| {
-                DateTime::parse_from_rfc3339(&s)
-                    .map(|started| Utc::now().signed_duration_since(started.with_timezone(&Utc)))
-                    .ok()
-            })
-            .flatten();
+        for id in pruned {
+            sqlx::query("DELETE FROM builds WHERE id = ?")
+                .bind(id.to_string())
+                .execute(&mut *tx)
+                .await?;
+        }
 
-        Ok(SystemStatus {
-            current_commit: row.get("current_commit"),
-            build_status: status,
-            is_running: row.get("is_running"),
-            last_check: DateTime::parse_from_rfc3339(&row.get::<String, _>("last_check"))?.with_timezone(&Utc),
-            uptime,
-        })
+        tx.commit().await?;
+        Ok(())
     }
 
-    pub async fn set_service_started(&self) -> Result<()> {
+    async fn save_state(&self, data: &StorageData) -> Result<()> {
+        let status = &data.system_status;
         sqlx::query(
             r#"
-            UPDATE system_status
-            SET started_at = ?, is_running = true
-            WHERE id = 1
+            INSERT OR REPLACE INTO system_status (
+                id, current_commit, build_status, is_running, last_check, uptime_secs, started_at,
+                process_pid, github_rate_limit, last_stop_graceful, queue_depth, queued_commits, last_built_commit, health,
+                github_error, process_started_at, paused, workspace_size_bytes, cancelled_commit,
+                pause_until, last_seen_commit
+            )
+            VALUES (1, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)
             "#,
         )
-        .bind(Utc::now().to_rfc3339())
+        .bind(&status.current_commit)
+        .bind(status.build_status.as_str())
+        .bind(status.is_running)
+        .bind(format_time(status.last_check))
+        .bind(status.uptime_seconds)
+        .bind(status.started_at.map(format_time))
+        .bind(status.process_pid.map(i64::from))
+        .bind(status.github_rate_limit.as_ref().map(serde_json::to_string).transpose()?)
+        .bind(status.last_stop_graceful)
+        .bind(status.queue_depth as i64)
+        .bind(serde_json::to_string(&status.queued_commits)?)
+        .bind(&data.last_built_commit)
+        .bind(status.health.as_str())
+        .bind(status.github_error.as_ref().map(serde_json::to_string).transpose()?)
+        .bind(status.process_started_at.map(|time| time as i64))
+        .bind(status.paused)
+        .bind(status.workspace_size_bytes.map(|size| size as i64))
+        .bind(&status.cancelled_commit)
+        .bind(status.pause_until.map(format_time))
+        .bind(&data.last_seen_commit)
         .execute(&self.pool)
         .await?;
 
         Ok(())
     }
 
-    pub async fn set_service_stopped(&self) -> Result<()> {
+    async fn save_status_transition(&self, transition: &StatusTransition, data: &StorageData) -> Result<()> {
+        let mut tx = self.pool.begin().await?;
+
         sqlx::query(
             r#"
-            UPDATE system_status
-            SET is_running = false
-            WHERE id = 1
+            INSERT INTO status_history (at, is_running, build_status, current_commit, health, paused)
+            VALUES (?, ?, ?, ?, ?, ?)
             "#,
         )
-        .execute(&self.pool)
+        .bind(format_time(transition.at))
+        .bind(transition.is_running)
+        .bind(transition.build_status.as_str())
+        .bind(&transition.current_commit)
+        .bind(transition.health.as_str())
+        .bind(transition.paused)
+        .execute(&mut *tx)
         .await?;
 
+        if let Some(oldest) = data.status_history.first() {
+            sqlx::query("DELETE FROM status_history WHERE at < ?")
+                .bind(format_time(oldest.at))
+                .execute(&mut *tx)
+                .await?;
+        }
+
+        tx.commit().await?;
         Ok(())
     }
 }
+
+// 为旧版本创建的表补上新增的列
+async fn add_column_if_missing(pool: &SqlitePool, table: &str, column: &str, definition: &str) -> Result<()> {
+    let columns = sqlx::query(&format!("PRAGMA table_info({})", table))
+        .fetch_all(pool)
+        .await?;
+    if columns.iter().any(|row| row.get::<String, _>("name") == column) {
+        return Ok(());
+    }
+
+    sqlx::query(&format!("ALTER TABLE {} ADD COLUMN {} {}", table, column, definition))
+        .execute(pool)
+        .await?;
+    Ok(())
+}
+
+fn parse_build_status(status: &str) -> BuildStatusType {
+    BuildStatusType::from_name(status).unwrap_or(BuildStatusType::Pending)
+}
+
+fn parse_health(health: Option<&str>) -> HealthState {
+    match health {
+        Some("healthy") => HealthState::Healthy,
+        Some("unhealthy") => HealthState::Unhealthy,
+        _ => HealthState::Unknown,
+    }
+}
+
+fn format_time(time: DateTime<Utc>) -> String {
+    time.to_rfc3339_opts(SecondsFormat::Micros, true)
+}
+
+fn parse_time(value: &str) -> Result<DateTime<Utc>> {
+    Ok(DateTime::parse_from_rfc3339(value)?.with_timezone(&Utc))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::storage::StorageData;
+    use crate::types::GitHubCommit;
+
+    // 保存一条构建记录后重新打开数据库，模拟监控程序重启
+    #[tokio::test]
+    async fn reloads_saved_build() {
+        let dir = std::env::temp_dir().join(format!("pumpkin-monitor-test-{}", uuid::Uuid::new_v4()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("data.db").to_string_lossy().into_owned();
+
+        let commit = GitHubCommit {
+            sha: "6dcb09b5b57875f334f61aebed695e2e4193db5e".to_string(),
+            message: "Fix all the bugs".to_string(),
+            author: "octocat".to_string(),
+            date: Utc::now(),
+            html_url: None,
+            parents: Vec::new(),
+            changed_files: vec!["src/main.rs".to_string()],
+        };
+        let mut build = BuildStatus::for_commit(Uuid::new_v4(), &commit);
+        build.status = BuildStatusType::Success;
+        build.finished_at = Some(Utc::now());
+        build.clean_build = Some(true);
+        build.toolchain = Some("1.80.0".to_string());
+
+        let data = StorageData { builds: vec![build.clone()], ..StorageData::default() };
+        {
+            let database = Database::new(&path).await.unwrap();
+            database.save_build(&build, &[], &data).await.unwrap();
+            database.save_state(&data).await.unwrap();
+            database.pool.close().await;
+        }
+
+        let database = Database::new(&path).await.unwrap();
+        let loaded = database.load().await.unwrap().expect("saved data should load");
+        database.pool.close().await;
+        assert_eq!(loaded.builds.len(), 1);
+        let reloaded = &loaded.builds[0];
+        assert_eq!(reloaded.id, build.id);
+        assert_eq!(reloaded.commit_sha, build.commit_sha);
+        assert_eq!(reloaded.status, BuildStatusType::Success);
+        assert_eq!(reloaded.clean_build, Some(true));
+        assert_eq!(reloaded.toolchain.as_deref(), Some("1.80.0"));
+        assert_eq!(reloaded.changed_files, ["src/main.rs"]);
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,72 @@
+use serde_json::Value;
+
+use crate::types::Diagnostic;
+
+// 单次构建最多保存的诊断数量
+pub const MAX_DIAGNOSTICS: usize = 200;
+
+// cargo --message-format=json 输出的一行
+pub enum CargoMessage {
+    // 编译器诊断，rendered 为带颜色的完整文本，用于写入日志
+    CompilerMessage {
+        diagnostic: Option<Diagnostic>,
+        rendered: String,
+    },
+    // compiler-artifact、build-finished 等其他消息
+    Other,
+}
+
+// 不是 cargo 的 JSON 消息时返回 None，按普通输出处理
+pub fn parse_cargo_message(line: &str) -> Option<CargoMessage> {
+    if !line.starts_with('{') {
+        return None;
+    }
+    let value: Value = serde_json::from_str(line).ok()?;
+    let reason = value.get("reason")?.as_str()?;
+    if reason != "compiler-message" {
+        return Some(CargoMessage::Other);
+    }
+
+    let message = value.get("message")?;
+    let rendered = message.get("rendered").and_then(Value::as_str).unwrap_or_default().to_string();
+    let level = message.get("level").and_then(Value::as_str).unwrap_or_default();
+    let text = message.get("message").and_then(Value::as_str).unwrap_or_default();
+    let spans = message.get("spans").and_then(Value::as_array).map(Vec::as_slice).unwrap_or_default();
+
+    // 只保留错误和警告，跳过 "aborting due to ..."、"N warnings emitted" 这类汇总
+    let is_summary = spans.is_empty() && (text.starts_with("aborting due to") || text.ends_with("emitted"));
+    let diagnostic = (matches!(level, "error" | "warning") && !is_summary).then(|| {
+        let primary = spans
+            .iter()
+            .find(|span| span.get("is_primary").and_then(Value::as_bool) == Some(true));
+        Diagnostic {
+            level: level.to_string(),
+            message: text.to_string(),
+            file: primary.and_then(|span| span.get("file_name")).and_then(Value::as_str).map(str::to_string),
+            line: primary.and_then(|span| span.get("line_start")).and_then(Value::as_u64).map(|line| line as u32),
+            rendered: (!rendered.is_empty()).then(|| strip_ansi(&rendered).trim_end().to_string()),
+        }
+    });
+
+    Some(CargoMessage::CompilerMessage { diagnostic, rendered })
+}
+
+// 去除 ANSI 颜色控制序列，构建日志和仪表盘只显示纯文本
+pub fn strip_ansi(text: &str) -> String {
+    let mut result = String::with_capacity(text.len());
+    let mut chars = text.chars();
+    while let Some(c) = chars.next() {
+        if c == '\x1b' {
+            if chars.next() == Some('[') {
+                for c in chars.by_ref() {
+                    if ('@'..='~').contains(&c) {
+                        break;
+                    }
+                }
+            }
+            continue;
+        }
+        result.push(c);
+    }
+    result
+}
@@ -1,136 +1,692 @@
 use anyhow::Result;
-use reqwest::Client;
+use reqwest::header::{ETAG, IF_NONE_MATCH};
+use reqwest::{Client, RequestBuilder, Response, StatusCode};
+use serde::de::DeserializeOwned;
+use serde::Deserialize;
 use serde_json::Value;
+use std::hash::{BuildHasher, Hasher};
+use std::time::Duration;
 use tracing::{info, warn};
 
-use crate::types::{Config, GitHubCommit};
+use crate::types::{BuildStatusType, GitHubCommit, GitHubConfig, GitHubErrorKind, RateLimitStatus};
+
+// 剩余配额低于该值时发出警告并放慢轮询
+const RATE_LIMIT_LOW_WATERMARK: u64 = 10;
+
+// compare 接口最多返回的文件数，达到该数量时文件列表可能不完整
+const COMPARE_MAX_FILES: usize = 300;
+
+// 跟踪标签时只查看标签列表接口返回的第一页
+const TAGS_PER_PAGE: usize = 100;
+
+// 单个请求的超时
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(30);
+// 第一次重试前的等待时间，之后每次翻倍，最多等待 RETRY_MAX_DELAY
+const RETRY_BASE_DELAY: Duration = Duration::from_secs(1);
+const RETRY_MAX_DELAY: Duration = Duration::from_secs(30);
+
+// GitHub API 配额耗尽，需要等到 reset_at 之后再请求
+#[derive(Debug)]
+pub struct RateLimited {
+    pub reset_at: chrono::DateTime<chrono::Utc>,
+}
+
+impl std::fmt::Display for RateLimited {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "GitHub API rate limit exceeded, resets at {}", self.reset_at)
+    }
+}
+
+impl std::error::Error for RateLimited {}
+
+// 仓库或分支不存在，或令牌没有访问权限；属于配置错误，重试不会成功
+#[derive(Debug)]
+pub struct RepoUnavailable {
+    pub status: StatusCode,
+    pub url: String,
+}
+
+impl std::fmt::Display for RepoUnavailable {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "GitHub API returned {} for {}, check repo_owner, repo_name, branch and token",
+            self.status, self.url
+        )
+    }
+}
+
+impl std::error::Error for RepoUnavailable {}
+
+// 重试之后 GitHub 仍然返回 5xx
+#[derive(Debug)]
+pub struct ServerError {
+    pub status: StatusCode,
+}
+
+impl std::fmt::Display for ServerError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "GitHub API returned {}", self.status)
+    }
+}
+
+impl std::error::Error for ServerError {}
+
+// 按错误类型归类，记录在系统状态的 github_error 中
+pub fn classify_error(error: &anyhow::Error) -> GitHubErrorKind {
+    if error.downcast_ref::<RateLimited>().is_some() {
+        GitHubErrorKind::RateLimited
+    } else if error.downcast_ref::<RepoUnavailable>().is_some() {
+        GitHubErrorKind::Config
+    } else if error.downcast_ref::<ServerError>().is_some() || error.downcast_ref::<reqwest::Error>().is_some() {
+        GitHubErrorKind::Network
+    } else {
+        GitHubErrorKind::Other
+    }
+}
 
 pub struct GitHubMonitor {
     client: Client,
-    config: Config,
+    config: GitHubConfig,
+    token: Option<String>,
     last_commit_sha: Option<String>,
+    // 上次轮询的 URL 及其返回的 ETag，304 响应不消耗配额
+    etag: Option<(String, String)>,
+    rate_limit: Option<RateLimitStatus>,
+    // 编译后的 watch_paths 和 ignore_paths，未配置时为 None
+    watch_globs: Option<globset::GlobSet>,
+    ignore_globs: Option<globset::GlobSet>,
+    // 编译后的 tag，跟踪分支时为 None
+    tag_glob: Option<globset::GlobMatcher>,
+    // 网络错误和 5xx 响应的重试次数
+    max_retries: u32,
 }
 
 impl GitHubMonitor {
-    pub fn new(config: Config) -> Self {
+    pub fn new(config: GitHubConfig, max_retries: u32) -> Self {
+        let token = config.resolved_token();
+
+        if token.is_some() {
+            info!("Using authenticated GitHub API requests");
+        }
+
+        // 加载配置时已经校验过 watch_paths、ignore_paths 和 tag
+        let watch_globs = config.watch_globs().expect("watch_paths are validated when loading the config");
+        let ignore_globs = config.ignore_globs().expect("ignore_paths are validated when loading the config");
+        let tag_glob = config.tag_glob().expect("tag is validated when loading the config");
+
         Self {
             client: Client::new(),
             config,
+            token,
             last_commit_sha: None,
+            etag: None,
+            rate_limit: None,
+            watch_globs,
+            ignore_globs,
+            tag_glob,
+            max_retries,
         }
     }
 
-    pub async fn check_for_updates(&mut self) -> Result<Option<GitHubCommit>> {
-        let url = format!(
-            "https://api.github.com/repos/{}/{}/commits/{}",
-            self.config.github.repo_owner,
-            self.config.github.repo_name,
-            self.config.github.branch
-        );
+    pub fn rate_limit_status(&self) -> Option<RateLimitStatus> {
+        self.rate_limit.clone()
+    }
 
-        info!("Checking for updates: {}", url);
+    // 配额即将耗尽时，把剩余请求平均分配到重置之前，避免提前用完
+    pub fn poll_delay(&self, interval: std::time::Duration) -> std::time::Duration {
+        let Some(RateLimitStatus { remaining: Some(remaining), reset_at: Some(reset_at), .. }) = self.rate_limit else {
+            return interval;
+        };
+        if remaining >= RATE_LIMIT_LOW_WATERMARK {
+            return interval;
+        }
 
-        let response = self.client
-            .get(&url)
-            .header("User-Agent", "pumpkin-monitor")
-            .send()
-            .await?;
+        let until_reset = (reset_at - chrono::Utc::now()).to_std().unwrap_or_default();
+        interval.max(until_reset / (remaining as u32 + 1))
+    }
+
+    // 记录响应中的配额信息，配额耗尽时返回 RateLimited 错误，其他失败状态返回 None
+    fn handle_response(&mut self, response: Response) -> Result<Option<Response>> {
+        let header_value = |name: &str| {
+            response.headers()
+                .get(name)
+                .and_then(|v| v.to_str().ok())
+                .and_then(|v| v.parse::<u64>().ok())
+        };
+
+        let rate_limit = RateLimitStatus {
+            limit: header_value("x-ratelimit-limit"),
+            remaining: header_value("x-ratelimit-remaining"),
+            reset_at: header_value("x-ratelimit-reset")
+                .and_then(|reset| chrono::DateTime::from_timestamp(reset as i64, 0)),
+        };
+        if let Some(remaining) = rate_limit.remaining {
+            if remaining < RATE_LIMIT_LOW_WATERMARK {
+                warn!(
+                    "GitHub API rate limit nearly exhausted: {} of {:?} requests remaining, resets at {:?}",
+                    remaining, rate_limit.limit, rate_limit.reset_at
+                );
+            }
+            self.rate_limit = Some(rate_limit.clone());
+        }
 
-        if !response.status().is_success() {
-            warn!("GitHub API returned status: {}", response.status());
+        let status = response.status();
+        if status.is_success() {
+            return Ok(Some(response));
+        }
+        if status == StatusCode::NOT_MODIFIED {
             return Ok(None);
         }
 
-        let commit_data: Value = response.json().await?;
-        
-        let sha = commit_data["sha"]
-            .as_str()
-            .ok_or_else(|| anyhow::anyhow!("Missing commit SHA"))?
-            .to_string();
+        let rate_limited = matches!(status, StatusCode::FORBIDDEN | StatusCode::TOO_MANY_REQUESTS)
+            && rate_limit.remaining == Some(0);
+        if rate_limited {
+            let reset_at = rate_limit.reset_at
+                .unwrap_or_else(|| chrono::Utc::now() + chrono::Duration::seconds(60));
+            warn!("GitHub API rate limit exceeded, resets at {}", reset_at);
+            return Err(RateLimited { reset_at }.into());
+        }
 
-        // 检查是否有新提交
-        if let Some(ref last_sha) = self.last_commit_sha {
-            if *last_sha == sha {
-                return Ok(None);
+        warn!("GitHub API returned status: {}", status);
+        Ok(None)
+    }
+
+    fn get(&self, url: &str) -> RequestBuilder {
+        let request = self.client
+            .get(url)
+            .header("User-Agent", "pumpkin-monitor")
+            .timeout(REQUEST_TIMEOUT);
+
+        match &self.token {
+            Some(token) => request.bearer_auth(token),
+            None => request,
+        }
+    }
+
+    // 是否跟踪标签而不是分支
+    pub fn follows_tags(&self) -> bool {
+        self.tag_glob.is_some()
+    }
+
+    // 跟踪分支时轮询分支的最新提交，跟踪标签时轮询标签列表
+    fn poll_url(&self) -> String {
+        match self.tag_glob {
+            Some(_) => format!("{}/tags?per_page={}", self.config.api_repo_url(), TAGS_PER_PAGE),
+            None => format!("{}/commits/{}", self.config.api_repo_url(), self.config.branch),
+        }
+    }
+
+    // 发送 GET 请求，网络错误和 5xx 响应按指数退避重试；其他响应（包括配额耗尽）直接返回
+    async fn send(&self, request: RequestBuilder) -> Result<Response> {
+        let mut attempt = 0;
+        loop {
+            let result = request
+                .try_clone()
+                .expect("GET requests have no streaming body")
+                .send()
+                .await;
+            let failure = match &result {
+                Ok(response) if response.status().is_server_error() => response.status().to_string(),
+                Ok(_) => return Ok(result?),
+                Err(e) => e.to_string(),
+            };
+            if attempt >= self.max_retries {
+                return Ok(result?);
+            }
+
+            attempt += 1;
+            let delay = retry_delay(attempt);
+            warn!(
+                "GitHub request failed ({}), retrying in {:.1}s ({}/{})",
+                failure, delay.as_secs_f32(), attempt, self.max_retries
+            );
+            tokio::time::sleep(delay).await;
+        }
+    }
+
+    pub async fn check_for_updates(&mut self) -> Result<Option<GitHubCommit>> {
+        let url = self.poll_url();
+
+        info!("Checking for updates: {}", url);
+
+        let mut request = self.get(&url);
+        // 只有轮询同一个 URL 时才复用 ETag，分支变化后需要完整响应
+        match &self.etag {
+            Some((etag_url, etag)) if *etag_url == url => {
+                request = request.header(IF_NONE_MATCH, etag);
             }
+            _ => self.etag = None,
         }
 
-        let commit = GitHubCommit {
-            sha: sha.clone(),
-            message: commit_data["commit"]["message"]
-                .as_str()
-                .unwrap_or("No message")
-                .to_string(),
-            author: commit_data["commit"]["author"]["name"]
-                .as_str()
-                .unwrap_or("Unknown")
-                .to_string(),
-            date: chrono::DateTime::parse_from_rfc3339(
-                commit_data["commit"]["author"]["date"]
-                    .as_str()
-                    .unwrap_or("1970-01-01T00:00:00Z")
-            )
-            .unwrap_or_else(|_| chrono::DateTime::parse_from_rfc3339("1970-01-01T00:00:00Z").unwrap())
-            .with_timezone(&chrono::Utc),
+        let response = self.send(request).await?;
+        let status = response.status();
+        let Some(response) = self.handle_response(response)? else {
+            return match status {
+                StatusCode::NOT_MODIFIED => Ok(None),
+                status => Err(poll_error(status, &url)),
+            };
+        };
+
+        let etag = response.headers()
+            .get(ETAG)
+            .and_then(|v| v.to_str().ok())
+            .map(|v| (url.clone(), v.to_string()));
+        let commit = match &self.tag_glob {
+            None => parse_response::<CommitResponse>(response, "commits").await?.into(),
+            Some(glob) => {
+                let tags: Vec<TagResponse> = parse_response(response, "tags").await?;
+                let Some((name, sha)) = newest_tag(tags, glob) else {
+                    warn!("No tag matching {} found", self.config.tag.as_deref().unwrap_or_default());
+                    self.etag = etag;
+                    return Ok(None);
+                };
+                if self.last_commit_sha.as_deref() == Some(sha.as_str()) {
+                    self.etag = etag;
+                    return Ok(None);
+                }
+                info!("Newest tag matching {} is {} ({})", self.config.tag.as_deref().unwrap_or_default(), name, sha);
+                // 获取提交信息失败时不保存 ETag，下次轮询重新获取标签列表
+                let Some(commit) = self.get_commit(&sha).await? else {
+                    return Ok(None);
+                };
+                commit
+            }
         };
+        self.etag = etag;
+
+        // 检查是否有新提交
+        if self.last_commit_sha.as_deref() == Some(commit.sha.as_str()) {
+            return Ok(None);
+        }
 
-        self.last_commit_sha = Some(sha);
+        self.last_commit_sha = Some(commit.sha.clone());
         info!("New commit found: {} by {}", commit.sha, commit.author);
         
         Ok(Some(commit))
     }
 
-    pub async fn get_latest_commit(&self) -> Result<Option<GitHubCommit>> {
-        let url = format!(
-            "https://api.github.com/repos/{}/{}/commits/{}",
-            self.config.github.repo_owner,
-            self.config.github.repo_name,
-            self.config.github.branch
-        );
+    // 分支的最新提交，跟踪标签时为最新标签指向的提交
+    pub async fn get_latest_commit(&mut self) -> Result<Option<GitHubCommit>> {
+        let Some(glob) = self.tag_glob.clone() else {
+            let branch = self.config.branch.clone();
+            return self.get_commit(&branch).await;
+        };
+
+        let url = self.poll_url();
+        let response = self.send(self.get(&url)).await?;
+        let status = response.status();
+        let Some(response) = self.handle_response(response)? else {
+            return Err(poll_error(status, &url));
+        };
+        let tags: Vec<TagResponse> = parse_response(response, "tags").await?;
+        match newest_tag(tags, &glob) {
+            Some((_, sha)) => self.get_commit(&sha).await,
+            None => {
+                warn!("No tag matching {} found", self.config.tag.as_deref().unwrap_or_default());
+                Ok(None)
+            }
+        }
+    }
 
-        info!("Getting latest commit: {}", url);
+    // 获取指定引用（分支名或提交 SHA）对应的提交信息
+    pub async fn get_commit(&mut self, reference: &str) -> Result<Option<GitHubCommit>> {
+        let url = format!("{}/commits/{}", self.config.api_repo_url(), reference);
 
-        let response = self.client
-            .get(&url)
-            .header("User-Agent", "pumpkin-monitor")
-            .send()
-            .await?;
+        info!("Getting commit: {}", url);
 
-        if !response.status().is_success() {
-            warn!("GitHub API returned status: {}", response.status());
+        let response = self.send(self.get(&url)).await?;
+        let Some(response) = self.handle_response(response)? else {
             return Ok(None);
+        };
+
+        let commit: CommitResponse = parse_response(response, "commits").await?;
+        Ok(Some(commit.into()))
+    }
+
+    // 获取 base 之后到 head 为止的所有提交，按提交顺序排列；历史被改写时只返回 head
+    pub async fn commits_between(&mut self, base: &str, head: &GitHubCommit) -> Result<Vec<GitHubCommit>> {
+        let url = format!("{}/compare/{}...{}", self.config.api_repo_url(), base, head.sha);
+
+        info!("Comparing commits: {}", url);
+
+        let response = self.send(self.get(&url)).await?;
+        let Some(response) = self.handle_response(response)? else {
+            return Ok(vec![head.clone()]);
+        };
+
+        let compare: CompareResponse = parse_response(response, "compare").await?;
+        if compare.status != "ahead" {
+            warn!(
+                "Commit {} is not a descendant of {} ({}), only building the branch head",
+                head.sha, base, compare.status
+            );
+            return Ok(vec![head.clone()]);
         }
 
-        let commit_data: Value = response.json().await?;
-        
-        let sha = commit_data["sha"]
-            .as_str()
-            .ok_or_else(|| anyhow::anyhow!("Missing commit SHA"))?
-            .to_string();
-
-        let commit = GitHubCommit {
-            sha: sha.clone(),
-            message: commit_data["commit"]["message"]
-                .as_str()
-                .unwrap_or("No message")
-                .to_string(),
-            author: commit_data["commit"]["author"]["name"]
-                .as_str()
-                .unwrap_or("Unknown")
-                .to_string(),
-            date: chrono::DateTime::parse_from_rfc3339(
-                commit_data["commit"]["author"]["date"]
-                    .as_str()
-                    .unwrap_or("1970-01-01T00:00:00Z")
-            )
-            .unwrap_or_else(|_| chrono::DateTime::parse_from_rfc3339("1970-01-01T00:00:00Z").unwrap())
-            .with_timezone(&chrono::Utc),
+        let mut commits: Vec<GitHubCommit> = compare.commits.into_iter().map(GitHubCommit::from).collect();
+        // compare 接口最多返回 250 个提交，确保分支最新提交一定在列表中
+        if commits.last().map(|c| c.sha.as_str()) != Some(head.sha.as_str()) {
+            commits.push(head.clone());
+        }
+
+        Ok(commits)
+    }
+
+    // 获取 base 到 head 之间变更的文件，重命名的文件同时包含新旧路径；
+    // 历史被改写或文件过多导致无法得到完整列表时返回 None
+    pub async fn changed_files(&mut self, base: &str, head: &str) -> Result<Option<Vec<String>>> {
+        let url = format!("{}/compare/{}...{}", self.config.api_repo_url(), base, head);
+
+        info!("Listing changed files: {}", url);
+
+        let response = self.send(self.get(&url)).await?;
+        let Some(response) = self.handle_response(response)? else {
+            return Ok(None);
         };
-        
-        Ok(Some(commit))
+
+        let compare: CompareResponse = parse_response(response, "compare").await?;
+        if compare.status != "ahead" {
+            warn!(
+                "Commit {} is not a descendant of {} ({}), cannot list changed files",
+                head, base, compare.status
+            );
+            return Ok(None);
+        }
+        let Some(files) = compare.files else {
+            return Ok(None);
+        };
+        if files.len() >= COMPARE_MAX_FILES {
+            warn!("Comparison of {}...{} lists {} or more files, the list may be incomplete", base, head, COMPARE_MAX_FILES);
+            return Ok(None);
+        }
+
+        Ok(Some(
+            files
+                .into_iter()
+                .flat_map(|file| file.previous_filename.into_iter().chain([file.filename]))
+                .collect(),
+        ))
+    }
+
+    // 不需要构建该提交的原因：提交信息带有跳过标记、作者在 ignore_authors 中，
+    // 或 base 之后的文件变更全部匹配 ignore_paths、没有匹配 watch_paths 的变更；
+    // 配置了 watch_paths 或 ignore_paths 时把变更的文件写入 commit.changed_files。
+    // 没有 base（首次运行）或无法列出变更的文件（强制推送、接口出错）时总是构建
+    pub async fn skip_reason(&mut self, base: Option<&str>, commit: &mut GitHubCommit) -> Option<String> {
+        if let Some(marker) = self.config.skip_marker(&commit.message) {
+            return Some(format!("commit message contains {}", marker));
+        }
+        if let Some(author) = self.config.ignored_author(&commit.author) {
+            info!("Skipping commit {} by ignored author {}", commit.sha, author);
+            return Some(format!("author {} is in ignore_authors", author));
+        }
+        if self.watch_globs.is_none() && self.ignore_globs.is_none() {
+            return None;
+        }
+        let base = base.filter(|base| *base != commit.sha)?;
+
+        let files = match self.changed_files(base, &commit.sha).await {
+            Ok(Some(files)) => files,
+            Ok(None) => {
+                info!("Changed files of {} are unknown, building it", commit.sha);
+                return None;
+            }
+            Err(e) => {
+                warn!("Failed to list changed files of {}, building it: {}", commit.sha, e);
+                return None;
+            }
+        };
+        commit.changed_files = files;
+
+        // 被忽略的文件不再参与 watch_paths 的判断
+        let relevant: Vec<&String> = commit
+            .changed_files
+            .iter()
+            .filter(|file| !self.ignore_globs.as_ref().is_some_and(|globs| globs.is_match(file)))
+            .collect();
+        if relevant.is_empty() && !commit.changed_files.is_empty() {
+            info!("skipping docs-only commit {}", commit.sha);
+            return Some("all changes match ignore_paths".to_string());
+        }
+        let globs = self.watch_globs.as_ref()?;
+        let watched = relevant.iter().any(|file| globs.is_match(file));
+        (!watched).then(|| "no changes under watch_paths".to_string())
+    }
+
+    pub fn last_commit(&self) -> Option<String> {
+        self.last_commit_sha.clone()
+    }
+
+    pub fn set_last_commit(&mut self, sha: Option<String>) {
+        // 基准提交变化后缓存的 ETag 不再可靠，下次轮询需要完整响应
+        if self.last_commit_sha != sha {
+            self.etag = None;
+        }
+        self.last_commit_sha = sha;
+    }
+}
+
+// 通过 GitHub Commit Status API 报告构建状态，未启用或没有令牌时什么也不做
+#[derive(Clone)]
+pub struct CommitStatusReporter {
+    client: Client,
+    token: Option<String>,
+    url_base: String,
+    target_url: Option<String>,
+}
+
+impl CommitStatusReporter {
+    pub fn new(config: &GitHubConfig, dashboard_url: Option<&str>) -> Self {
+        let token = if config.report_status {
+            let token = config.resolved_token();
+            if token.is_none() {
+                warn!("report_status is enabled for {} but no GitHub token is configured", config.key());
+            }
+            token
+        } else {
+            None
+        };
+
+        Self {
+            client: Client::new(),
+            token,
+            url_base: format!("{}/statuses", config.api_repo_url()),
+            target_url: dashboard_url.map(|url| format!("{}/?repo={}", url.trim_end_matches('/'), config.key())),
+        }
+    }
+
+    pub async fn build_started(&self, sha: &str) {
+        self.report(sha, "pending", "Build started").await;
+    }
+
+    pub async fn build_finished(&self, sha: &str, status: &BuildStatusType) {
+        let (state, description) = match status {
+            BuildStatusType::Success => ("success", "Build succeeded"),
+            BuildStatusType::TestsFailed => ("failure", "Tests failed"),
+            BuildStatusType::Cancelled => ("error", "Build cancelled"),
+            _ => ("failure", "Build failed"),
+        };
+        self.report(sha, state, description).await;
+    }
+
+    // 报告失败只记录警告，不影响构建
+    async fn report(&self, sha: &str, state: &str, description: &str) {
+        let Some(token) = &self.token else {
+            return;
+        };
+
+        let mut body = serde_json::json!({
+            "state": state,
+            "description": description,
+            "context": "pumpkin-monitor",
+        });
+        if let Some(target_url) = &self.target_url {
+            body["target_url"] = Value::String(target_url.clone());
+        }
+
+        let result = self.client
+            .post(format!("{}/{}", self.url_base, sha))
+            .header("User-Agent", "pumpkin-monitor")
+            .bearer_auth(token)
+            .timeout(std::time::Duration::from_secs(10))
+            .json(&body)
+            .send()
+            .await;
+        match result {
+            Ok(response) if response.status().is_success() => info!("Reported {} status for {}", state, sha),
+            Ok(response) => warn!("GitHub commit status API returned {} for {}", response.status(), sha),
+            Err(e) => warn!("Failed to report commit status for {}: {}", sha, e),
+        }
+    }
+}
+
+// 轮询失败的状态码转换为错误：404、401 和 403 说明仓库、分支或令牌配置有误，5xx 在重试后仍然失败
+fn poll_error(status: StatusCode, url: &str) -> anyhow::Error {
+    match status {
+        StatusCode::NOT_FOUND | StatusCode::UNAUTHORIZED | StatusCode::FORBIDDEN => {
+            RepoUnavailable { status, url: url.to_string() }.into()
+        }
+        status if status.is_server_error() => ServerError { status }.into(),
+        status => anyhow::anyhow!("GitHub API returned {} for {}", status, url),
+    }
+}
+
+// 第 attempt 次重试前的等待时间：1s、2s、4s……最多 30s，再随机缩短至一半到全部，避免多个仓库同时重试
+fn retry_delay(attempt: u32) -> Duration {
+    let delay = RETRY_BASE_DELAY
+        .saturating_mul(1 << (attempt - 1).min(16))
+        .min(RETRY_MAX_DELAY);
+    let jitter = std::collections::hash_map::RandomState::new().build_hasher().finish() % 1000;
+    delay.mul_f64(0.5 + jitter as f64 / 2000.0)
+}
+
+// commits 接口返回的提交，compare 接口的 commits 列表也是同样的结构；
+// 只声明用到的字段，缺少时解析失败，而不是用默认值代替
+#[derive(Deserialize)]
+struct CommitResponse {
+    sha: String,
+    html_url: String,
+    commit: CommitDetails,
+    parents: Vec<CommitParent>,
+}
+
+#[derive(Deserialize)]
+struct CommitParent {
+    sha: String,
+}
+
+#[derive(Deserialize)]
+struct CommitDetails {
+    message: String,
+    // git 提交的作者，不是 GitHub 账号，也不是 committer
+    author: GitSignature,
+}
+
+#[derive(Deserialize)]
+struct GitSignature {
+    name: String,
+    date: chrono::DateTime<chrono::Utc>,
+}
+
+#[derive(Deserialize)]
+struct CompareResponse {
+    // ahead、behind、diverged 或 identical
+    status: String,
+    commits: Vec<CommitResponse>,
+    files: Option<Vec<CompareFile>>,
+}
+
+#[derive(Deserialize)]
+struct CompareFile {
+    filename: String,
+    // 只有重命名的文件才有
+    previous_filename: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct TagResponse {
+    name: String,
+    commit: TagCommit,
+}
+
+#[derive(Deserialize)]
+struct TagCommit {
+    sha: String,
+}
+
+impl From<CommitResponse> for GitHubCommit {
+    fn from(response: CommitResponse) -> Self {
+        Self {
+            sha: response.sha,
+            message: response.commit.message,
+            author: response.commit.author.name,
+            date: response.commit.author.date,
+            html_url: Some(response.html_url),
+            parents: response.parents.into_iter().map(|parent| parent.sha).collect(),
+            changed_files: Vec::new(),
+        }
+    }
+}
+
+// 按接口的响应结构解析，错误信息中包含缺少或类型不对的字段
+async fn parse_response<T: DeserializeOwned>(response: Response, endpoint: &str) -> Result<T> {
+    let body = response.bytes().await?;
+    serde_json::from_slice(&body)
+        .map_err(|e| anyhow::anyhow!("Unexpected response from the GitHub {} API: {}", endpoint, e))
+}
+
+// 标签列表中名称匹配的最新标签及其指向的提交，按版本号比较，如 v1.10.0 新于 v1.9.0
+fn newest_tag(tags: Vec<TagResponse>, glob: &globset::GlobMatcher) -> Option<(String, String)> {
+    tags.into_iter()
+        .filter(|tag| glob.is_match(&tag.name))
+        .max_by(|a, b| version_key(&a.name).cmp(&version_key(&b.name)))
+        .map(|tag| (tag.name, tag.commit.sha))
+}
+// 把名称拆成数字和非数字片段，数字片段按数值比较
+fn version_key(name: &str) -> Vec<std::result::Result<u64, &str>> {
+    let mut key = Vec::new();
+    let mut rest = name;
+    while let Some(first) = rest.chars().next() {
+        let numeric = first.is_ascii_digit();
+        let end = rest.find(|c: char| c.is_ascii_digit() != numeric).unwrap_or(rest.len());
+        let (chunk, tail) = rest.split_at(end);
+        key.push(if numeric { chunk.parse().map_err(|_| chunk) } else { Err(chunk) });
+        rest = tail;
+    }
+    key
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const COMMIT_FIXTURE: &str = include_str!("../tests/fixtures/github_commit.json");
+
+    #[test]
+    fn parses_commit_response() {
+        let response: CommitResponse = serde_json::from_str(COMMIT_FIXTURE).unwrap();
+        let commit = GitHubCommit::from(response);
+        assert_eq!(commit.sha, "6dcb09b5b57875f334f61aebed695e2e4193db5e");
+        assert_eq!(commit.message, "Fix all the bugs");
+        // 使用 git 作者，而不是 committer 或 GitHub 账号
+        assert_eq!(commit.author, "Monalisa Octocat");
+        assert_eq!(commit.date.to_rfc3339(), "2011-04-14T16:00:49+00:00");
+        assert_eq!(
+            commit.html_url.as_deref(),
+            Some("https://github.com/Pumpkin-MC/Pumpkin/commit/6dcb09b5b57875f334f61aebed695e2e4193db5e")
+        );
+        assert_eq!(
+            commit.parents,
+            ["553c2077f0edc3d5dc5d17262f6aa498e69d6f8e", "762941318ee16e59dabbacb1b4049eec22f0d303"]
+        );
     }
 
-    pub fn set_last_commit(&mut self, sha: String) {
-        self.last_commit_sha = Some(sha);
+    #[test]
+    fn missing_author_is_an_error() {
+        let mut value: serde_json::Value = serde_json::from_str(COMMIT_FIXTURE).unwrap();
+        value["commit"].as_object_mut().unwrap().remove("author");
+        let error = serde_json::from_value::<CommitResponse>(value).err().expect("commit without author should be rejected");
+        assert!(error.to_string().contains("missing field `author`"), "{}", error);
     }
 }
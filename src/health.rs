@@ -0,0 +1,227 @@
+use std::time::Duration;
+
+use anyhow::{Context, Result};
+use reqwest::Client;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::TcpStream;
+use tokio::time::Instant;
+use tracing::{info, warn};
+
+use crate::types::{elapsed_between, HealthCheckConfig, HealthState, ServerListInfo};
+
+// 定期探测服务是否有响应，进程存活但连续多次无响应时判定为不健康
+pub struct HealthChecker {
+    client: Client,
+    config: HealthCheckConfig,
+    // 服务启动后的这段时间内不检查，等待服务器完成加载
+    startup_grace_period: u64,
+    last_probe: Option<Instant>,
+    failures: u32,
+}
+
+impl HealthChecker {
+    pub fn new(config: HealthCheckConfig, startup_grace_period: u64) -> Self {
+        Self {
+            client: Client::new(),
+            config,
+            startup_grace_period,
+            last_probe: None,
+            failures: 0,
+        }
+    }
+
+    // 到达检查间隔时探测一次；返回 None 表示本次未检查或失败次数尚未达到阈值
+    pub async fn check(&mut self, started_at: Option<chrono::DateTime<chrono::Utc>>) -> Option<HealthState> {
+        if !self.config.is_enabled() {
+            return None;
+        }
+        if let Some(started_at) = started_at {
+            if (elapsed_between(started_at, chrono::Utc::now()).num_seconds() as u64) < self.startup_grace_period {
+                return None;
+            }
+        }
+        if self.last_probe.is_some_and(|last| last.elapsed() < Duration::from_secs(self.config.interval)) {
+            return None;
+        }
+        self.last_probe = Some(Instant::now());
+
+        match self.probe().await {
+            Ok(()) => {
+                if self.failures > 0 {
+                    info!("Health check recovered after {} failures", self.failures);
+                }
+                self.failures = 0;
+                Some(HealthState::Healthy)
+            }
+            Err(e) => {
+                self.failures += 1;
+                warn!("Health check failed ({}/{}): {:#}", self.failures, self.config.failure_threshold, e);
+                (self.failures >= self.config.failure_threshold).then_some(HealthState::Unhealthy)
+            }
+        }
+    }
+
+    // 服务重启或停止后重新计数
+    pub fn reset(&mut self) {
+        self.last_probe = None;
+        self.failures = 0;
+    }
+
+    async fn probe(&self) -> Result<()> {
+        let timeout = Duration::from_secs(self.config.timeout);
+        if let Some(url) = self.config.http_url.as_deref().filter(|url| !url.is_empty()) {
+            let response = self.client.get(url).timeout(timeout).send().await?;
+            if !response.status().is_success() {
+                anyhow::bail!("{} returned {}", url, response.status());
+            }
+        } else if let Some(port) = self.config.tcp_port {
+            let mut stream = tokio::time::timeout(timeout, TcpStream::connect(("127.0.0.1", port)))
+                .await
+                .with_context(|| format!("Connection to port {} timed out", port))?
+                .with_context(|| format!("Failed to connect to port {}", port))?;
+            if self.config.server_list_ping {
+                tokio::time::timeout(timeout, server_list_ping(&mut stream, port))
+                    .await
+                    .with_context(|| format!("Server List Ping on port {} timed out", port))?
+                    .with_context(|| format!("Server List Ping on port {} failed", port))?;
+            }
+        }
+        Ok(())
+    }
+
+    // 新版本启动后每秒探测一次，直到服务有响应；进程退出或超过 startup_timeout 时返回错误
+    pub async fn wait_until_ready(&self, mut process_alive: impl FnMut() -> bool) -> Result<()> {
+        if !self.config.is_enabled() {
+            return Ok(());
+        }
+        let deadline = Instant::now() + self.config.startup_timeout;
+        loop {
+            let error = match self.probe().await {
+                Ok(()) => return Ok(()),
+                Err(e) => e,
+            };
+            if !process_alive() {
+                anyhow::bail!("process exited before accepting connections");
+            }
+            if Instant::now() >= deadline {
+                return Err(error.context(format!(
+                    "service did not respond within {}",
+                    humantime::format_duration(self.config.startup_timeout)
+                )));
+            }
+            tokio::time::sleep(Duration::from_secs(1)).await;
+        }
+    }
+}
+
+// 查询服务器的在线玩家、版本和 MOTD；连接、握手和读取响应合计不超过 timeout，避免卡住的连接阻塞调用方
+pub async fn query_server_status(port: u16, timeout: Duration) -> Result<ServerListInfo> {
+    let response = tokio::time::timeout(timeout, async {
+        let mut stream = TcpStream::connect(("127.0.0.1", port))
+            .await
+            .with_context(|| format!("Failed to connect to port {}", port))?;
+        server_list_ping(&mut stream, port).await
+    })
+    .await
+    .with_context(|| format!("Server List Ping on port {} timed out", port))??;
+
+    let players = &response["players"];
+    Ok(ServerListInfo {
+        players_online: players["online"].as_u64().unwrap_or(0) as u32,
+        players_max: players["max"].as_u64().unwrap_or(0) as u32,
+        version: response["version"]["name"].as_str().unwrap_or_default().to_string(),
+        motd: strip_formatting_codes(&chat_text(&response["description"])).trim().to_string(),
+    })
+}
+
+// description 可以是字符串，也可以是带 extra 的聊天组件
+fn chat_text(component: &serde_json::Value) -> String {
+    match component {
+        serde_json::Value::String(text) => text.clone(),
+        serde_json::Value::Array(parts) => parts.iter().map(chat_text).collect(),
+        serde_json::Value::Object(object) => {
+            let mut text = object.get("text").and_then(|text| text.as_str()).unwrap_or_default().to_string();
+            if let Some(extra) = object.get("extra") {
+                text.push_str(&chat_text(extra));
+            }
+            text
+        }
+        _ => String::new(),
+    }
+}
+
+// 去掉 §a 这样的颜色和格式代码
+fn strip_formatting_codes(text: &str) -> String {
+    let mut result = String::with_capacity(text.len());
+    let mut chars = text.chars();
+    while let Some(c) = chars.next() {
+        if c == '§' {
+            chars.next();
+        } else {
+            result.push(c);
+        }
+    }
+    result
+}
+
+const MAX_STATUS_RESPONSE_BYTES: i32 = 1 << 20;
+
+// 发送握手包和状态请求，读取状态响应（JSON 字符串）
+async fn server_list_ping(stream: &mut TcpStream, port: u16) -> Result<serde_json::Value> {
+    let mut handshake = Vec::new();
+    write_varint(&mut handshake, 0x00);
+    // 协议版本 -1 表示只查询状态
+    write_varint(&mut handshake, -1);
+    write_varint(&mut handshake, "127.0.0.1".len() as i32);
+    handshake.extend_from_slice(b"127.0.0.1");
+    handshake.extend_from_slice(&port.to_be_bytes());
+    write_varint(&mut handshake, 1);
+
+    let mut request = Vec::new();
+    write_varint(&mut request, handshake.len() as i32);
+    request.extend_from_slice(&handshake);
+    // 状态请求：长度 1，包 ID 0
+    request.extend_from_slice(&[0x01, 0x00]);
+    stream.write_all(&request).await?;
+
+    let length = read_varint(stream).await?;
+    if length <= 0 {
+        anyhow::bail!("empty status response");
+    }
+    let packet_id = read_varint(stream).await?;
+    if packet_id != 0x00 {
+        anyhow::bail!("unexpected packet id {:#04x} in status response", packet_id);
+    }
+    let json_length = read_varint(stream).await?;
+    // 中途启动的服务器可能返回不完整的数据，过大的长度同样视为协议错误
+    if json_length <= 0 || json_length >= length || json_length > MAX_STATUS_RESPONSE_BYTES {
+        anyhow::bail!("invalid status response length");
+    }
+    let mut json = vec![0; json_length as usize];
+    stream.read_exact(&mut json).await?;
+    serde_json::from_slice(&json).context("status response is not valid JSON")
+}
+
+fn write_varint(buf: &mut Vec<u8>, value: i32) {
+    let mut value = value as u32;
+    loop {
+        if value & !0x7f == 0 {
+            buf.push(value as u8);
+            return;
+        }
+        buf.push((value & 0x7f) as u8 | 0x80);
+        value >>= 7;
+    }
+}
+
+async fn read_varint(stream: &mut TcpStream) -> Result<i32> {
+    let mut value = 0u32;
+    for i in 0..5 {
+        let byte = stream.read_u8().await?;
+        value |= u32::from(byte & 0x7f) << (7 * i);
+        if byte & 0x80 == 0 {
+            return Ok(value as i32);
+        }
+    }
+    anyhow::bail!("VarInt is too long")
+}
@@ -0,0 +1,329 @@
+use serde::Serialize;
+
+// 仪表盘的界面文字，同时序列化为页面脚本中的 translations，字段名即脚本中的键
+// 添加语言只需新增一个 Labels 常量并加入 LANGUAGES
+#[derive(Serialize)]
+pub struct Labels {
+    pub code: &'static str,
+    // <html lang> 属性
+    pub html_lang: &'static str,
+    // 语言切换菜单中显示的名称
+    pub name: &'static str,
+    pub subtitle: &'static str,
+    pub server_info: &'static str,
+    pub running_status: &'static str,
+    pub build_status: &'static str,
+    pub current_commit: &'static str,
+    pub uptime: &'static str,
+    pub memory_usage: &'static str,
+    pub cpu_usage: &'static str,
+    pub workspace_size: &'static str,
+    pub players: &'static str,
+    pub build_history: &'static str,
+    pub refresh_status: &'static str,
+    pub refreshing: &'static str,
+    pub auto_refresh_enabled: &'static str,
+    pub no_builds: &'static str,
+    pub console: &'static str,
+    pub build_output: &'static str,
+    pub build_now: &'static str,
+    pub running: &'static str,
+    pub stopped: &'static str,
+    pub building: &'static str,
+    pub success: &'static str,
+    pub failed: &'static str,
+    pub pending: &'static str,
+    pub tests_failed: &'static str,
+    pub rolled_back: &'static str,
+    pub skipped: &'static str,
+    pub queued: &'static str,
+    pub cancelled: &'static str,
+    pub waiting_for_players: &'static str,
+    // {} 替换为等待部署的提交
+    pub pending_deploy: &'static str,
+    pub healthy: &'static str,
+    pub unhealthy: &'static str,
+    // {} 替换为剩余分钟数
+    pub remaining: &'static str,
+    // {attempt} 和 {max} 替换为尝试次序和最多尝试次数
+    pub attempt: &'static str,
+    pub trigger_poll: &'static str,
+    pub trigger_webhook: &'static str,
+    pub trigger_manual: &'static str,
+    // {failed} 和 {sha} 替换为失败的提交和回滚到的提交
+    pub rolled_back_after: &'static str,
+    // 诊断摘要中的名词，分别用于数量为 1 和其他数量
+    pub error_noun: [&'static str; 2],
+    pub warning_noun: [&'static str; 2],
+    // {} 替换为变更的文件数
+    pub changed_files: &'static str,
+    pub list_separator: &'static str,
+    pub theme_dark: &'static str,
+    pub theme_light: &'static str,
+    pub enter_api_token: &'static str,
+    pub build_request_failed: &'static str,
+    pub dry_run_banner: &'static str,
+    pub paused_banner: &'static str,
+    // {} 替换为自动恢复的时间
+    pub paused_until: &'static str,
+    pub pause_monitoring: &'static str,
+    pub resume_monitoring: &'static str,
+}
+
+const ZH: Labels = Labels {
+    code: "zh",
+    html_lang: "zh-CN",
+    name: "中文",
+    subtitle: "自动化部署监控系统",
+    server_info: "测试用 Minecraft 服务器 - 连接地址: slv4.starlight.cool:3082",
+    running_status: "运行状态",
+    build_status: "构建状态",
+    current_commit: "当前提交",
+    uptime: "运行时长",
+    memory_usage: "内存占用",
+    cpu_usage: "CPU 占用",
+    workspace_size: "工作区占用",
+    players: "在线玩家",
+    build_history: "构建历史",
+    refresh_status: "刷新状态",
+    refreshing: "刷新中...",
+    auto_refresh_enabled: "自动刷新已启用",
+    no_builds: "暂无构建记录",
+    console: "服务器控制台",
+    build_output: "构建输出",
+    build_now: "立即构建",
+    running: "运行中",
+    stopped: "已停止",
+    building: "构建中",
+    success: "成功",
+    failed: "失败",
+    pending: "等待中",
+    tests_failed: "测试失败",
+    rolled_back: "已回滚",
+    skipped: "已跳过",
+    queued: "排队中",
+    cancelled: "已取消",
+    waiting_for_players: "等待玩家离开",
+    pending_deploy: "待部署 {}",
+    healthy: "响应正常",
+    unhealthy: "无响应",
+    remaining: "约剩 {} 分钟",
+    attempt: "第 {attempt}/{max} 次尝试",
+    trigger_poll: "定时检查",
+    trigger_webhook: "Webhook 推送",
+    trigger_manual: "手动触发",
+    rolled_back_after: "{failed} 构建失败后回滚到 {sha}",
+    error_noun: ["个错误", "个错误"],
+    warning_noun: ["个警告", "个警告"],
+    changed_files: "变更的文件（{}）",
+    list_separator: "，",
+    theme_dark: "🌙 深色",
+    theme_light: "☀️ 浅色",
+    enter_api_token: "请输入 API 令牌",
+    build_request_failed: "构建请求失败",
+    dry_run_banner: "试运行模式：只检查更新，不会构建或启动服务",
+    paused_banner: "部署已暂停：新提交只排队，不会构建或自动重启服务",
+    paused_until: "（将于 {} 自动恢复）",
+    pause_monitoring: "暂停监控",
+    resume_monitoring: "恢复监控",
+};
+
+const EN: Labels = Labels {
+    code: "en",
+    html_lang: "en",
+    name: "English",
+    subtitle: "Automated Deployment Monitoring System",
+    server_info: "Test Pumpkin Minecraft Server Hosted by zly2006 - Connect to: slv4.starlight.cool:3082",
+    running_status: "Running Status",
+    build_status: "Build Status",
+    current_commit: "Current Commit",
+    uptime: "Uptime",
+    memory_usage: "Memory",
+    cpu_usage: "CPU",
+    workspace_size: "Workspace size",
+    players: "Players online",
+    build_history: "Build History",
+    refresh_status: "Refresh Status",
+    refreshing: "Refreshing...",
+    auto_refresh_enabled: "Auto refresh enabled",
+    no_builds: "No build records",
+    console: "Server Console",
+    build_output: "Build Output",
+    build_now: "Build Now",
+    running: "Running",
+    stopped: "Stopped",
+    building: "Building",
+    success: "Success",
+    failed: "Failed",
+    pending: "Pending",
+    tests_failed: "Tests Failed",
+    rolled_back: "Rolled Back",
+    skipped: "Skipped",
+    queued: "Queued",
+    cancelled: "Cancelled",
+    waiting_for_players: "Waiting for players",
+    pending_deploy: "{} waiting to deploy",
+    healthy: "Responding",
+    unhealthy: "Not responding",
+    remaining: "~{} min remaining",
+    attempt: "attempt {attempt} of {max}",
+    trigger_poll: "poll",
+    trigger_webhook: "webhook",
+    trigger_manual: "manual",
+    rolled_back_after: "Rolled back to {sha} after {failed} failed",
+    error_noun: ["error", "errors"],
+    warning_noun: ["warning", "warnings"],
+    changed_files: "Changed files ({})",
+    list_separator: ", ",
+    theme_dark: "🌙 Dark",
+    theme_light: "☀️ Light",
+    enter_api_token: "Enter API token",
+    build_request_failed: "Build request failed",
+    dry_run_banner: "Dry-run mode: checking for updates only, nothing will be built or started",
+    paused_banner: "Deployments paused: new commits are queued but not built, and the service is not restarted automatically",
+    paused_until: "(resumes automatically at {})",
+    pause_monitoring: "Pause Monitoring",
+    resume_monitoring: "Resume Monitoring",
+};
+
+const JA: Labels = Labels {
+    code: "ja",
+    html_lang: "ja",
+    name: "日本語",
+    subtitle: "自動デプロイ監視システム",
+    server_info: "テスト用 Pumpkin Minecraft サーバー（zly2006 運営）- 接続先: slv4.starlight.cool:3082",
+    running_status: "稼働状態",
+    build_status: "ビルド状態",
+    current_commit: "現在のコミット",
+    uptime: "稼働時間",
+    memory_usage: "メモリ使用量",
+    cpu_usage: "CPU 使用率",
+    workspace_size: "ワークスペースのサイズ",
+    players: "オンラインのプレイヤー",
+    build_history: "ビルド履歴",
+    refresh_status: "状態を更新",
+    refreshing: "更新中...",
+    auto_refresh_enabled: "自動更新が有効",
+    no_builds: "ビルド記録はありません",
+    console: "サーバーコンソール",
+    build_output: "ビルド出力",
+    build_now: "今すぐビルド",
+    running: "稼働中",
+    stopped: "停止",
+    building: "ビルド中",
+    success: "成功",
+    failed: "失敗",
+    pending: "待機中",
+    tests_failed: "テスト失敗",
+    rolled_back: "ロールバック済み",
+    skipped: "スキップ",
+    queued: "待ち行列",
+    cancelled: "キャンセル済み",
+    waiting_for_players: "プレイヤーの退出待ち",
+    pending_deploy: "{} のデプロイ待ち",
+    healthy: "応答あり",
+    unhealthy: "応答なし",
+    remaining: "残り約 {} 分",
+    attempt: "{attempt}/{max} 回目の試行",
+    trigger_poll: "定期チェック",
+    trigger_webhook: "Webhook",
+    trigger_manual: "手動",
+    rolled_back_after: "{failed} のビルド失敗後、{sha} にロールバック",
+    error_noun: ["件のエラー", "件のエラー"],
+    warning_noun: ["件の警告", "件の警告"],
+    changed_files: "変更されたファイル（{}）",
+    list_separator: "、",
+    theme_dark: "🌙 ダーク",
+    theme_light: "☀️ ライト",
+    enter_api_token: "API トークンを入力してください",
+    build_request_failed: "ビルドのリクエストに失敗しました",
+    dry_run_banner: "ドライランモード：更新の確認のみ行い、ビルドや起動は行いません",
+    paused_banner: "デプロイを一時停止中：新しいコミットは待ち行列に追加されるだけで、ビルドやサービスの自動再起動は行いません",
+    paused_until: "（{} に自動再開）",
+    pause_monitoring: "監視を一時停止",
+    resume_monitoring: "監視を再開",
+};
+
+const DE: Labels = Labels {
+    code: "de",
+    html_lang: "de",
+    name: "Deutsch",
+    subtitle: "Überwachung automatisierter Deployments",
+    server_info: "Pumpkin-Minecraft-Testserver von zly2006 - Verbinden mit: slv4.starlight.cool:3082",
+    running_status: "Betriebsstatus",
+    build_status: "Build-Status",
+    current_commit: "Aktueller Commit",
+    uptime: "Laufzeit",
+    memory_usage: "Arbeitsspeicher",
+    cpu_usage: "CPU-Auslastung",
+    workspace_size: "Größe des Arbeitsbereichs",
+    players: "Spieler online",
+    build_history: "Build-Verlauf",
+    refresh_status: "Status aktualisieren",
+    refreshing: "Aktualisiere...",
+    auto_refresh_enabled: "Automatische Aktualisierung aktiv",
+    no_builds: "Keine Builds vorhanden",
+    console: "Serverkonsole",
+    build_output: "Build-Ausgabe",
+    build_now: "Jetzt bauen",
+    running: "Läuft",
+    stopped: "Gestoppt",
+    building: "Wird gebaut",
+    success: "Erfolgreich",
+    failed: "Fehlgeschlagen",
+    pending: "Wartend",
+    tests_failed: "Tests fehlgeschlagen",
+    rolled_back: "Zurückgesetzt",
+    skipped: "Übersprungen",
+    queued: "In Warteschlange",
+    cancelled: "Abgebrochen",
+    waiting_for_players: "Wartet auf leeren Server",
+    pending_deploy: "{} wartet auf Deployment",
+    healthy: "Antwortet",
+    unhealthy: "Antwortet nicht",
+    remaining: "noch ca. {} Min.",
+    attempt: "Versuch {attempt} von {max}",
+    trigger_poll: "Abfrage",
+    trigger_webhook: "Webhook",
+    trigger_manual: "manuell",
+    rolled_back_after: "Nach fehlgeschlagenem Build von {failed} auf {sha} zurückgesetzt",
+    error_noun: ["Fehler", "Fehler"],
+    warning_noun: ["Warnung", "Warnungen"],
+    changed_files: "Geänderte Dateien ({})",
+    list_separator: ", ",
+    theme_dark: "🌙 Dunkel",
+    theme_light: "☀️ Hell",
+    enter_api_token: "API-Token eingeben",
+    build_request_failed: "Build-Anfrage fehlgeschlagen",
+    dry_run_banner: "Testlauf: Es wird nur nach Updates gesucht, nichts wird gebaut oder gestartet",
+    paused_banner: "Deployments pausiert: Neue Commits werden nur eingereiht, nicht gebaut, und der Dienst wird nicht automatisch neu gestartet",
+    paused_until: "(wird um {} automatisch fortgesetzt)",
+    pause_monitoring: "Überwachung pausieren",
+    resume_monitoring: "Überwachung fortsetzen",
+};
+
+// 语言切换菜单的顺序
+pub const LANGUAGES: [&Labels; 4] = [&ZH, &EN, &JA, &DE];
+
+// 未知的语言代码使用英文
+pub fn labels(lang: &str) -> &'static Labels {
+    LANGUAGES
+        .into_iter()
+        .find(|labels| labels.code.eq_ignore_ascii_case(lang))
+        .unwrap_or(&EN)
+}
+
+impl Labels {
+    // 例如 "2 个错误，1 个警告" 或 "1 error, 2 warnings"
+    pub fn diagnostics_summary(&self, errors: usize, warnings: usize) -> String {
+        let noun = |nouns: [&'static str; 2], count: usize| nouns[usize::from(count != 1)];
+        format!(
+            "{} {}{}{} {}",
+            errors,
+            noun(self.error_noun, errors),
+            self.list_separator,
+            warnings,
+            noun(self.warning_noun, warnings)
+        )
+    }
+}
@@ -1,21 +1,40 @@
 mod types;
+mod config_env;
+mod console;
 mod github;
 mod build;
+mod queue;
+mod process;
 mod storage;
+mod database;
+mod metrics;
+mod notify;
+mod diagnostics;
+mod health;
+mod i18n;
+mod version;
 mod web;
 
 use anyhow::Result;
 use std::sync::Arc;
-use tokio::sync::RwLock;
+use tokio::sync::{mpsc, RwLock};
+use tokio::task::JoinSet;
 use tokio::time::{sleep, Duration};
-use tracing::{info, error, warn};
-use clap::Parser;
+use tracing::{debug, info, info_span, error, warn, Instrument};
+use clap::{Parser, ValueEnum};
 
-use types::{Config, BuildStatusType};
-use github::GitHubMonitor;
+use types::{BuildStatus, BuildStatusType, BuildTrigger, Config, ControlCommand, GitHubCommit, GitHubConfig, GitHubErrorKind, GitHubErrorStatus, HealthState, QueuePolicy, StorageBackendKind};
+use github::{GitHubMonitor, RateLimited};
 use build::BuildManager;
-use storage::Storage;
-use web::WebServer;
+use queue::{BuildQueue, QueuedBuild};
+use console::Console;
+use storage::{JsonFileBackend, SharedStore, Storage, StorageBackend, Store};
+use database::Database;
+use metrics::Metrics;
+use notify::Notifier;
+use health::{query_server_status, HealthChecker};
+use process::ResourceSampler;
+use web::{RepoState, WebServer};
 
 #[derive(Parser)]
 #[command(name = "pumpkin-monitor")]
@@ -23,42 +42,65 @@ use web::WebServer;
 struct Args {
     #[arg(short, long, default_value = "config.toml")]
     config: String,
+
+    /// 只检查更新并记录将要构建的提交，不克隆、构建或启动服务
+    #[arg(long)]
+    dry_run: bool,
+
+    /// 日志格式，json 每行输出一个 JSON 对象，包含所在 span 的字段
+    #[arg(long, value_enum, env = "RUST_LOG_FORMAT", default_value = "text")]
+    log_format: LogFormat,
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+enum LogFormat {
+    Text,
+    Json,
 }
 
 #[tokio::main]
 async fn main() -> Result<()> {
-    // 初始化日志
-    tracing_subscriber::fmt()
-        .with_env_filter("pumpkin_monitor=info,tower_http=debug")
-        .init();
+    let args = Args::parse();
+    let started_at = chrono::Utc::now();
 
-    let _args = Args::parse();
+    // 初始化日志
+    let filter = "pumpkin_monitor=info,tower_http=debug";
+    match args.log_format {
+        LogFormat::Text => tracing_subscriber::fmt().with_env_filter(filter).init(),
+        LogFormat::Json => tracing_subscriber::fmt().json().with_env_filter(filter).init(),
+    }
     
-    // 加载配置
-    let config = Config::load()?;
-    info!("Configuration loaded successfully");
-
-    // 初始化组件
-    let mut github_monitor = GitHubMonitor::new(config.clone());
-    let mut build_manager = BuildManager::new(config.clone());
+    info!("pumpkin-monitor {} ({})", version::VERSION, version::GIT_COMMIT);
 
-    // 确保工作空间存在
-    build_manager.ensure_workspace().await?;
-
-    // 准备workspace配置
-    build_manager.prepare_workspace_config().await?;
-
-    // 初始化存储 - 将数据文件放在workspace中
-    let workspace_data_file = std::path::Path::new(&config.build.workspace_dir)
-        .join(&config.storage.data_file);
-    let storage = Arc::new(RwLock::new(Storage::new(workspace_data_file.to_string_lossy().to_string()).await?));
-    info!("Storage initialized in workspace: {:?}", workspace_data_file);
+    // 加载配置
+    let config = Config::load_from(std::path::Path::new(&args.config))?;
+    info!("Configuration loaded from {:?}", config.source_path);
+    if args.dry_run {
+        warn!("Dry-run mode: new commits are only logged, nothing will be cloned, built or started");
+    }
 
-    // 检查并清理可能存在的旧进程
-    build_manager.prepare_for_start(&storage).await?;
+    // 每个仓库各自启动一组监控、构建和存储
+    let mut tasks = JoinSet::new();
+    let mut repos = Vec::new();
+    let mut shutdown_targets = Vec::new();
+    let metrics = Metrics::default();
+    for repo in &config.github {
+        let (repo_state, build_manager) = start_repo(&config, repo, &metrics, args.dry_run, &mut tasks).await?;
+        shutdown_targets.push((build_manager, repo_state.storage.clone()));
+        repos.push(repo_state);
+    }
 
     // 启动 Web 服务器
-    let web_server = WebServer::new(storage.clone())?;
+    let web_server = WebServer::new(
+        repos,
+        config.server.console_token.clone(),
+        config.server.auth(),
+        &config.server.allowed_origins,
+        config.static_dir(),
+        metrics,
+        args.dry_run,
+        started_at,
+    )?;
     let addr = format!("{}:{}", config.server.host, config.server.port);
     
     info!("Starting web server on {}", addr);
@@ -70,94 +112,331 @@ async fn main() -> Result<()> {
         }
     });
 
+    info!("Pumpkin Monitor started successfully");
+    info!("Web interface available at: http://{}", addr);
+
+    // 等待任一任务完成
+    tokio::select! {
+        _ = server_handle => {
+            warn!("Web server stopped");
+        }
+        _ = tasks.join_next() => {
+            warn!("Monitor stopped");
+        }
+        _ = shutdown_signal() => {}
+    }
+
+    info!("Shutting down...");
+
+    // 先停止监控任务并等待其结束，避免状态监控或部署在关闭过程中重新拉起服务
+    tasks.abort_all();
+    while tasks.join_next().await.is_some() {}
+
+    for (mut build_manager, storage) in shutdown_targets {
+        info!("Stopping server before exit");
+        if let Some(graceful) = build_manager.stop_current_process().await? {
+            let mut storage_guard = storage.write().await;
+            let mut status = storage_guard.get_system_status();
+            status.last_stop_graceful = Some(graceful);
+            status.process_pid = None;
+            status.process_started_at = None;
+            storage_guard.update_system_status(status).await?;
+            storage_guard.set_service_stopped().await?;
+        }
+        storage.write().await.flush().await?;
+    }
+
+    Ok(())
+}
+
+// 等待 Ctrl+C 或 SIGTERM（systemd 停止服务时发送）
+async fn shutdown_signal() {
+    #[cfg(unix)]
+    {
+        use tokio::signal::unix::{signal, SignalKind};
+
+        match signal(SignalKind::terminate()) {
+            Ok(mut sigterm) => {
+                tokio::select! {
+                    _ = tokio::signal::ctrl_c() => info!("Received Ctrl+C, shutting down"),
+                    _ = sigterm.recv() => info!("Received SIGTERM, shutting down"),
+                }
+                return;
+            }
+            Err(e) => warn!("Failed to listen for SIGTERM: {}", e),
+        }
+    }
+
+    let _ = tokio::signal::ctrl_c().await;
+    info!("Received Ctrl+C, shutting down");
+}
+
+// 初始化单个仓库的组件，并把它的状态监控和主监控任务加入 tasks
+async fn start_repo(
+    config: &Config,
+    repo: &GitHubConfig,
+    metrics: &Metrics,
+    dry_run: bool,
+    tasks: &mut JoinSet<()>,
+) -> Result<(RepoState, BuildManager)> {
+    let span = info_span!("repo", repo = repo.key());
+    let workspace = config.repo_workspace(repo);
+
+    // 初始化组件
+    let console = Console::new(workspace.join("server.log"), config.runtime.max_service_log_bytes);
+    let mut github_monitor = GitHubMonitor::new(repo.clone(), config.runtime.max_retries);
+    let build_manager = BuildManager::new(config.clone(), repo.clone(), console.clone());
+
+    // 确保工作空间存在
+    build_manager.ensure_workspace().await?;
+
+    // 准备workspace配置
+    build_manager.prepare_workspace_config().await?;
+
+    // 初始化存储 - 将数据文件放在workspace中
+    let workspace_data_file = workspace.join(&config.storage.data_file);
+    let data_file = workspace_data_file.to_string_lossy().to_string();
+    let backend: Box<dyn StorageBackend> = match config.storage.backend {
+        StorageBackendKind::Json => Box::new(JsonFileBackend::new(data_file)),
+        StorageBackendKind::Sqlite => Box::new(Database::new(&data_file).await?),
+    };
+    let storage: SharedStore = Arc::new(RwLock::new(Storage::new(backend, config.storage.clone(), metrics.repo(repo.key())).await?));
+    info!("Storage ({:?}) for {} initialized in workspace: {:?}", config.storage.backend, repo.key(), workspace_data_file);
+
+    // 从存储中恢复上次检查到的提交，避免重启后重复构建
+    if let Some(sha) = restored_last_commit(&*storage.read().await) {
+        info!("Last seen commit for {}: {}", repo.key(), sha);
+        github_monitor.set_last_commit(Some(sha));
+    }
+
+    // 检查并清理可能存在的旧进程；dry-run 时不碰正在运行的服务
+    if !dry_run {
+        build_manager.prepare_for_start(&storage).await?;
+    }
+
+    // Web 接口通过控制通道向监控循环发送命令
+    let (control_tx, control_rx) = mpsc::channel::<ControlCommand>(16);
+
+    let repo_state = RepoState {
+        name: repo.key().to_string(),
+        storage: storage.clone(),
+        console,
+        github: repo.clone(),
+        control_tx,
+        build_output: build_manager.build_output(),
+        build_canceller: build_manager.build_canceller(),
+        deploy_gate: build_manager.deploy_gate(),
+    };
+
     // 运行状态监控任务 - 每秒检查一次
+    let notifier = Notifier::new(config.notifications.clone(), repo.key());
     let storage_clone_status = storage.clone();
-    let mut build_manager_clone = BuildManager::new(config.clone());
-    let status_monitor_handle = tokio::spawn(async move {
-        loop {
-            match status_monitor_iteration(&mut build_manager_clone, &storage_clone_status).await {
-                Ok(()) => {
-                    // 状态监控成功，无需日志
-                }
-                Err(e) => {
-                    warn!("Status monitor iteration failed: {}", e);
+    let notifier_status = notifier.clone();
+    let mut build_manager_clone = build_manager.clone();
+    let mut health_checker = HealthChecker::new(config.healthcheck.clone(), config.runtime.startup_grace_period);
+    let mut resource_sampler = ResourceSampler::new();
+    // 状态监控会在服务停止时重新启动它，dry-run 时不运行
+    if !dry_run {
+        tasks.spawn(async move {
+            loop {
+                match status_monitor_iteration(&mut build_manager_clone, &storage_clone_status, &notifier_status, &mut health_checker, &mut resource_sampler).await {
+                    Ok(()) => {
+                        // 状态监控成功，无需日志
+                    }
+                    Err(e) => {
+                        warn!("Status monitor iteration failed: {}", e);
+                    }
                 }
-            }
             
-            // 每秒检查一次
-            sleep(Duration::from_secs(1)).await;
-        }
-    });
+                // 每秒检查一次
+                sleep(Duration::from_secs(1)).await;
+            }
+        }.instrument(span.clone()));
+    }
 
-    // 主监控循环 - 检查更新和构建
-    let storage_clone = storage.clone();
-    let monitor_handle = tokio::spawn(async move {
-        let mut retry_count = 0;
-        
-        loop {
-            match monitor_iteration(&mut github_monitor, &mut build_manager, &storage_clone).await {
-                Ok(()) => {
-                    retry_count = 0;
-                    info!("Monitor iteration completed successfully");
+    // 定期通过 Server List Ping 查询在线玩家和 MOTD，失败时只清空显示，不影响健康检查
+    if let Some(port) = config.healthcheck.tcp_port.filter(|_| !dry_run) {
+        let storage = storage.clone();
+        let interval = config.healthcheck.status_interval;
+        let timeout = Duration::from_secs(config.healthcheck.timeout);
+        tasks.spawn(async move {
+            loop {
+                let server_info = match query_server_status(port, timeout).await {
+                    Ok(info) => Some(info),
+                    Err(e) => {
+                        debug!("Server List Ping failed: {:#}", e);
+                        None
+                    }
+                };
+                if let Err(e) = storage.write().await.set_server_info(server_info).await {
+                    warn!("Failed to record server status: {}", e);
                 }
-                Err(e) => {
-                    retry_count += 1;
-                    error!("Monitor iteration failed (attempt {}): {}", retry_count, e);
-                    
-                    if retry_count >= config.runtime.max_retries {
-                        error!("Max retries reached, continuing with next iteration");
-                        retry_count = 0;
+                sleep(interval).await;
+            }
+        }.instrument(span.clone()));
+    }
+
+    // 定期清理工作区并记录其大小，dry-run 时不删除任何文件
+    if !dry_run {
+        let build_manager = build_manager.clone();
+        let storage = storage.clone();
+        let interval = Duration::from_secs(config.maintenance.interval_hours * 3600);
+        tasks.spawn(async move {
+            loop {
+                match build_manager.run_maintenance().await {
+                    Ok(report) => {
+                        info!(
+                            "Workspace maintenance reclaimed {} bytes, workspace now uses {} bytes",
+                            report.reclaimed_bytes, report.workspace_size_bytes
+                        );
+                        if let Err(e) = storage.write().await.set_workspace_size(report.workspace_size_bytes).await {
+                            warn!("Failed to record workspace size: {}", e);
+                        }
                     }
+                    Err(e) => warn!("Workspace maintenance failed: {}", e),
                 }
+                sleep(interval).await;
             }
+        }.instrument(span.clone()));
+    }
 
-            // 等待下次检查
-            sleep(Duration::from_secs(config.github.check_interval)).await;
-        }
-    });
+    // 主监控循环 - 检查更新和构建
+    tasks.spawn(run_monitor(
+        repo.poll_interval(),
+        BuildQueue::new(config.build.queue_policy, config.build.max_queue_depth),
+        github_monitor,
+        build_manager.clone(),
+        storage,
+        notifier,
+        control_rx,
+        dry_run,
+    ).instrument(span));
 
-    info!("Pumpkin Monitor started successfully");
-    info!("Web interface available at: http://{}", addr);
+    Ok((repo_state, build_manager))
+}
 
-    // 等待任一任务完成
-    tokio::select! {
-        _ = server_handle => {
-            warn!("Web server stopped");
-        }
-        _ = monitor_handle => {
-            warn!("Monitor stopped");
+// 重启后轮询以此为基准，分支最新提交没有变化时不会被当作新提交；
+// 旧版本的数据文件没有 last_seen_commit，依次退回到 last_built_commit 和成功部署时记录的 current_commit
+fn restored_last_commit(storage: &dyn Store) -> Option<String> {
+    storage.get_last_seen_commit()
+        .or_else(|| storage.get_last_built_commit())
+        .or_else(|| {
+            let status = storage.get_system_status();
+            let deployed = matches!(status.build_status, BuildStatusType::Success | BuildStatusType::RolledBack);
+            status.current_commit.filter(|_| deployed)
+        })
+}
+
+// 检查到的提交变化时保存；队列中还有待构建的提交时不保存，重启后会重新发现它们
+async fn save_last_seen_commit(github_monitor: &GitHubMonitor, queue: &BuildQueue, storage: &SharedStore) {
+    if !queue.is_empty() {
+        return;
+    }
+    if let Err(e) = storage.write().await.set_last_seen_commit(github_monitor.last_commit()).await {
+        warn!("Failed to save last seen commit: {}", e);
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
+async fn run_monitor(
+    poll_interval: Duration,
+    mut queue: BuildQueue,
+    mut github_monitor: GitHubMonitor,
+    mut build_manager: BuildManager,
+    storage: SharedStore,
+    notifier: Notifier,
+    mut control_rx: mpsc::Receiver<ControlCommand>,
+    dry_run: bool,
+) {
+    loop {
+        let mut wait = poll_interval;
+
+        // 网络错误已经在 GitHubMonitor 中重试过，这里只记录失败原因，等到下次检查
+        let github_error = match monitor_iteration(&mut github_monitor, &mut build_manager, &storage, &mut queue, dry_run).await {
+            Ok(()) => {
+                wait = github_monitor.poll_delay(wait);
+                info!("Monitor iteration completed successfully");
+                None
+            }
+            Err(e) => {
+                let kind = github::classify_error(&e);
+                if let Some(rate_limited) = e.downcast_ref::<RateLimited>() {
+                    // 配额耗尽时一直等到重置时间
+                    let until_reset = (rate_limited.reset_at - chrono::Utc::now())
+                        .to_std()
+                        .unwrap_or_default();
+                    wait = wait.max(until_reset);
+                    warn!("GitHub API rate limited, next check in {}s", wait.as_secs());
+                } else if kind == GitHubErrorKind::Config {
+                    error!("Cannot check for updates, fix the [github] configuration: {}", e);
+                } else {
+                    error!("Monitor iteration failed: {}", e);
+                }
+                Some(GitHubErrorStatus { kind, message: e.to_string(), at: chrono::Utc::now() })
+            }
+        };
+
+        process_queue(&mut queue, &mut github_monitor, &mut build_manager, &storage, &notifier, &mut control_rx).await;
+        if !dry_run {
+            save_last_seen_commit(&github_monitor, &queue, &storage).await;
         }
-        _ = status_monitor_handle => {
-            warn!("Status monitor stopped");
+
+        // 记录 GitHub API 配额和检查失败的原因，供 /api/status 展示
+        if let Err(e) = update_github_status(&github_monitor, github_error, &storage).await {
+            warn!("Failed to update GitHub status: {}", e);
         }
-        _ = tokio::signal::ctrl_c() => {
-            info!("Received Ctrl+C, shutting down");
+
+        // 等待下次检查，期间处理来自 Web 接口的控制命令
+        let next_check = tokio::time::Instant::now() + wait;
+        loop {
+            tokio::select! {
+                _ = tokio::time::sleep_until(next_check) => break,
+                Some(command) = control_rx.recv() => {
+                    if dry_run {
+                        log_dry_run_command(&command);
+                        continue;
+                    }
+                    handle_control_command(command, &mut github_monitor, &mut build_manager, &storage, &notifier, &mut queue).await;
+                    process_queue(&mut queue, &mut github_monitor, &mut build_manager, &storage, &notifier, &mut control_rx).await;
+                    save_last_seen_commit(&github_monitor, &queue, &storage).await;
+                }
+            }
         }
     }
-
-    info!("Shutting down...");
-    Ok(())
 }
 
+#[tracing::instrument(name = "poll", skip_all, fields(commit = tracing::field::Empty))]
 async fn monitor_iteration(
     github_monitor: &mut GitHubMonitor,
     build_manager: &mut BuildManager,
-    storage: &Arc<RwLock<Storage>>,
+    storage: &SharedStore,
+    queue: &mut BuildQueue,
+    dry_run: bool,
 ) -> Result<()> {
-    // 更新系统状态
-    let is_running = build_manager.is_process_running();
-    let current_status = {
-        let storage_guard = storage.read().await;
-        storage_guard.get_system_status()
-    };
-    
-    let mut new_status = current_status.clone();
-    new_status.is_running = is_running;
-    new_status.last_check = chrono::Utc::now();
-    
-    {
+    // 更新系统状态；读取和写回在同一把锁内完成，避免覆盖状态监控任务刚写入的 PID
+    let (is_running, mut paused) = {
         let mut storage_guard = storage.write().await;
-        storage_guard.update_system_status(new_status.clone()).await?;
+        let is_running = build_manager.is_process_running();
+        let mut new_status = storage_guard.get_system_status();
+        new_status.is_running = is_running;
+        new_status.last_check = chrono::Utc::now();
+        let paused = new_status.paused;
+        storage_guard.update_system_status(new_status).await?;
+        (is_running, paused)
+    };
+
+    let pause_expired = storage.read().await
+        .get_system_status()
+        .pause_until
+        .is_some_and(|until| until <= chrono::Utc::now());
+    if paused && pause_expired {
+        info!("Pause expired, resuming automatic deployments");
+        storage.write().await.set_paused(false, None).await?;
+        resume_queue(queue, storage).await?;
+        paused = false;
+    }
+    if paused {
+        info!("Deployments are paused, new commits are only queued");
     }
 
     // 检查系统完整性
@@ -169,95 +448,542 @@ async fn monitor_iteration(
           repo_cloned, binary_built, service_running);
 
     // 检查新提交
-    let mut needs_rebuild = false;
-    let mut target_commit = None;
+    let previous_commit = github_monitor.last_commit();
 
     if let Some(commit) = github_monitor.check_for_updates().await? {
+        tracing::Span::current().record("commit", commit.sha.as_str());
         info!("New commit detected: {} by {}", commit.sha, commit.author);
-        needs_rebuild = true;
-        target_commit = Some(commit);
+
+        // 按顺序构建时补上两次检查之间推送的所有提交；跟踪标签时只构建标签指向的提交
+        let commits = match &previous_commit {
+            Some(base) if queue.policy() == QueuePolicy::Sequential && !github_monitor.follows_tags() => {
+                github_monitor.commits_between(base, &commit).await?
+            }
+            _ => vec![commit],
+        };
+        // 每个提交与前一个提交比较变更的文件，第一个提交与上次检查到的提交比较
+        let mut base = previous_commit;
+        for mut commit in commits {
+            let skip_reason = github_monitor.skip_reason(base.as_deref(), &mut commit).await;
+            base = Some(commit.sha.clone());
+            if dry_run {
+                match skip_reason {
+                    Some(reason) => info!("Dry run: would skip {} ({})", commit.sha, reason),
+                    None => info!("Dry run: would build {}", commit.sha),
+                }
+                continue;
+            }
+            enqueue_build(queue, storage, commit, uuid::Uuid::new_v4(), BuildTrigger::Poll, skip_reason).await?;
+        }
+        return Ok(());
+    }
+
+    // 暂停期间不因缺少构建产物而构建
+    if paused {
+        return Ok(());
+    }
+
+    // 即使没有新提交，也要检查系统状态；队列中已有待构建的提交时无需另行构建
+    // 注意：不再在这里处理服务重启，由状态监控任务负责
+    let needs_rebuild = if !repo_cloned {
+        info!("Repository not cloned, need to clone");
+        true
+    } else if !binary_built {
+        info!("Binary not built, need to build");
+        true
     } else {
-        // 即使没有新提交，也要检查系统状态
-        if !repo_cloned {
-            info!("Repository not cloned, need to clone");
-            needs_rebuild = true;
-        } else if !binary_built {
-            info!("Binary not built, need to build");
-            needs_rebuild = true;
+        false
+    };
+
+    if needs_rebuild && queue.is_empty() {
+        // 没有新提交但需要重建，获取当前最新提交信息
+        let Some(commit) = github_monitor.get_latest_commit().await? else {
+            error!("Cannot get latest commit information");
+            return Err(anyhow::anyhow!("Failed to get latest commit"));
+        };
+        tracing::Span::current().record("commit", commit.sha.as_str());
+
+        if dry_run {
+            info!("Dry run: would rebuild for {}", commit.sha);
+            return Ok(());
         }
-        // 注意：不再在这里处理服务重启，由状态监控任务负责
+        // 没有可用的构建产物时即使提交可以跳过也要构建
+        enqueue_build(queue, storage, commit, uuid::Uuid::new_v4(), BuildTrigger::Poll, None).await?;
     }
 
-    // 如果需要重建或者有新提交
-    if needs_rebuild {
-        let commit = if let Some(c) = target_commit {
-            c
-        } else {
-            // 如果没有新提交但需要重建，获取当前最新提交信息
-            match github_monitor.get_latest_commit().await? {
-                Some(c) => c,
-                None => {
-                    error!("Cannot get latest commit information");
-                    return Err(anyhow::anyhow!("Failed to get latest commit"));
+    Ok(())
+}
+
+// 加入构建队列，被合并或挤出队列的提交记录为已跳过的构建，保证每个提交都有记录
+async fn enqueue_build(
+    queue: &mut BuildQueue,
+    storage: &SharedStore,
+    commit: GitHubCommit,
+    build_id: uuid::Uuid,
+    trigger: BuildTrigger,
+    skip_reason: Option<String>,
+) -> Result<()> {
+    if let Some(reason) = skip_reason {
+        return record_skipped_commit(storage, &commit, build_id, trigger, &reason).await;
+    }
+
+    let sha = commit.sha.clone();
+    // 被取消的提交在手动构建或出现更新的提交之前不再自动构建
+    if storage.read().await.get_system_status().cancelled_commit.as_deref() == Some(sha.as_str()) {
+        info!("Build of {} was cancelled, not rebuilding it automatically", sha);
+        return Ok(());
+    }
+    if queue.contains(&sha) {
+        info!("Commit {} is already queued", sha);
+        return Ok(());
+    }
+
+    let mut record = BuildStatus::for_commit(build_id, &commit);
+    record.trigger = trigger;
+    record.status = BuildStatusType::Queued;
+    let skipped = queue.push(QueuedBuild { build_id, commit, trigger });
+    info!("Queued commit {} for build", sha);
+
+    let mut storage_guard = storage.write().await;
+    storage_guard.save_build_status(record).await?;
+    let reason = match queue.policy() {
+        QueuePolicy::Coalesce => format!("Skipped: superseded by newer commit {}", sha),
+        QueuePolicy::Sequential => "Skipped: build queue is full".to_string(),
+    };
+    record_dropped_builds(&mut *storage_guard, skipped, &reason).await?;
+
+    publish_queue(queue, &mut *storage_guard).await
+}
+
+// 从暂停中恢复时只构建最新的提交，其余排队的提交记录为已停止
+async fn resume_queue(queue: &mut BuildQueue, storage: &SharedStore) -> Result<()> {
+    let skipped = queue.keep_newest();
+    let Some(newest) = queue.queued_commits().pop() else {
+        return Ok(());
+    };
+    info!("Resuming with queued commit {}", newest);
+    let mut storage_guard = storage.write().await;
+    let reason = format!("Skipped: superseded by newer commit {}", newest);
+    record_dropped_builds(&mut *storage_guard, skipped, &reason).await?;
+    publish_queue(queue, &mut *storage_guard).await
+}
+
+async fn record_dropped_builds(storage: &mut dyn Store, builds: Vec<QueuedBuild>, reason: &str) -> Result<()> {
+    for build in builds {
+        warn!("Not building {}: {}", build.commit.sha, reason);
+        let mut record = BuildStatus::for_commit(build.build_id, &build.commit);
+        record.trigger = build.trigger;
+        record.status = BuildStatusType::Stopped;
+        record.finish_at(record.started_at);
+        record.error_message = Some(reason.to_string());
+        storage.save_build_status(record).await?;
+    }
+    Ok(())
+}
+
+// 提交信息带有跳过标记或没有需要关注的文件变更时不构建，只留下一条 Skipped 记录；
+// 重启后轮询可能再次看到同一个提交，已有记录时不重复添加
+async fn record_skipped_commit(
+    storage: &SharedStore,
+    commit: &GitHubCommit,
+    build_id: uuid::Uuid,
+    trigger: BuildTrigger,
+    reason: &str,
+) -> Result<()> {
+    let mut storage_guard = storage.write().await;
+    let already_recorded = storage_guard
+        .get_latest_builds(usize::MAX)
+        .iter()
+        .any(|build| build.commit_sha == commit.sha && build.status == BuildStatusType::Skipped);
+    if already_recorded {
+        return Ok(());
+    }
+
+    info!("Skipping build of {}: {}", commit.sha, reason);
+    let mut record = BuildStatus::for_commit(build_id, commit);
+    record.trigger = trigger;
+    record.status = BuildStatusType::Skipped;
+    record.finish_at(record.started_at);
+    record.error_message = Some(format!("Skipped: {}", reason));
+    storage_guard.save_build_status(record).await
+}
+
+// 把队列内容写入系统状态，供 /api/status 展示
+async fn publish_queue(queue: &BuildQueue, storage: &mut dyn Store) -> Result<()> {
+    let mut status = storage.get_system_status();
+    let queued_commits = queue.queued_commits();
+    if status.queued_commits != queued_commits {
+        status.queue_depth = queued_commits.len();
+        status.queued_commits = queued_commits;
+        storage.update_system_status(status).await?;
+    }
+    Ok(())
+}
+
+// 依次构建队列中的提交，构建期间收到的控制命令在每次构建结束后处理；暂停期间提交留在队列中
+async fn process_queue(
+    queue: &mut BuildQueue,
+    github_monitor: &mut GitHubMonitor,
+    build_manager: &mut BuildManager,
+    storage: &SharedStore,
+    notifier: &Notifier,
+    control_rx: &mut mpsc::Receiver<ControlCommand>,
+) {
+    loop {
+        if storage.read().await.get_system_status().paused {
+            break;
+        }
+        let Some(build) = queue.pop() else {
+            break;
+        };
+        if let Err(e) = publish_queue(queue, &mut *storage.write().await).await {
+            warn!("Failed to update build queue status: {}", e);
+        }
+
+        match deploy_commit(build_manager, storage, notifier, &build.commit, build.build_id, build.trigger).await {
+            // 已回滚或被取消的提交不会自动重试
+            Ok(BuildStatusType::Success | BuildStatusType::RolledBack | BuildStatusType::Cancelled) => {}
+            Ok(_) => {
+                // 构建失败且没有后续提交时回退到上次成功构建的提交，下一轮检查会重试
+                if queue.is_empty() {
+                    github_monitor.set_last_commit(storage.read().await.get_last_built_commit());
                 }
             }
-        };
+            Err(e) => error!("Build {} of {} failed: {}", build.build_id, build.commit.sha, e),
+        }
+
+        while let Ok(command) = control_rx.try_recv() {
+            handle_control_command(command, github_monitor, build_manager, storage, notifier, queue).await;
+        }
+    }
+}
 
-        // 更新构建状态
+// 构建并部署指定提交，记录构建结果并更新系统状态
+async fn deploy_commit(
+    build_manager: &mut BuildManager,
+    storage: &SharedStore,
+    notifier: &Notifier,
+    commit: &GitHubCommit,
+    build_id: uuid::Uuid,
+    trigger: BuildTrigger,
+) -> Result<BuildStatusType> {
+    // 更新构建状态，构建记录先以 Building 状态保存，便于通过接口查看进度；
+    // 构建可能持续很久，期间暂停、健康检查等写入的状态不能被覆盖，所以每次都在锁内重新读取，只修改部署相关的字段
+    let previous_commit = {
+        let mut storage_guard = storage.write().await;
+        let mut new_status = storage_guard.get_system_status();
+        let previous_commit = new_status.current_commit.clone();
         new_status.build_status = BuildStatusType::Building;
         new_status.current_commit = Some(commit.sha.clone());
-        {
-            let mut storage_guard = storage.write().await;
-            storage_guard.update_system_status(new_status.clone()).await?;
-        }
+        new_status.cancelled_commit = None;
+        storage_guard.update_system_status(new_status).await?;
+        let mut record = BuildStatus::for_commit(build_id, commit);
+        record.trigger = trigger;
+        storage_guard.save_build_status(record).await?;
+        previous_commit
+    };
 
-        // 重启服务
-        let (build_result, new_pid) = build_manager.restart_service(&commit).await?;
-        
-        // 保存构建状态
-        {
-            let mut storage_guard = storage.write().await;
-            storage_guard.save_build_status(build_result.clone()).await?;
+    // 重启服务
+    let restart = build_manager.restart_service(commit, build_id).await?;
+    let mut build_result = restart.build_status;
+    build_result.trigger = trigger;
+    let new_pid = restart.pid;
+    
+    let mut storage_guard = storage.write().await;
+    storage_guard.save_build_status(build_result.clone()).await?;
+    let mut new_status = storage_guard.get_system_status();
+    new_status.last_stop_graceful = build_manager.last_stop_graceful();
+
+    match build_result.status {
+        BuildStatusType::Success => {
+            info!("Service restarted successfully for commit: {}", commit.sha);
+            
+            new_status.build_status = BuildStatusType::Success;
+            new_status.current_commit = Some(commit.sha.clone());
+            if let Some(pid) = new_pid {
+                new_status.process_pid = Some(pid);
+                new_status.process_started_at = build_manager.process_start_time(pid);
+            }
+            storage_guard.update_system_status(new_status).await?;
+            storage_guard.set_service_started().await?;
+            storage_guard.set_last_built_commit(commit.sha.clone()).await?;
         }
+        BuildStatusType::RolledBack => {
+            error!("New build failed to start: {:?}", build_result.error_message);
+            notifier.build_failed(&build_result);
 
-        match build_result.status {
-            BuildStatusType::Success => {
-                info!("Service restarted successfully for commit: {}", commit.sha);
-                
-                new_status.build_status = BuildStatusType::Success;
-                if let Some(pid) = new_pid {
-                    new_status.process_pid = Some(pid);
-                }
-                let mut storage_guard = storage.write().await;
-                storage_guard.update_system_status(new_status).await?;
-                storage_guard.set_service_started().await?;
+            // 记录实际运行的提交，优先使用构建历史中的完整 SHA
+            let running_commit = restart.rolled_back_to.map(|short_sha| {
+                storage_guard
+                    .get_latest_builds(usize::MAX)
+                    .into_iter()
+                    .find(|b| b.status == BuildStatusType::Success && b.commit_sha.starts_with(&short_sha))
+                    .map(|b| b.commit_sha)
+                    .unwrap_or(short_sha)
+            });
+            new_status.build_status = BuildStatusType::RolledBack;
+            new_status.current_commit = running_commit;
+            new_status.process_pid = new_pid;
+            new_status.process_started_at = new_pid.and_then(|pid| build_manager.process_start_time(pid));
+            new_status.is_running = true;
+            new_status.started_at = Some(chrono::Utc::now());
+            storage_guard.update_system_status(new_status).await?;
+        }
+        BuildStatusType::Cancelled => {
+            // 取消发生在停止旧进程之前，正在运行的服务不受影响
+            new_status.build_status = BuildStatusType::Cancelled;
+            new_status.cancelled_commit = Some(commit.sha.clone());
+            if build_manager.is_process_running() {
+                new_status.current_commit = previous_commit;
             }
-            _ => {
-                error!("Failed to restart service: {:?}", build_result.error_message);
-                
-                new_status.build_status = BuildStatusType::Failed;
+            storage_guard.update_system_status(new_status).await?;
+        }
+        _ => {
+            error!("Failed to restart service: {:?}", build_result.error_message);
+            notifier.build_failed(&build_result);
+            
+            new_status.build_status = build_result.status.clone();
+            // 构建或测试失败时旧版本服务不会被停止，继续保持运行状态
+            if build_manager.is_process_running() {
+                new_status.current_commit = previous_commit;
+                storage_guard.update_system_status(new_status).await?;
+            } else {
                 new_status.process_pid = None;
-                let mut storage_guard = storage.write().await;
+                new_status.process_started_at = None;
                 storage_guard.update_system_status(new_status).await?;
                 storage_guard.set_service_stopped().await?;
             }
         }
     }
+    drop(storage_guard);
+
+    // 构建失败时重新构建上次成功的提交，避免之后的自动重启使用不可用的构建产物
+    if matches!(build_result.status, BuildStatusType::Failed | BuildStatusType::TestsFailed) && build_manager.auto_rollback() {
+        let last_good = storage.read().await.get_last_built_commit();
+        if let Some(last_good) = last_good.filter(|sha| *sha != commit.sha) {
+            if rollback_to_commit(build_manager, storage, notifier, &last_good, &commit.sha, trigger).await? {
+                return Ok(BuildStatusType::RolledBack);
+            }
+        }
+    }
 
+    Ok(build_result.status)
+}
+
+// 重新构建之前成功的提交，作为一条单独的构建记录保存，返回是否回滚成功
+async fn rollback_to_commit(
+    build_manager: &mut BuildManager,
+    storage: &SharedStore,
+    notifier: &Notifier,
+    sha: &str,
+    failed_sha: &str,
+    trigger: BuildTrigger,
+) -> Result<bool> {
+    warn!("Rolling back to {} after {} failed", sha, failed_sha);
+    let build_id = uuid::Uuid::new_v4();
+    let mut record = BuildStatus::new(build_id, sha.to_string());
+    record.rollback_of = Some(failed_sha.to_string());
+    record.trigger = trigger;
+    let previous = storage.read().await
+        .get_latest_builds(usize::MAX)
+        .into_iter()
+        .find(|b| b.commit_sha == sha && b.commit_message.is_some());
+    if let Some(previous) = &previous {
+        record.copy_commit_info(previous);
+    }
+    storage.write().await.save_build_status(record.clone()).await?;
+
+    let (mut result, new_pid) = match build_manager.rebuild_commit(sha, build_id).await {
+        Ok(result) => result,
+        Err(e) => {
+            let mut result = BuildStatus::new(build_id, sha.to_string());
+            result.status = BuildStatusType::Failed;
+            result.error_message = Some(format!("Rollback failed: {}", e));
+            result.finish();
+            (result, None)
+        }
+    };
+    result.rollback_of = Some(failed_sha.to_string());
+    result.trigger = trigger;
+    result.copy_commit_info(&record);
+
+    let mut storage_guard = storage.write().await;
+    storage_guard.save_build_status(result.clone()).await?;
+    if result.status != BuildStatusType::Success {
+        error!("Rollback to {} failed: {:?}", sha, result.error_message);
+        notifier.build_failed(&result);
+        return Ok(false);
+    }
+
+    info!("Rolled back to {} after {} failed", sha, failed_sha);
+    let mut status = storage_guard.get_system_status();
+    status.build_status = BuildStatusType::RolledBack;
+    status.current_commit = Some(sha.to_string());
+    if let Some(pid) = new_pid {
+        status.process_pid = Some(pid);
+        status.process_started_at = build_manager.process_start_time(pid);
+        status.is_running = true;
+        status.started_at = Some(chrono::Utc::now());
+    }
+    storage_guard.update_system_status(status).await?;
+    Ok(true)
+}
+
+async fn handle_control_command(
+    command: ControlCommand,
+    github_monitor: &mut GitHubMonitor,
+    build_manager: &mut BuildManager,
+    storage: &SharedStore,
+    notifier: &Notifier,
+    queue: &mut BuildQueue,
+) {
+    match command {
+        ControlCommand::Build { build_id, sha } => {
+            // 手动构建不经过队列，立即执行
+            info!("Manual build requested for {}", sha.as_deref().unwrap_or("latest commit"));
+            // 指定提交的构建（例如部署旧版本）不改变轮询的基准，否则下一次检查会把分支最新提交当作新提交重新部署
+            let pinned = sha.is_some();
+            match manual_build(github_monitor, build_manager, storage, notifier, build_id, sha).await {
+                Ok(_) if pinned => {}
+                Ok((commit, BuildStatusType::Success)) => github_monitor.set_last_commit(Some(commit)),
+                Ok((_, BuildStatusType::RolledBack | BuildStatusType::Cancelled)) => {}
+                Ok(_) => {
+                    if queue.is_empty() {
+                        github_monitor.set_last_commit(storage.read().await.get_last_built_commit());
+                    }
+                }
+                Err(e) => error!("Manual build {} failed: {}", build_id, e),
+            }
+        }
+        ControlCommand::Push { build_id, mut commit } => {
+            let already_built = storage.read().await.get_last_built_commit().as_deref() == Some(commit.sha.as_str());
+            if already_built {
+                info!("Commit {} from webhook is already deployed, skipping", commit.sha);
+                return;
+            }
+
+            info!("Webhook push received for commit {} by {}", commit.sha, commit.author);
+            // 轮询不应再把这个提交当作新提交
+            let previous_commit = github_monitor.last_commit();
+            github_monitor.set_last_commit(Some(commit.sha.clone()));
+            let skip_reason = github_monitor.skip_reason(previous_commit.as_deref(), &mut commit).await;
+            if let Err(e) = enqueue_build(queue, storage, commit, build_id, BuildTrigger::Webhook, skip_reason).await {
+                error!("Failed to queue webhook build {}: {}", build_id, e);
+            }
+        }
+        ControlCommand::Resume => {
+            if let Err(e) = resume_queue(queue, storage).await {
+                error!("Failed to resume build queue: {}", e);
+            }
+        }
+    }
+}
+
+// dry-run 时 Web 接口的构建请求只记录日志
+fn log_dry_run_command(command: &ControlCommand) {
+    match command {
+        ControlCommand::Build { sha, .. } => {
+            info!("Dry run: ignoring manual build of {}", sha.as_deref().unwrap_or("latest commit"));
+        }
+        ControlCommand::Push { commit, .. } => {
+            info!("Dry run: would build {} from webhook push", commit.sha);
+        }
+        ControlCommand::Resume => info!("Dry run: deployments resumed"),
+    }
+}
+
+async fn manual_build(
+    github_monitor: &mut GitHubMonitor,
+    build_manager: &mut BuildManager,
+    storage: &SharedStore,
+    notifier: &Notifier,
+    build_id: uuid::Uuid,
+    sha: Option<String>,
+) -> Result<(String, BuildStatusType)> {
+    let commit = match &sha {
+        Some(sha) => github_monitor.get_commit(sha).await,
+        None => github_monitor.get_latest_commit().await,
+    };
+
+    let commit = match commit {
+        Ok(Some(commit)) => commit,
+        result => {
+            let error_message = match result {
+                Err(e) => format!("Failed to fetch commit information: {}", e),
+                _ => format!("Commit {} not found", sha.as_deref().unwrap_or("latest")),
+            };
+
+            // 记录失败的构建，让调用方能通过构建 ID 看到原因
+            let mut build = BuildStatus::new(build_id, sha.unwrap_or_default());
+            build.status = BuildStatusType::Failed;
+            build.trigger = BuildTrigger::Manual;
+            build.finish_at(build.started_at);
+            build.error_message = Some(error_message.clone());
+
+            let mut storage_guard = storage.write().await;
+            storage_guard.save_build_status(build).await?;
+            return Err(anyhow::anyhow!(error_message));
+        }
+    };
+
+    let status = deploy_commit(build_manager, storage, notifier, &commit, build_id, BuildTrigger::Manual).await?;
+    Ok((commit.sha, status))
+}
+
+async fn update_github_status(
+    github_monitor: &GitHubMonitor,
+    github_error: Option<GitHubErrorStatus>,
+    storage: &SharedStore,
+) -> Result<()> {
+    let rate_limit = github_monitor.rate_limit_status();
+    let mut storage_guard = storage.write().await;
+    let mut status = storage_guard.get_system_status();
+    if status.github_rate_limit != rate_limit || status.github_error != github_error {
+        status.github_rate_limit = rate_limit;
+        status.github_error = github_error;
+        storage_guard.update_system_status(status).await?;
+    }
     Ok(())
 }
 
+// 部署等待玩家离开时在状态中显示，等待结束后由 deploy_commit 写入最终状态
+async fn sync_pending_deploy(build_manager: &BuildManager, storage: &SharedStore) -> Result<()> {
+    let mut storage_guard = storage.write().await;
+    // 在锁内读取，避免覆盖 deploy_commit 刚写入的最终状态
+    let pending_commit = build_manager.deploy_gate().waiting_commit();
+    let mut status = storage_guard.get_system_status();
+    if status.pending_commit == pending_commit {
+        return Ok(());
+    }
+    if pending_commit.is_some() {
+        status.build_status = BuildStatusType::WaitingForPlayers;
+    } else if status.build_status == BuildStatusType::WaitingForPlayers {
+        status.build_status = BuildStatusType::Building;
+    }
+    status.pending_commit = pending_commit;
+    storage_guard.update_system_status(status).await
+}
+
 async fn status_monitor_iteration(
     build_manager: &mut BuildManager,
-    storage: &Arc<RwLock<Storage>>,
+    storage: &SharedStore,
+    notifier: &Notifier,
+    health: &mut HealthChecker,
+    sampler: &mut ResourceSampler,
 ) -> Result<()> {
     let is_running = build_manager.is_process_running();
+    sync_pending_deploy(build_manager, storage).await?;
     
     // 获取当前状态
     let current_status = {
         let storage_guard = storage.read().await;
         storage_guard.get_system_status()
     };
+
+    // 暂停期间不通知、不做健康检查，也不自动重启；运行状态的变化在恢复后照常处理
+    if current_status.paused {
+        return Ok(());
+    }
     
     // 如果运行状态发生变化，更新存储
     if current_status.is_running != is_running {
@@ -272,24 +998,57 @@ async fn status_monitor_iteration(
             info!("Service started and is now running");
         } else {
             warn!("Service stopped unexpectedly");
+            // 部署过程中会主动停止服务，只在非构建期间通知
+            if current_status.build_status != BuildStatusType::Building {
+                notifier.service_crashed(current_status.current_commit.as_deref());
+            }
         }
         
         let mut storage_guard = storage.write().await;
         storage_guard.update_system_status(new_status.clone()).await?;
         
+        health.reset();
         if !is_running {
             storage_guard.set_service_stopped().await?;
             // 清除PID信息
             let mut updated_status = new_status.clone();
             updated_status.process_pid = None;
+            updated_status.process_started_at = None;
             storage_guard.update_system_status(updated_status).await?;
         } else {
             storage_guard.set_service_started().await?;
         }
     }
     
-    // 如果服务没有运行且没有正在构建，尝试重启
-    if !is_running && current_status.build_status != BuildStatusType::Building {
+    // 进程存活但健康检查连续失败时重启服务；构建进行中由部署负责重启
+    if is_running && current_status.build_status != BuildStatusType::Building {
+        match health.check(current_status.started_at).await {
+            Some(HealthState::Unhealthy) => match build_manager.try_lock_build() {
+                Some(_build_guard) => {
+                    restart_unhealthy_service(build_manager, storage, notifier, &current_status).await?;
+                    health.reset();
+                }
+                None => info!("Build already in progress, not restarting unresponsive service"),
+            },
+            Some(state) => storage.write().await.set_health(state).await?,
+            None => {}
+        }
+    }
+
+    // 采样服务进程的资源占用，进程在采样时退出不视为错误
+    let pid = build_manager.process_id();
+    if sampler.due(pid) {
+        let usage = sampler.sample(pid);
+        storage.write().await.set_resource_usage(
+            usage.map(|usage| usage.memory_bytes),
+            usage.and_then(|usage| usage.cpu_percent),
+        ).await?;
+        storage.write().await.set_free_space(build_manager.free_space_mb()).await?;
+    }
+
+    // 如果服务没有运行且没有正在构建，尝试重启；部署过程中会短暂停止服务，持有构建锁期间不重启
+    let build_guard = build_manager.try_lock_build();
+    if !is_running && current_status.build_status != BuildStatusType::Building && build_guard.is_some() {
         let repo_cloned = build_manager.is_repo_cloned();
         let binary_built = build_manager.is_binary_built();
         
@@ -301,6 +1060,7 @@ async fn status_monitor_iteration(
                     info!("Service restarted successfully with PID: {}", pid);
                     let mut new_status = current_status.clone();
                     new_status.process_pid = Some(pid);
+                    new_status.process_started_at = build_manager.process_start_time(pid);
                     new_status.is_running = true;
                     
                     let mut storage_guard = storage.write().await;
@@ -324,3 +1084,107 @@ async fn status_monitor_iteration(
     
     Ok(())
 }
+
+async fn restart_unhealthy_service(
+    build_manager: &mut BuildManager,
+    storage: &SharedStore,
+    notifier: &Notifier,
+    current_status: &types::SystemStatus,
+) -> Result<()> {
+    warn!("Service is running but not responding, restarting");
+    notifier.service_unhealthy(current_status.current_commit.as_deref());
+    storage.write().await.set_health(HealthState::Unhealthy).await?;
+
+    let graceful = build_manager.stop_current_process().await?;
+    sleep(build_manager.restart_delay()).await;
+
+    let mut storage_guard = storage.write().await;
+    let mut status = storage_guard.get_system_status();
+    status.last_stop_graceful = graceful.or(status.last_stop_graceful);
+    match build_manager.start_new_process() {
+        Ok(pid) => {
+            info!("Service restarted with PID: {}", pid);
+            status.process_pid = Some(pid);
+            status.process_started_at = build_manager.process_start_time(pid);
+            storage_guard.update_system_status(status).await?;
+            storage_guard.set_service_started().await?;
+            // 保持不健康状态，直到下一次检查成功
+            storage_guard.set_health(HealthState::Unhealthy).await?;
+        }
+        Err(e) => {
+            warn!("Failed to restart unresponsive service: {}", e);
+            status.process_pid = None;
+            status.process_started_at = None;
+            storage_guard.update_system_status(status).await?;
+            storage_guard.set_service_stopped().await?;
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const HEAD_SHA: &str = "6dcb09b5b57875f334f61aebed695e2e4193db5e";
+
+    // 分支最新提交固定为 HEAD_SHA 的 GitHub 接口
+    async fn serve_head() -> String {
+        let commit = include_str!("../tests/fixtures/github_commit.json");
+        let app = axum::Router::new().route(
+            "/repos/Pumpkin-MC/Pumpkin/commits/main",
+            axum::routing::get(move || async move { ([("content-type", "application/json")], commit) }),
+        );
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}", listener.local_addr().unwrap());
+        tokio::spawn(async move { axum::serve(listener, app).await });
+        url
+    }
+
+    async fn open_storage(config: &Config) -> SharedStore {
+        let data_file = config.repo_workspace(&config.github[0]).join(&config.storage.data_file);
+        let backend = Box::new(JsonFileBackend::new(data_file.to_string_lossy().into_owned()));
+        let storage = Storage::new(backend, config.storage.clone(), Metrics::default().repo("Pumpkin")).await.unwrap();
+        Arc::new(RwLock::new(storage))
+    }
+
+    #[tokio::test]
+    async fn restart_with_unchanged_head_does_not_rebuild() {
+        let workspace = std::env::temp_dir().join(format!("pumpkin-monitor-test-{}", uuid::Uuid::new_v4()));
+        let mut config: Config = toml::from_str(include_str!("../config.example.toml")).unwrap();
+        config.build.workspace_dir = workspace.to_string_lossy().into_owned();
+        config.github[0].api_base_url = serve_head().await;
+        let repo = config.github[0].clone();
+
+        // 仓库已克隆、构建产物存在，不因缺少产物而重建
+        std::fs::create_dir_all(workspace.join("Pumpkin/.git")).unwrap();
+        std::fs::create_dir_all(workspace.join("bin")).unwrap();
+        std::fs::write(workspace.join("bin").join(&config.build.binary_name), b"").unwrap();
+
+        // 重启前：检查到分支最新提交并保存
+        {
+            let storage = open_storage(&config).await;
+            let mut github_monitor = GitHubMonitor::new(repo.clone(), 0);
+            github_monitor.set_last_commit(Some(HEAD_SHA.to_string()));
+            let queue = BuildQueue::new(config.build.queue_policy, config.build.max_queue_depth);
+            save_last_seen_commit(&github_monitor, &queue, &storage).await;
+        }
+
+        // 重启后：从存储恢复，分支最新提交没有变化
+        let storage = open_storage(&config).await;
+        let mut github_monitor = GitHubMonitor::new(repo.clone(), 0);
+        let restored = restored_last_commit(&*storage.read().await);
+        assert_eq!(restored.as_deref(), Some(HEAD_SHA));
+        github_monitor.set_last_commit(restored);
+
+        let console = Console::new(workspace.join("server.log"), config.runtime.max_service_log_bytes);
+        let mut build_manager = BuildManager::new(config.clone(), repo, console);
+        let mut queue = BuildQueue::new(config.build.queue_policy, config.build.max_queue_depth);
+        monitor_iteration(&mut github_monitor, &mut build_manager, &storage, &mut queue, false).await.unwrap();
+
+        assert!(queue.is_empty(), "unchanged head was queued for a rebuild");
+        assert!(storage.read().await.get_latest_builds(usize::MAX).is_empty());
+
+        std::fs::remove_dir_all(&workspace).unwrap();
+    }
+}
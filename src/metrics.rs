@@ -0,0 +1,162 @@
+use std::collections::BTreeMap;
+use std::fmt::Write;
+use std::sync::{Arc, Mutex};
+
+use crate::types::{elapsed_between, BuildStatus, BuildStatusType, SystemStatus};
+
+// 构建耗时直方图的桶上限，秒
+const DURATION_BUCKETS: [u64; 9] = [30, 60, 120, 300, 600, 900, 1200, 1800, 3600];
+
+// 每个构建结果都输出计数，即使为零，便于告警规则引用
+const BUILD_RESULTS: [BuildStatusType; 7] = [
+    BuildStatusType::Success,
+    BuildStatusType::Failed,
+    BuildStatusType::TestsFailed,
+    BuildStatusType::RolledBack,
+    BuildStatusType::Stopped,
+    BuildStatusType::Skipped,
+    BuildStatusType::Cancelled,
+];
+
+#[derive(Default)]
+struct RepoMetricsData {
+    builds_total: BTreeMap<&'static str, u64>,
+    duration_buckets: [u64; DURATION_BUCKETS.len()],
+    duration_sum: u64,
+    duration_count: u64,
+    is_running: bool,
+    last_check: Option<chrono::DateTime<chrono::Utc>>,
+    memory_bytes: Option<u64>,
+    cpu_percent: Option<f32>,
+    workspace_size_bytes: Option<u64>,
+}
+
+// 单个仓库的指标，由该仓库的存储在保存构建记录和系统状态时更新
+#[derive(Clone, Default)]
+pub struct RepoMetrics {
+    data: Arc<Mutex<RepoMetricsData>>,
+}
+
+impl RepoMetrics {
+    pub fn record_build(&self, build: &BuildStatus) {
+        let mut data = self.data.lock().unwrap();
+        *data.builds_total.entry(build.status.as_str()).or_default() += 1;
+
+        // 被跳过的构建没有实际运行，不计入耗时
+        if matches!(build.status, BuildStatusType::Stopped | BuildStatusType::Skipped | BuildStatusType::Cancelled) {
+            return;
+        }
+        if let Some(duration) = build.duration_seconds.map(|secs| secs as u64) {
+            for (bucket, count) in DURATION_BUCKETS.iter().zip(data.duration_buckets.iter_mut()) {
+                if duration <= *bucket {
+                    *count += 1;
+                }
+            }
+            data.duration_sum += duration;
+            data.duration_count += 1;
+        }
+    }
+
+    pub fn set_status(&self, status: &SystemStatus) {
+        let mut data = self.data.lock().unwrap();
+        data.is_running = status.is_running;
+        data.last_check = Some(status.last_check);
+        data.memory_bytes = status.memory_bytes;
+        data.cpu_percent = status.cpu_percent;
+        data.workspace_size_bytes = status.workspace_size_bytes;
+    }
+}
+
+// 所有仓库的指标，按 Prometheus 文本格式输出
+#[derive(Clone, Default)]
+pub struct Metrics {
+    repos: Arc<Mutex<BTreeMap<String, RepoMetrics>>>,
+}
+
+impl Metrics {
+    pub fn repo(&self, name: &str) -> RepoMetrics {
+        self.repos.lock().unwrap().entry(name.to_string()).or_default().clone()
+    }
+
+    pub fn render(&self) -> String {
+        let repos = self.repos.lock().unwrap();
+        let now = chrono::Utc::now();
+        let mut out = String::new();
+
+        out.push_str("# HELP pumpkin_monitor_builds_total Finished builds by result.\n");
+        out.push_str("# TYPE pumpkin_monitor_builds_total counter\n");
+        for (name, repo) in repos.iter() {
+            let data = repo.data.lock().unwrap();
+            for result in BUILD_RESULTS.iter().map(BuildStatusType::as_str) {
+                let count = data.builds_total.get(result).copied().unwrap_or(0);
+                let _ = writeln!(out, "pumpkin_monitor_builds_total{{repo=\"{}\",result=\"{}\"}} {}", escape_label(name), result, count);
+            }
+        }
+
+        out.push_str("# HELP pumpkin_monitor_server_running Whether the server process is running (1) or not (0).\n");
+        out.push_str("# TYPE pumpkin_monitor_server_running gauge\n");
+        for (name, repo) in repos.iter() {
+            let data = repo.data.lock().unwrap();
+            let _ = writeln!(out, "pumpkin_monitor_server_running{{repo=\"{}\"}} {}", escape_label(name), data.is_running as u8);
+        }
+
+        out.push_str("# HELP pumpkin_monitor_server_memory_bytes Resident memory of the server process.\n");
+        out.push_str("# TYPE pumpkin_monitor_server_memory_bytes gauge\n");
+        for (name, repo) in repos.iter() {
+            let data = repo.data.lock().unwrap();
+            if let Some(memory_bytes) = data.memory_bytes {
+                let _ = writeln!(out, "pumpkin_monitor_server_memory_bytes{{repo=\"{}\"}} {}", escape_label(name), memory_bytes);
+            }
+        }
+
+        out.push_str("# HELP pumpkin_monitor_server_cpu_percent CPU usage of the server process, 100 per fully used core.\n");
+        out.push_str("# TYPE pumpkin_monitor_server_cpu_percent gauge\n");
+        for (name, repo) in repos.iter() {
+            let data = repo.data.lock().unwrap();
+            if let Some(cpu_percent) = data.cpu_percent {
+                let _ = writeln!(out, "pumpkin_monitor_server_cpu_percent{{repo=\"{}\"}} {}", escape_label(name), cpu_percent);
+            }
+        }
+
+        out.push_str("# HELP pumpkin_monitor_workspace_size_bytes Disk space used by the workspace, measured by the periodic maintenance.\n");
+        out.push_str("# TYPE pumpkin_monitor_workspace_size_bytes gauge\n");
+        for (name, repo) in repos.iter() {
+            let data = repo.data.lock().unwrap();
+            if let Some(workspace_size_bytes) = data.workspace_size_bytes {
+                let _ = writeln!(out, "pumpkin_monitor_workspace_size_bytes{{repo=\"{}\"}} {}", escape_label(name), workspace_size_bytes);
+            }
+        }
+
+        out.push_str("# HELP pumpkin_monitor_build_duration_seconds Duration of finished builds.\n");
+        out.push_str("# TYPE pumpkin_monitor_build_duration_seconds histogram\n");
+        for (name, repo) in repos.iter() {
+            let data = repo.data.lock().unwrap();
+            let name = escape_label(name);
+            for (bucket, count) in DURATION_BUCKETS.iter().zip(data.duration_buckets.iter()) {
+                let _ = writeln!(out, "pumpkin_monitor_build_duration_seconds_bucket{{repo=\"{}\",le=\"{}\"}} {}", name, bucket, count);
+            }
+            let _ = writeln!(out, "pumpkin_monitor_build_duration_seconds_bucket{{repo=\"{}\",le=\"+Inf\"}} {}", name, data.duration_count);
+            let _ = writeln!(out, "pumpkin_monitor_build_duration_seconds_sum{{repo=\"{}\"}} {}", name, data.duration_sum);
+            let _ = writeln!(out, "pumpkin_monitor_build_duration_seconds_count{{repo=\"{}\"}} {}", name, data.duration_count);
+        }
+
+        out.push_str("# HELP pumpkin_monitor_seconds_since_last_check Seconds since the last GitHub check.\n");
+        out.push_str("# TYPE pumpkin_monitor_seconds_since_last_check gauge\n");
+        for (name, repo) in repos.iter() {
+            let data = repo.data.lock().unwrap();
+            if let Some(last_check) = data.last_check {
+                let secs = elapsed_between(last_check, now).num_seconds();
+                let _ = writeln!(out, "pumpkin_monitor_seconds_since_last_check{{repo=\"{}\"}} {}", escape_label(name), secs);
+            }
+        }
+
+        out
+    }
+}
+
+fn escape_label(value: &str) -> String {
+    value
+        .replace('\\', "\\\\")
+        .replace('"', "\\\"")
+        .replace('\n', "\\n")
+}
@@ -0,0 +1,95 @@
+use reqwest::Client;
+use serde_json::json;
+use tracing::{info, warn};
+
+use crate::types::{BuildStatus, NotificationConfig, NotificationFormat, short_sha};
+
+// 错误摘要最多包含的行数和字符数，Discord 单条消息上限为 2000 字符
+const SNIPPET_LINES: usize = 15;
+const SNIPPET_CHARS: usize = 1500;
+
+// 构建失败或服务崩溃时向 Discord/Slack 的 incoming webhook 发送通知
+#[derive(Clone)]
+pub struct Notifier {
+    client: Client,
+    config: NotificationConfig,
+    repo: String,
+}
+
+impl Notifier {
+    pub fn new(config: NotificationConfig, repo: &str) -> Self {
+        Self {
+            client: Client::new(),
+            config,
+            repo: repo.to_string(),
+        }
+    }
+
+    pub fn build_failed(&self, build: &BuildStatus) {
+        let mut text = format!(
+            "❌ [{}] Build {:?} for commit {}",
+            self.repo,
+            build.status,
+            short_sha(&build.commit_sha)
+        );
+        if let Some(error) = build.error_message.as_deref().filter(|error| !error.is_empty()) {
+            text.push_str(&format!("\n```\n{}\n```", snippet(error)));
+        }
+        self.send(text);
+    }
+
+    pub fn service_crashed(&self, commit: Option<&str>) {
+        let text = format!(
+            "⚠️ [{}] Server stopped unexpectedly (commit {})",
+            self.repo,
+            commit.map(short_sha).unwrap_or("unknown")
+        );
+        self.send(text);
+    }
+
+    pub fn service_unhealthy(&self, commit: Option<&str>) {
+        let text = format!(
+            "⚠️ [{}] Server stopped responding to health checks, restarting (commit {})",
+            self.repo,
+            commit.map(short_sha).unwrap_or("unknown")
+        );
+        self.send(text);
+    }
+
+    // 在后台发送，不阻塞监控循环
+    fn send(&self, mut text: String) {
+        let Some(url) = self.config.webhook_url.clone().filter(|url| !url.is_empty()) else {
+            return;
+        };
+        if let Some(dashboard_url) = &self.config.dashboard_url {
+            text.push_str(&format!(
+                "\nDashboard: {}/?repo={}",
+                dashboard_url.trim_end_matches('/'),
+                self.repo
+            ));
+        }
+
+        let body = match self.config.format {
+            NotificationFormat::Discord => json!({ "content": text }),
+            NotificationFormat::Slack => json!({ "text": text }),
+        };
+        let client = self.client.clone();
+        tokio::spawn(async move {
+            match client.post(&url).json(&body).send().await {
+                Ok(response) if response.status().is_success() => info!("Notification sent"),
+                Ok(response) => warn!("Notification webhook returned {}", response.status()),
+                Err(e) => warn!("Failed to send notification: {}", e),
+            }
+        });
+    }
+}
+
+// 错误信息的最后几行，过长时从开头截断
+fn snippet(error: &str) -> String {
+    let lines: Vec<&str> = error.lines().collect();
+    let text = lines[lines.len().saturating_sub(SNIPPET_LINES)..].join("\n");
+    match text.char_indices().rev().nth(SNIPPET_CHARS - 1) {
+        Some((index, _)) => format!("…{}", &text[index..]),
+        None => text,
+    }
+}
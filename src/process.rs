@@ -0,0 +1,299 @@
+use std::io;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Signal {
+    Terminate,
+    Kill,
+}
+
+// 查询进程和发送信号，BuildManager 通过它处理不是自己启动的进程
+pub trait ProcessTable: Send + Sync {
+    // 进程是否存在，属于其他用户的进程同样视为存在
+    fn is_alive(&self, pid: u32) -> bool;
+    // 进程正在运行的可执行文件，无法获取时返回 None
+    fn executable(&self, pid: u32) -> Option<PathBuf>;
+    // 进程的启动时间，Unix 时间戳（秒），无法获取时返回 None
+    fn start_time(&self, pid: u32) -> Option<u64>;
+    fn signal(&self, pid: u32, signal: Signal) -> io::Result<()>;
+}
+
+// 读取 /proc 并通过 kill(2) 发送信号；没有 /proc 的平台上无法获取可执行文件
+pub struct SystemProcessTable;
+
+#[cfg(unix)]
+impl ProcessTable for SystemProcessTable {
+    fn is_alive(&self, pid: u32) -> bool {
+        use nix::errno::Errno;
+
+        // 信号为 None 时只检查进程是否存在，没有权限时返回 EPERM
+        matches!(nix::sys::signal::kill(nix_pid(pid), None), Ok(()) | Err(Errno::EPERM))
+    }
+
+    fn executable(&self, pid: u32) -> Option<PathBuf> {
+        std::fs::read_link(format!("/proc/{}/exe", pid)).ok()
+    }
+
+    fn start_time(&self, pid: u32) -> Option<u64> {
+        process_start_time(pid)
+    }
+
+    fn signal(&self, pid: u32, signal: Signal) -> io::Result<()> {
+        use nix::sys::signal::Signal as NixSignal;
+
+        let signal = match signal {
+            Signal::Terminate => NixSignal::SIGTERM,
+            Signal::Kill => NixSignal::SIGKILL,
+        };
+        nix::sys::signal::kill(nix_pid(pid), signal).map_err(io::Error::from)
+    }
+}
+
+#[cfg(unix)]
+fn nix_pid(pid: u32) -> nix::unistd::Pid {
+    nix::unistd::Pid::from_raw(pid as i32)
+}
+
+#[cfg(not(unix))]
+impl ProcessTable for SystemProcessTable {
+    fn is_alive(&self, _pid: u32) -> bool {
+        false
+    }
+
+    fn executable(&self, _pid: u32) -> Option<PathBuf> {
+        None
+    }
+
+    fn start_time(&self, pid: u32) -> Option<u64> {
+        process_start_time(pid)
+    }
+
+    fn signal(&self, _pid: u32, _signal: Signal) -> io::Result<()> {
+        Err(io::Error::new(io::ErrorKind::Unsupported, "signals are not supported on this platform"))
+    }
+}
+
+fn process_start_time(pid: u32) -> Option<u64> {
+    let sys_pid = sysinfo::Pid::from_u32(pid);
+    let mut system = sysinfo::System::new();
+    system.refresh_processes_specifics(
+        sysinfo::ProcessesToUpdate::Some(&[sys_pid]),
+        true,
+        sysinfo::ProcessRefreshKind::nothing(),
+    );
+    system.process(sys_pid).map(|process| process.start_time())
+}
+
+// 启动时间由开机时间和时钟节拍换算而来，两次读取可能相差一秒
+const START_TIME_TOLERANCE_SECS: u64 = 2;
+
+// 按 PID 找到的进程与部署的二进制文件的关系
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ProcessMatch {
+    // 正在运行当前部署的二进制文件
+    Current,
+    // 运行的二进制文件已被新版本替换
+    Stale,
+    // PID 已被其他程序复用
+    Foreign,
+    Gone,
+    // 无法获取可执行文件，不能确认是否为本程序启动的进程
+    Unknown,
+}
+
+impl ProcessMatch {
+    // 是否为本程序部署的服务进程（当前版本或旧版本）
+    pub fn is_ours(self) -> bool {
+        matches!(self, ProcessMatch::Current | ProcessMatch::Stale)
+    }
+}
+
+// 比较进程的可执行文件和部署的二进制文件
+pub fn match_executable(processes: &dyn ProcessTable, pid: u32, deployed: &Path) -> ProcessMatch {
+    if !processes.is_alive(pid) {
+        return ProcessMatch::Gone;
+    }
+    let Some(exe) = processes.executable(pid) else {
+        return ProcessMatch::Unknown;
+    };
+
+    // 二进制文件被替换或删除后，/proc/<pid>/exe 指向原路径并带有 " (deleted)" 后缀
+    let exe = exe.to_string_lossy();
+    let (path, deleted) = match exe.strip_suffix(" (deleted)") {
+        Some(path) => (path, true),
+        None => (exe.as_ref(), false),
+    };
+    match (Path::new(path) == canonical_path(deployed), deleted) {
+        (true, false) => ProcessMatch::Current,
+        (true, true) => ProcessMatch::Stale,
+        (false, _) => ProcessMatch::Foreign,
+    }
+}
+
+// 在比较可执行文件的基础上比较记录的启动时间：重启机器后 PID 可能被复用，
+// 即使运行的是同一个二进制文件（例如由其他人手动启动），启动时间不同也不是本程序启动的进程。
+// 旧数据没有记录启动时间时只比较可执行文件
+pub fn match_process(processes: &dyn ProcessTable, pid: u32, started_at: Option<u64>, deployed: &Path) -> ProcessMatch {
+    let matched = match_executable(processes, pid, deployed);
+    if !matched.is_ours() {
+        return matched;
+    }
+    match (started_at, processes.start_time(pid)) {
+        (Some(expected), Some(actual)) if expected.abs_diff(actual) > START_TIME_TOLERANCE_SECS => ProcessMatch::Foreign,
+        _ => matched,
+    }
+}
+
+// 文件已被删除时规范化所在目录，保证仍能和 /proc 中的绝对路径比较
+fn canonical_path(path: &Path) -> PathBuf {
+    if let Ok(path) = path.canonicalize() {
+        return path;
+    }
+    match (path.parent().and_then(|dir| dir.canonicalize().ok()), path.file_name()) {
+        (Some(dir), Some(name)) => dir.join(name),
+        _ => std::path::absolute(path).unwrap_or_else(|_| path.to_path_buf()),
+    }
+}
+
+// 服务进程的资源占用
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ResourceUsage {
+    // 常驻内存，字节
+    pub memory_bytes: u64,
+    // 相对单个 CPU 核心的百分比，多线程时可能超过 100；第一次采样没有可比较的数据，为 None
+    pub cpu_percent: Option<f32>,
+}
+
+// 资源占用的采样间隔
+const SAMPLE_INTERVAL: Duration = Duration::from_secs(5);
+
+// 定期采样服务进程的内存和 CPU 占用；CPU 占用按两次采样之间的差值计算，因此需要保留 System
+pub struct ResourceSampler {
+    system: sysinfo::System,
+    last_sample: Option<Instant>,
+    last_pid: Option<u32>,
+}
+
+impl ResourceSampler {
+    pub fn new() -> Self {
+        Self {
+            system: sysinfo::System::new(),
+            last_sample: None,
+            last_pid: None,
+        }
+    }
+
+    // 距上次采样已超过采样间隔，或跟踪的进程发生了变化
+    pub fn due(&self, pid: Option<u32>) -> bool {
+        pid != self.last_pid || self.last_sample.is_none_or(|last| last.elapsed() >= SAMPLE_INTERVAL)
+    }
+
+    pub fn sample(&mut self, pid: Option<u32>) -> Option<ResourceUsage> {
+        self.last_sample = Some(Instant::now());
+        let previous_pid = std::mem::replace(&mut self.last_pid, pid);
+        let sys_pid = sysinfo::Pid::from_u32(pid?);
+
+        // 只刷新这一个进程；进程在刷新前后退出时 process() 返回 None，按未运行处理
+        self.system.refresh_processes_specifics(
+            sysinfo::ProcessesToUpdate::Some(&[sys_pid]),
+            true,
+            sysinfo::ProcessRefreshKind::nothing().with_memory().with_cpu(),
+        );
+        let process = self.system.process(sys_pid)?;
+        Some(ResourceUsage {
+            memory_bytes: process.memory(),
+            cpu_percent: (previous_pid == pid).then(|| process.cpu_usage()),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+
+    struct FakeProcess {
+        exe: Option<&'static str>,
+        start_time: Option<u64>,
+    }
+
+    // 按 PID 返回预设的可执行文件和启动时间，表中没有的进程视为不存在
+    #[derive(Default)]
+    struct FakeProcessTable(HashMap<u32, FakeProcess>);
+
+    impl FakeProcessTable {
+        fn with(mut self, pid: u32, exe: Option<&'static str>, start_time: Option<u64>) -> Self {
+            self.0.insert(pid, FakeProcess { exe, start_time });
+            self
+        }
+    }
+
+    impl ProcessTable for FakeProcessTable {
+        fn is_alive(&self, pid: u32) -> bool {
+            self.0.contains_key(&pid)
+        }
+
+        fn executable(&self, pid: u32) -> Option<PathBuf> {
+            self.0.get(&pid)?.exe.map(PathBuf::from)
+        }
+
+        fn start_time(&self, pid: u32) -> Option<u64> {
+            self.0.get(&pid)?.start_time
+        }
+
+        fn signal(&self, _pid: u32, _signal: Signal) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    // 不存在的目录，canonical_path 按原样比较
+    const DEPLOYED: &str = "/nonexistent/workspace/bin/pumpkin";
+    const STARTED_AT: u64 = 1_700_000_000;
+
+    fn matched(processes: &FakeProcessTable, started_at: Option<u64>) -> ProcessMatch {
+        match_process(processes, 42, started_at, Path::new(DEPLOYED))
+    }
+
+    #[test]
+    fn current_binary() {
+        let processes = FakeProcessTable::default().with(42, Some(DEPLOYED), Some(STARTED_AT + 1));
+        assert_eq!(matched(&processes, Some(STARTED_AT)), ProcessMatch::Current);
+        // 旧数据没有记录启动时间时只比较可执行文件
+        assert_eq!(matched(&processes, None), ProcessMatch::Current);
+    }
+
+    #[test]
+    fn replaced_binary_is_stale() {
+        let processes = FakeProcessTable::default()
+            .with(42, Some("/nonexistent/workspace/bin/pumpkin (deleted)"), Some(STARTED_AT));
+        assert_eq!(matched(&processes, Some(STARTED_AT)), ProcessMatch::Stale);
+        assert!(ProcessMatch::Stale.is_ours());
+    }
+
+    #[test]
+    fn different_executable_is_foreign() {
+        let processes = FakeProcessTable::default().with(42, Some("/usr/bin/python3"), Some(STARTED_AT));
+        assert_eq!(matched(&processes, Some(STARTED_AT)), ProcessMatch::Foreign);
+    }
+
+    #[test]
+    fn reused_pid_with_other_start_time_is_foreign() {
+        let processes = FakeProcessTable::default().with(42, Some(DEPLOYED), Some(STARTED_AT + 3600));
+        assert_eq!(matched(&processes, Some(STARTED_AT)), ProcessMatch::Foreign);
+        assert!(!ProcessMatch::Foreign.is_ours());
+    }
+
+    #[test]
+    fn missing_process_is_gone() {
+        let processes = FakeProcessTable::default().with(7, Some(DEPLOYED), Some(STARTED_AT));
+        assert_eq!(matched(&processes, Some(STARTED_AT)), ProcessMatch::Gone);
+    }
+
+    #[test]
+    fn unreadable_executable_is_unknown() {
+        let processes = FakeProcessTable::default().with(42, None, Some(STARTED_AT));
+        assert_eq!(matched(&processes, Some(STARTED_AT)), ProcessMatch::Unknown);
+        assert!(!ProcessMatch::Unknown.is_ours());
+    }
+}
@@ -0,0 +1,73 @@
+use std::collections::VecDeque;
+
+use crate::types::{BuildTrigger, GitHubCommit, QueuePolicy};
+
+pub struct QueuedBuild {
+    pub build_id: uuid::Uuid,
+    pub commit: GitHubCommit,
+    pub trigger: BuildTrigger,
+}
+
+// 等待构建的提交，构建进行中到达的提交先在这里排队
+pub struct BuildQueue {
+    policy: QueuePolicy,
+    max_depth: usize,
+    pending: VecDeque<QueuedBuild>,
+}
+
+impl BuildQueue {
+    pub fn new(policy: QueuePolicy, max_depth: usize) -> Self {
+        Self {
+            policy,
+            max_depth: max_depth.max(1),
+            pending: VecDeque::new(),
+        }
+    }
+
+    pub fn policy(&self) -> QueuePolicy {
+        self.policy
+    }
+
+    // 加入队列，返回因合并或队列已满而被跳过的构建；已在队列中的提交不会重复加入
+    pub fn push(&mut self, build: QueuedBuild) -> Vec<QueuedBuild> {
+        if self.contains(&build.commit.sha) {
+            return Vec::new();
+        }
+
+        let mut skipped = Vec::new();
+        match self.policy {
+            QueuePolicy::Coalesce => skipped.extend(self.pending.drain(..)),
+            QueuePolicy::Sequential => {
+                while self.pending.len() >= self.max_depth {
+                    skipped.extend(self.pending.pop_front());
+                }
+            }
+        }
+        self.pending.push_back(build);
+        skipped
+    }
+
+    // 只保留最新的提交，返回被丢弃的构建；从暂停中恢复时使用
+    pub fn keep_newest(&mut self) -> Vec<QueuedBuild> {
+        let newest = self.pending.pop_back();
+        let skipped = self.pending.drain(..).collect();
+        self.pending.extend(newest);
+        skipped
+    }
+
+    pub fn pop(&mut self) -> Option<QueuedBuild> {
+        self.pending.pop_front()
+    }
+
+    pub fn contains(&self, sha: &str) -> bool {
+        self.pending.iter().any(|build| build.commit.sha == sha)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.pending.is_empty()
+    }
+
+    pub fn queued_commits(&self) -> Vec<String> {
+        self.pending.iter().map(|build| build.commit.sha.clone()).collect()
+    }
+}
@@ -1,15 +1,33 @@
 use anyhow::Result;
+use async_trait::async_trait;
 use serde::{Deserialize, Serialize};
 use std::path::Path;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
 use tokio::fs;
+use tokio::io::AsyncWriteExt;
+use tokio::sync::{broadcast, watch, RwLock};
 use tracing::{info, warn};
 
-use crate::types::{BuildStatus, BuildStatusType, SystemStatus};
+use crate::metrics::RepoMetrics;
+use crate::types::{
+    elapsed_between, BuildStats, BuildStatus, BuildStatusType, BuildStreak, HealthState, ServerListInfo, StatusTransition,
+    StorageConfig, SystemStatus,
+};
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct StorageData {
     pub builds: Vec<BuildStatus>,
     pub system_status: SystemStatus,
+    // 最近一次构建成功的提交，重启后据此判断是否需要重新构建
+    #[serde(default)]
+    pub last_built_commit: Option<String>,
+    // 最近一次检查到的分支最新提交，重启后据此判断分支是否有新提交
+    #[serde(default)]
+    pub last_seen_commit: Option<String>,
+    // 系统状态的变化历史，按时间从旧到新排列，与构建记录使用相同的保留策略
+    #[serde(default)]
+    pub status_history: Vec<StatusTransition>,
 }
 
 impl Default for StorageData {
@@ -21,102 +39,580 @@ impl Default for StorageData {
                 build_status: BuildStatusType::Pending,
                 is_running: false,
                 last_check: chrono::Utc::now(),
-                uptime: None,
+                uptime_seconds: None,
                 started_at: None,
                 process_pid: None,
+                process_started_at: None,
+                paused: false,
+                pause_until: None,
+                github_rate_limit: None,
+                github_error: None,
+                last_stop_graceful: None,
+                queue_depth: 0,
+                queued_commits: Vec::new(),
+                avg_build_duration_secs: None,
+                health: HealthState::Unknown,
+                memory_bytes: None,
+                cpu_percent: None,
+                free_space_mb: None,
+                server_info: None,
+                pending_commit: None,
+                workspace_size_bytes: None,
+                cancelled_commit: None,
             },
+            last_built_commit: None,
+            last_seen_commit: None,
+            status_history: Vec::new(),
         }
     }
 }
 
-pub struct Storage {
+// 持久化后端，Storage 在内存中维护完整数据，变化时交给后端保存
+#[async_trait]
+pub trait StorageBackend: Send + Sync {
+    // 读取已保存的数据，尚无数据时返回 None
+    async fn load(&self) -> Result<Option<StorageData>>;
+    // 保存一条构建记录，pruned 为因超出保留数量而被移除的构建
+    async fn save_build(&self, build: &BuildStatus, pruned: &[uuid::Uuid], data: &StorageData) -> Result<()>;
+    // 保存系统状态和最近一次成功构建的提交
+    async fn save_state(&self, data: &StorageData) -> Result<()>;
+    // 追加一条状态历史，并删除早于 data.status_history 中第一条的记录；随后会调用 save_state
+    async fn save_status_transition(&self, transition: &StatusTransition, data: &StorageData) -> Result<()>;
+}
+
+// 将全部数据保存为一个 JSON 文件
+pub struct JsonFileBackend {
     file_path: String,
+}
+
+impl JsonFileBackend {
+    pub fn new(file_path: String) -> Self {
+        Self { file_path }
+    }
+
+    // 先写入同目录下的临时文件再重命名覆盖，进程中途被杀时原文件保持完整
+    async fn write(&self, data: &StorageData) -> Result<()> {
+        let json = serde_json::to_string_pretty(data)?;
+        let tmp_path = format!("{}.tmp", self.file_path);
+
+        let mut file = fs::File::create(&tmp_path).await?;
+        file.write_all(json.as_bytes()).await?;
+        file.sync_all().await?;
+        drop(file);
+
+        fs::rename(&tmp_path, &self.file_path).await?;
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl StorageBackend for JsonFileBackend {
+    async fn load(&self) -> Result<Option<StorageData>> {
+        if !Path::new(&self.file_path).exists() {
+            info!("Creating new data file: {}", self.file_path);
+            return Ok(None);
+        }
+
+        let content = fs::read(&self.file_path).await?;
+        match serde_json::from_slice(&content) {
+            Ok(data) => {
+                info!("Loaded existing data from {}", self.file_path);
+                Ok(Some(data))
+            }
+            Err(e) => {
+                // 保留无法解析的文件，避免下一次保存时覆盖掉全部构建历史
+                let backup = format!("{}.corrupt-{}", self.file_path, chrono::Utc::now().format("%Y%m%dT%H%M%SZ"));
+                fs::rename(&self.file_path, &backup).await?;
+                warn!("Failed to parse existing data file: {}, moved it to {} and starting with defaults", e, backup);
+                Ok(None)
+            }
+        }
+    }
+
+    async fn save_build(&self, _build: &BuildStatus, _pruned: &[uuid::Uuid], data: &StorageData) -> Result<()> {
+        self.write(data).await
+    }
+
+    async fn save_state(&self, data: &StorageData) -> Result<()> {
+        self.write(data).await
+    }
+
+    // 状态历史是数据文件的一部分，随 save_state 一起写入
+    async fn save_status_transition(&self, _transition: &StatusTransition, _data: &StorageData) -> Result<()> {
+        Ok(())
+    }
+}
+
+// 计算平均构建耗时时参考的成功构建数量
+const AVG_DURATION_BUILDS: usize = 10;
+
+// 构建历史的筛选条件，记录按时间从新到旧排列
+pub struct BuildFilter {
+    pub status: Option<BuildStatusType>,
+    pub commit_prefix: Option<String>,
+    pub offset: usize,
+    pub limit: usize,
+}
+
+// Web 接口和监控循环通过这个 trait 读写状态和构建历史，不依赖具体的存储实现
+#[async_trait]
+pub trait Store: Send + Sync {
+    // since 之后的状态历史，按时间从旧到新排列
+    fn get_status_history(&self, since: Option<chrono::DateTime<chrono::Utc>>) -> Vec<StatusTransition>;
+    // 写入尚未保存的时间字段变化，退出前调用
+    async fn flush(&mut self) -> Result<()>;
+    fn subscribe_status(&self) -> watch::Receiver<SystemStatus>;
+    fn subscribe_builds(&self) -> broadcast::Receiver<BuildStatus>;
+    async fn save_build_status(&mut self, build: BuildStatus) -> Result<()>;
+    fn get_latest_builds(&self, limit: usize) -> Vec<BuildStatus>;
+    // 按条件筛选构建历史并分页，同时返回符合条件的记录总数
+    fn query_builds(&self, filter: &BuildFilter) -> (Vec<BuildStatus>, usize);
+    fn get_build(&self, id: uuid::Uuid) -> Option<BuildStatus>;
+    // 统计构建历史，since 为 None 时统计全部记录
+    fn get_build_stats(&self, since: Option<chrono::DateTime<chrono::Utc>>) -> BuildStats;
+    // 只有 last_check 等时间字段变化时推迟写入，运行状态、构建状态等变化立即保存
+    async fn update_system_status(&mut self, status: SystemStatus) -> Result<()>;
+    fn get_system_status(&self) -> SystemStatus;
+    fn get_last_built_commit(&self) -> Option<String>;
+    async fn set_last_built_commit(&mut self, sha: String) -> Result<()>;
+    fn get_last_seen_commit(&self) -> Option<String>;
+    // 只在变化时保存
+    async fn set_last_seen_commit(&mut self, sha: Option<String>) -> Result<()>;
+    async fn set_service_started(&mut self) -> Result<()>;
+    async fn set_service_stopped(&mut self) -> Result<()>;
+    // 资源占用只更新内存中的状态并推送给页面，随下一次保存写入磁盘
+    async fn set_resource_usage(&mut self, memory_bytes: Option<u64>, cpu_percent: Option<f32>) -> Result<()>;
+    async fn set_free_space(&mut self, free_space_mb: Option<u64>) -> Result<()>;
+    async fn set_server_info(&mut self, server_info: Option<ServerListInfo>) -> Result<()>;
+    async fn set_workspace_size(&mut self, workspace_size_bytes: u64) -> Result<()>;
+    async fn set_paused(&mut self, paused: bool, until: Option<chrono::DateTime<chrono::Utc>>) -> Result<()>;
+    // 健康状态变化时才保存
+    async fn set_health(&mut self, health: HealthState) -> Result<()>;
+}
+
+// Web 接口和各个监控任务共享的存储
+pub type SharedStore = Arc<RwLock<dyn Store>>;
+
+// 构建记录推送通道的容量
+const BUILD_EVENT_CAPACITY: usize = 64;
+
+pub struct Storage {
+    backend: Box<dyn StorageBackend>,
+    config: StorageConfig,
+    metrics: RepoMetrics,
     data: StorageData,
+    // 每次保存后推送最新的系统状态，供 /api/events 订阅
+    status_tx: watch::Sender<SystemStatus>,
+    // 构建记录创建或更新时推送，供 /ws 订阅；订阅者跟不上时会跳过旧记录，不阻塞保存
+    build_tx: broadcast::Sender<BuildStatus>,
+    // 内存中的状态是否有尚未写入磁盘的时间字段变化，以及上次写入的时间
+    dirty: bool,
+    last_flush: Instant,
 }
 
 impl Storage {
-    pub async fn new(file_path: String) -> Result<Self> {
-        let data = if Path::new(&file_path).exists() {
-            let content = fs::read_to_string(&file_path).await?;
-            match serde_json::from_str(&content) {
-                Ok(data) => {
-                    info!("Loaded existing data from {}", file_path);
-                    data
-                }
-                Err(e) => {
-                    warn!("Failed to parse existing data file: {}, using default", e);
-                    StorageData::default()
-                }
-            }
-        } else {
-            info!("Creating new data file: {}", file_path);
-            StorageData::default()
-        };
+    pub async fn new(backend: Box<dyn StorageBackend>, config: StorageConfig, metrics: RepoMetrics) -> Result<Self> {
+        let data = backend.load().await?.unwrap_or_default();
 
-        let mut storage = Self { file_path, data };
+        let (status_tx, _) = watch::channel(data.system_status.clone());
+        let (build_tx, _) = broadcast::channel(BUILD_EVENT_CAPACITY);
+        let mut storage = Self {
+            backend,
+            config,
+            metrics,
+            data,
+            status_tx,
+            build_tx,
+            dirty: false,
+            last_flush: Instant::now(),
+        };
         storage.save().await?;
+        storage.stop_stale_queued().await?;
         
         Ok(storage)
     }
 
-    pub async fn save(&self) -> Result<()> {
-        let json = serde_json::to_string_pretty(&self.data)?;
-        fs::write(&self.file_path, json).await?;
+    // 构建队列只保存在内存中，监控程序重启前仍在排队的构建不会再执行
+    async fn stop_stale_queued(&mut self) -> Result<()> {
+        let stale: Vec<BuildStatus> = self.data.builds
+            .iter()
+            .filter(|b| b.status == BuildStatusType::Queued)
+            .cloned()
+            .collect();
+        for mut build in stale {
+            build.status = BuildStatusType::Stopped;
+            build.finish_at(build.started_at);
+            build.error_message = Some("Skipped: the monitor restarted before the build started".to_string());
+            self.save_build_status(build).await?;
+        }
+        Ok(())
+    }
+
+    async fn save(&mut self) -> Result<()> {
+        self.record_transition().await?;
+        self.backend.save_state(&self.data).await?;
+        self.dirty = false;
+        self.last_flush = Instant::now();
+        self.metrics.set_status(&self.data.system_status);
+        self.publish_status();
+        Ok(())
+    }
+
+    // 只在内存中更新，等到 flush_interval 到期或下一次真正的状态变化时再写入磁盘
+    async fn save_deferred(&mut self) -> Result<()> {
+        if self.last_flush.elapsed() >= self.flush_interval() {
+            return self.save().await;
+        }
+        self.dirty = true;
+        self.metrics.set_status(&self.data.system_status);
+        self.publish_status();
+        Ok(())
+    }
+
+    // 运行状态、构建状态、提交、健康状态或暂停状态与上一条历史不同时追加一条记录
+    async fn record_transition(&mut self) -> Result<()> {
+        let transition = StatusTransition::from_status(&self.data.system_status);
+        if self.data.status_history.last().is_some_and(|last| last.same_state(&transition)) {
+            return Ok(());
+        }
+        self.data.status_history.push(transition.clone());
+
+        // 与构建记录相同的保留策略：超过保留天数或数量的旧记录被删除
+        if let Some(days) = self.config.max_age_days {
+            let cutoff = chrono::Utc::now() - chrono::Duration::days(days as i64);
+            let expired = self.data.status_history.partition_point(|t| t.at < cutoff);
+            self.data.status_history.drain(..expired);
+        }
+        let excess = self.data.status_history.len().saturating_sub(self.config.max_builds);
+        self.data.status_history.drain(..excess);
+
+        self.backend.save_status_transition(&transition, &self.data).await
+    }
+
+    pub fn flush_interval(&self) -> Duration {
+        Duration::from_secs(self.config.flush_interval)
+    }
+
+    fn publish_status(&self) {
+        let status = self.current_status();
+        self.status_tx.send_if_modified(|current| {
+            if *current == status {
+                return false;
+            }
+            *current = status;
+            true
+        });
+    }
+
+    // 系统状态加上由构建历史得出的平均构建耗时
+    fn current_status(&self) -> SystemStatus {
+        let mut status = self.data.system_status.clone();
+        status.avg_build_duration_secs = self.average_build_duration();
+        status
+    }
+
+    // 最近 AVG_DURATION_BUILDS 次成功构建的平均耗时
+    fn average_build_duration(&self) -> Option<u64> {
+        let durations: Vec<u64> = self.data.builds
+            .iter()
+            .filter(|b| b.status == BuildStatusType::Success)
+            .filter_map(|b| b.duration_seconds.map(|secs| secs as u64))
+            .take(AVG_DURATION_BUILDS)
+            .collect();
+        if durations.is_empty() {
+            None
+        } else {
+            Some(durations.iter().sum::<u64>() / durations.len() as u64)
+        }
+    }
+}
+
+#[async_trait]
+impl Store for Storage {
+    fn get_status_history(&self, since: Option<chrono::DateTime<chrono::Utc>>) -> Vec<StatusTransition> {
+        let start = since.map_or(0, |since| self.data.status_history.partition_point(|t| t.at < since));
+        self.data.status_history[start..].to_vec()
+    }
+
+    async fn flush(&mut self) -> Result<()> {
+        if self.dirty {
+            self.save().await?;
+        }
         Ok(())
     }
 
-    pub async fn save_build_status(&mut self, build: BuildStatus) -> Result<()> {
+    fn subscribe_status(&self) -> watch::Receiver<SystemStatus> {
+        self.status_tx.subscribe()
+    }
+
+    fn subscribe_builds(&self) -> broadcast::Receiver<BuildStatus> {
+        self.build_tx.subscribe()
+    }
+
+    async fn save_build_status(&mut self, build: BuildStatus) -> Result<()> {
+        // 构建记录第一次带上结束时间时计入监控指标
+        let was_finished = self.data.builds.iter().any(|b| b.id == build.id && b.finished_at.is_some());
+        if build.finished_at.is_some() && !was_finished {
+            self.metrics.record_build(&build);
+        }
+
         // 移除相同 ID 的构建记录（如果存在）
         self.data.builds.retain(|b| b.id != build.id);
         
         // 添加新的构建记录
-        self.data.builds.push(build);
+        self.data.builds.push(build.clone());
         
         // 按时间排序，最新的在前面
-        self.data.builds.sort_by(|a, b| b.started_at.cmp(&a.started_at));
+        self.data.builds.sort_by_key(|b| std::cmp::Reverse(b.started_at));
         
-        // 只保留最近的100条记录
-        if self.data.builds.len() > 100 {
-            self.data.builds.truncate(100);
+        // 按保留策略清理旧的构建记录
+        let mut pruned: Vec<BuildStatus> = Vec::new();
+        if let Some(days) = self.config.max_age_days {
+            let cutoff = chrono::Utc::now() - chrono::Duration::days(days as i64);
+            let keep = self.data.builds.partition_point(|b| b.started_at >= cutoff);
+            pruned.extend(self.data.builds.drain(keep..));
+        }
+        if self.data.builds.len() > self.config.max_builds {
+            pruned.extend(self.data.builds.drain(self.config.max_builds..));
         }
         
-        self.save().await?;
+        let pruned_ids: Vec<uuid::Uuid> = pruned.iter().map(|b| b.id).collect();
+        self.backend.save_build(&build, &pruned_ids, &self.data).await?;
+
+        for build in &pruned {
+            remove_build_log(build).await;
+        }
+
+        let _ = self.build_tx.send(build);
         Ok(())
     }
 
-    pub fn get_latest_builds(&self, limit: usize) -> Vec<BuildStatus> {
+    fn get_latest_builds(&self, limit: usize) -> Vec<BuildStatus> {
         self.data.builds
             .iter()
             .take(limit)
-            .cloned()
+            .map(|b| b.clone().with_current_duration())
             .collect()
     }
 
-    pub async fn update_system_status(&mut self, status: SystemStatus) -> Result<()> {
+    fn query_builds(&self, filter: &BuildFilter) -> (Vec<BuildStatus>, usize) {
+        let matches = self.data.builds.iter().filter(|b| {
+            filter.status.as_ref().is_none_or(|status| b.status == *status)
+                && filter.commit_prefix.as_deref().is_none_or(|prefix| b.commit_sha.starts_with(prefix))
+        });
+        let mut total = 0;
+        let mut page = Vec::new();
+        for (index, build) in matches.enumerate() {
+            total += 1;
+            if index >= filter.offset && page.len() < filter.limit {
+                page.push(build.clone().with_current_duration());
+            }
+        }
+        (page, total)
+    }
+
+    fn get_build(&self, id: uuid::Uuid) -> Option<BuildStatus> {
+        self.data.builds.iter().find(|b| b.id == id).map(|b| b.clone().with_current_duration())
+    }
+
+    fn get_build_stats(&self, since: Option<chrono::DateTime<chrono::Utc>>) -> BuildStats {
+        let builds: Vec<&BuildStatus> = self.data.builds
+            .iter()
+            .filter(|b| since.is_none_or(|since| b.started_at >= since))
+            .collect();
+
+        let success_count = builds.iter().filter(|b| b.status == BuildStatusType::Success).count();
+        let failure_count = builds.iter().filter(|b| b.status.is_failure()).count();
+        let finished_count = success_count + failure_count;
+        let success_rate = if finished_count > 0 {
+            Some(success_count as f64 / finished_count as f64)
+        } else {
+            None
+        };
+
+        let mut durations: Vec<i64> = builds
+            .iter()
+            .filter_map(|b| b.finished_at.map(|finished_at| elapsed_between(b.started_at, finished_at).num_seconds()))
+            .collect();
+        durations.sort_unstable();
+
+        let avg_duration_secs = if durations.is_empty() {
+            None
+        } else {
+            Some(durations.iter().sum::<i64>() as f64 / durations.len() as f64)
+        };
+        let p95_duration_secs = if durations.is_empty() {
+            None
+        } else {
+            let index = ((durations.len() as f64 * 0.95).ceil() as usize).saturating_sub(1);
+            Some(durations[index])
+        };
+
+        // 构建记录按时间倒序排列，从最新的已完成构建开始计算连续次数
+        let mut current_streak: Option<BuildStreak> = None;
+        // 编译失败和测试失败都算作失败
+        for build in builds.iter().filter(|b| b.status == BuildStatusType::Success || b.status.is_failure()) {
+            match current_streak {
+                Some(ref mut streak) if streak.status.is_failure() == build.status.is_failure() => streak.count += 1,
+                Some(_) => break,
+                None => {
+                    current_streak = Some(BuildStreak {
+                        status: build.status.clone(),
+                        count: 1,
+                    });
+                }
+            }
+        }
+
+        let secs_since_last_failure = builds
+            .iter()
+            .find(|b| b.status.is_failure())
+            .map(|b| elapsed_between(b.finished_at.unwrap_or(b.started_at), chrono::Utc::now()).num_seconds());
+
+        BuildStats {
+            total_builds: builds.len(),
+            success_count,
+            failure_count,
+            success_rate,
+            avg_duration_secs,
+            p95_duration_secs,
+            current_streak,
+            secs_since_last_failure,
+        }
+    }
+
+    async fn update_system_status(&mut self, status: SystemStatus) -> Result<()> {
+        let unchanged = self.data.system_status.same_state(&status);
         self.data.system_status = status;
+        if unchanged {
+            self.save_deferred().await
+        } else {
+            self.save().await
+        }
+    }
+
+    fn get_system_status(&self) -> SystemStatus {
+        self.current_status().with_current_uptime()
+    }
+
+    fn get_last_built_commit(&self) -> Option<String> {
+        self.data.last_built_commit.clone()
+    }
+
+    async fn set_last_built_commit(&mut self, sha: String) -> Result<()> {
+        self.data.last_built_commit = Some(sha);
         self.save().await?;
         Ok(())
     }
 
-    pub fn get_system_status(&self) -> SystemStatus {
-        let mut status = self.data.system_status.clone();
-        status.uptime = status.started_at
-            .map(|started_at| chrono::Utc::now() - started_at)
-            .or_else(|| status.uptime);
-        status
+    fn get_last_seen_commit(&self) -> Option<String> {
+        self.data.last_seen_commit.clone()
+    }
+
+    async fn set_last_seen_commit(&mut self, sha: Option<String>) -> Result<()> {
+        if self.data.last_seen_commit == sha {
+            return Ok(());
+        }
+        self.data.last_seen_commit = sha;
+        self.save().await
     }
 
-    pub async fn set_service_started(&mut self) -> Result<()> {
+    async fn set_service_started(&mut self) -> Result<()> {
         self.data.system_status.is_running = true;
         self.data.system_status.build_status = BuildStatusType::Success;
         self.data.system_status.started_at = Some(chrono::Utc::now());
+        self.data.system_status.health = HealthState::Unknown;
         self.save().await?;
         Ok(())
     }
 
-    pub async fn set_service_stopped(&mut self) -> Result<()> {
+    async fn set_service_stopped(&mut self) -> Result<()> {
         self.data.system_status.is_running = false;
+        self.data.system_status.health = HealthState::Unknown;
         self.save().await?;
         Ok(())
     }
+
+    async fn set_resource_usage(&mut self, memory_bytes: Option<u64>, cpu_percent: Option<f32>) -> Result<()> {
+        self.data.system_status.memory_bytes = memory_bytes;
+        self.data.system_status.cpu_percent = cpu_percent;
+        self.save_deferred().await
+    }
+
+    async fn set_free_space(&mut self, free_space_mb: Option<u64>) -> Result<()> {
+        self.data.system_status.free_space_mb = free_space_mb;
+        self.save_deferred().await
+    }
+
+    async fn set_server_info(&mut self, server_info: Option<ServerListInfo>) -> Result<()> {
+        if self.data.system_status.server_info == server_info {
+            return Ok(());
+        }
+        self.data.system_status.server_info = server_info;
+        self.save_deferred().await
+    }
+
+    async fn set_workspace_size(&mut self, workspace_size_bytes: u64) -> Result<()> {
+        self.data.system_status.workspace_size_bytes = Some(workspace_size_bytes);
+        self.save().await
+    }
+
+    async fn set_paused(&mut self, paused: bool, until: Option<chrono::DateTime<chrono::Utc>>) -> Result<()> {
+        let until = until.filter(|_| paused);
+        let status = &mut self.data.system_status;
+        if status.paused == paused && status.pause_until == until {
+            return Ok(());
+        }
+        status.paused = paused;
+        status.pause_until = until;
+        self.save().await
+    }
+
+    async fn set_health(&mut self, health: HealthState) -> Result<()> {
+        if self.data.system_status.health == health {
+            return Ok(());
+        }
+        self.data.system_status.health = health;
+        self.save().await
+    }
+}
+
+// 删除被清理的构建记录对应的日志文件
+async fn remove_build_log(build: &BuildStatus) {
+    let Some(log_path) = build.log_path.as_deref() else {
+        return;
+    };
+    match fs::remove_file(log_path).await {
+        Ok(()) => {}
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => {}
+        Err(e) => warn!("Failed to remove build log {}: {}", log_path, e),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn interrupted_write_keeps_previous_file() {
+        let dir = std::env::temp_dir().join(format!("pumpkin-monitor-test-{}", uuid::Uuid::new_v4()));
+        fs::create_dir_all(&dir).await.unwrap();
+        let file_path = dir.join("data.json").to_string_lossy().into_owned();
+        let backend = JsonFileBackend::new(file_path.clone());
+
+        let data = StorageData {
+            last_built_commit: Some("abc123".to_string()),
+            ..StorageData::default()
+        };
+        backend.save_state(&data).await.unwrap();
+
+        // 模拟写到一半被杀：临时文件只有一部分内容，重命名没有发生
+        let json = serde_json::to_string_pretty(&data).unwrap();
+        fs::write(format!("{}.tmp", file_path), &json[..json.len() / 2]).await.unwrap();
+
+        let loaded = backend.load().await.unwrap().expect("previous data file should still load");
+        assert_eq!(loaded.last_built_commit.as_deref(), Some("abc123"));
+
+        fs::remove_dir_all(&dir).await.unwrap();
+    }
 }
@@ -1,52 +1,987 @@
+use anyhow::Context;
 use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+use std::time::Duration;
 
 #[derive(Debug, Clone, Deserialize)]
 pub struct Config {
     pub server: ServerConfig,
-    pub github: GitHubConfig,
+    // 支持 [github] 单仓库写法和 [[github]] 多仓库写法
+    #[serde(deserialize_with = "one_or_many")]
+    pub github: Vec<GitHubConfig>,
     pub build: BuildConfig,
     pub runtime: RuntimeConfig,
     pub storage: StorageConfig,
+    #[serde(default)]
+    pub notifications: NotificationConfig,
+    #[serde(default)]
+    pub healthcheck: HealthCheckConfig,
+    #[serde(default)]
+    pub service: ServiceConfig,
+    #[serde(default)]
+    pub maintenance: MaintenanceConfig,
+    // 配置文件的绝对路径，由 load_from 填充
+    #[serde(skip)]
+    pub source_path: PathBuf,
+}
+
+// 先读成 toml::Value 再按表或数组分别解析，保留内部的字段错误（untagged 枚举只会报告没有匹配的变体）
+fn one_or_many<'de, D, T>(deserializer: D) -> Result<Vec<T>, D::Error>
+where
+    D: serde::Deserializer<'de>,
+    T: serde::de::DeserializeOwned,
+{
+    use serde::de::Error;
+
+    match toml::Value::deserialize(deserializer)? {
+        table @ toml::Value::Table(_) => T::deserialize(table).map(|item| vec![item]).map_err(D::Error::custom),
+        toml::Value::Array(items) => items
+            .into_iter()
+            .enumerate()
+            .map(|(index, item)| T::deserialize(item).map_err(|e| D::Error::custom(format!("entry {}: {}", index, e))))
+            .collect(),
+        other => Err(D::Error::custom(format!("expected a table or an array of tables, found {}", other.type_str()))),
+    }
 }
 
 #[derive(Debug, Clone, Deserialize)]
 pub struct ServerConfig {
     pub host: String,
     pub port: u16,
+    // 允许通过控制台 WebSocket 发送命令的令牌，未设置时控制台只读
+    #[serde(default)]
+    pub console_token: Option<String>,
+    // /static 对应的静态文件目录，相对路径以配置文件所在目录为基准
+    #[serde(default = "default_static_dir")]
+    pub static_dir: String,
+    #[serde(default)]
+    pub auth: AuthConfig,
+    // [server.auth] token 的简写，两者都设置时使用 auth.token
+    #[serde(default)]
+    pub api_key: Option<String>,
+    // 允许跨域访问接口的来源，例如 "https://dashboard.example.com"；为空时允许任意来源
+    #[serde(default)]
+    pub allowed_origins: Vec<String>,
+}
+
+impl ServerConfig {
+    pub fn auth(&self) -> AuthConfig {
+        let mut auth = self.auth.clone();
+        if auth.token().is_none() {
+            auth.token = self.api_key.clone();
+        }
+        auth
+    }
+}
+
+// 接口鉴权，设置 token 后 POST 接口需要携带 Authorization: Bearer <token>
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct AuthConfig {
+    #[serde(default)]
+    pub token: Option<String>,
+    // 同时保护 GET 接口和 WebSocket，首页只返回空白框架，数据由页面凭令牌加载
+    #[serde(default)]
+    pub protect_reads: bool,
+}
+
+impl AuthConfig {
+    pub fn token(&self) -> Option<&str> {
+        self.token.as_deref().filter(|token| !token.is_empty())
+    }
+}
+
+fn default_static_dir() -> String {
+    "static".to_string()
 }
 
 #[derive(Debug, Clone, Deserialize)]
 pub struct GitHubConfig {
+    // 仓库在 Web 界面和工作空间中使用的名称，默认为 repo_name
+    #[serde(default)]
+    pub name: Option<String>,
     pub repo_owner: String,
     pub repo_name: String,
     pub branch: String,
-    pub check_interval: u64,
+    // 检查间隔，可以是秒数或 "30s"、"5m"、"500ms" 这样的时长
+    #[serde(deserialize_with = "duration_format::deserialize")]
+    pub check_interval: Duration,
+    // GitHub 访问令牌，未设置时回退到 GITHUB_TOKEN 环境变量
+    #[serde(default)]
+    pub token: Option<String>,
+    // 设置后启用 /webhook/github，用于校验 X-Hub-Signature-256 签名
+    #[serde(default)]
+    pub webhook_secret: Option<String>,
+    // 启用 webhook 后轮询只作为兜底，使用更长的检查间隔，秒
+    #[serde(default = "default_webhook_poll_interval")]
+    pub webhook_poll_interval: u64,
+    // 把构建结果写回 GitHub commit status，令牌需要 repo:status 写权限
+    #[serde(default)]
+    pub report_status: bool,
+    // 提交信息包含其中任一标记时不构建，只记录为 Skipped，不区分大小写
+    #[serde(default = "default_skip_markers")]
+    pub skip_markers: Vec<String>,
+    // 只有变更的文件匹配其中任一 glob（如 "src/**"）时才构建，为空时任何变更都构建
+    #[serde(default)]
+    pub watch_paths: Vec<String>,
+    // 变更的文件全部匹配其中的 glob（如 "docs/**"、"*.md"）时不构建，只记录为 Skipped
+    #[serde(default)]
+    pub ignore_paths: Vec<String>,
+    // 提交作者为其中之一时不构建（如机器人账号），不区分大小写
+    #[serde(default)]
+    pub ignore_authors: Vec<String>,
+    // 设置后不再跟踪分支最新提交，而是部署名称匹配该 glob（如 "v*"）的最新标签；branch 仍用于首次克隆
+    #[serde(default)]
+    pub tag: Option<String>,
+    // GitHub API 地址，GitHub Enterprise 一般为 https://<host>/api/v3
+    #[serde(default = "default_api_base_url")]
+    pub api_base_url: String,
+    // git clone 使用的地址，可以是 https 地址或 git@host:owner/repo.git 形式的 SSH 地址；
+    // 未设置时使用 https://github.com/<repo_owner>/<repo_name>.git
+    #[serde(default)]
+    pub clone_url: Option<String>,
+}
+
+fn default_api_base_url() -> String {
+    "https://api.github.com".to_string()
+}
+
+fn default_webhook_poll_interval() -> u64 {
+    3600
+}
+
+fn default_skip_markers() -> Vec<String> {
+    vec!["[skip ci]".to_string(), "[ci skip]".to_string()]
+}
+
+impl GitHubConfig {
+    pub fn key(&self) -> &str {
+        self.name.as_deref().unwrap_or(&self.repo_name)
+    }
+
+    // 仓库的 API 地址，后面接 /commits、/compare 等路径
+    pub fn api_repo_url(&self) -> String {
+        format!(
+            "{}/repos/{}/{}",
+            self.api_base_url.trim_end_matches('/'),
+            self.repo_owner,
+            self.repo_name
+        )
+    }
+
+    pub fn resolved_clone_url(&self) -> String {
+        match &self.clone_url {
+            Some(url) => url.clone(),
+            None => format!("https://github.com/{}/{}.git", self.repo_owner, self.repo_name),
+        }
+    }
+
+    // api_base_url 必须是不带查询参数的 http(s) 地址，clone_url 必须是 URL 或 SSH 简写形式
+    fn validate_urls(&self) -> anyhow::Result<()> {
+        let key = self.key();
+        let api_base_url = reqwest::Url::parse(self.api_base_url.trim_end_matches('/'))
+            .with_context(|| format!("Invalid github.api_base_url '{}' ({})", self.api_base_url, key))?;
+        if !matches!(api_base_url.scheme(), "http" | "https") || !api_base_url.has_host() {
+            anyhow::bail!("github.api_base_url must be an http(s) URL, got '{}' ({})", self.api_base_url, key);
+        }
+        if api_base_url.query().is_some() || api_base_url.fragment().is_some() {
+            anyhow::bail!("github.api_base_url must not contain a query or fragment ({})", key);
+        }
+
+        let Some(clone_url) = &self.clone_url else {
+            return Ok(());
+        };
+        if clone_url.trim().is_empty() {
+            anyhow::bail!("github.clone_url must not be empty when set ({})", key);
+        }
+        if !is_scp_like_url(clone_url) {
+            let url = reqwest::Url::parse(clone_url)
+                .with_context(|| format!("Invalid github.clone_url '{}' ({})", clone_url, key))?;
+            if !matches!(url.scheme(), "http" | "https" | "ssh" | "git" | "file") {
+                anyhow::bail!("github.clone_url has unsupported scheme '{}' ({})", url.scheme(), key);
+            }
+        }
+        Ok(())
+    }
+
+    // 配置中的令牌，未设置时使用 GITHUB_TOKEN 环境变量
+    pub fn resolved_token(&self) -> Option<String> {
+        self.token
+            .clone()
+            .or_else(|| std::env::var("GITHUB_TOKEN").ok())
+            .filter(|token| !token.is_empty())
+    }
+
+    // 提交信息中出现的第一个跳过标记
+    pub fn skip_marker(&self, message: &str) -> Option<&str> {
+        let message = message.to_lowercase();
+        self.skip_markers
+            .iter()
+            .map(String::as_str)
+            .filter(|marker| !marker.is_empty())
+            .find(|marker| message.contains(&marker.to_lowercase()))
+    }
+
+    // 提交作者在 ignore_authors 中时返回匹配的条目
+    pub fn ignored_author(&self, author: &str) -> Option<&str> {
+        self.ignore_authors
+            .iter()
+            .map(String::as_str)
+            .find(|ignored| ignored.eq_ignore_ascii_case(author))
+    }
+
+    // 编译 watch_paths，* 不跨越目录，** 匹配任意层目录；未配置时返回 None
+    pub fn watch_globs(&self) -> anyhow::Result<Option<globset::GlobSet>> {
+        self.path_globs("watch_paths", &self.watch_paths)
+    }
+
+    // 编译 ignore_paths，规则与 watch_paths 相同；未配置时返回 None
+    pub fn ignore_globs(&self) -> anyhow::Result<Option<globset::GlobSet>> {
+        self.path_globs("ignore_paths", &self.ignore_paths)
+    }
+
+    fn path_globs(&self, field: &str, patterns: &[String]) -> anyhow::Result<Option<globset::GlobSet>> {
+        if patterns.is_empty() {
+            return Ok(None);
+        }
+        let mut builder = globset::GlobSetBuilder::new();
+        for pattern in patterns {
+            let glob = globset::GlobBuilder::new(pattern)
+                .literal_separator(true)
+                .build()
+                .with_context(|| format!("Invalid github.{} pattern '{}' ({})", field, pattern, self.key()))?;
+            builder.add(glob);
+        }
+        Ok(Some(builder.build()?))
+    }
+
+    // 编译 tag，标签名中的 / 也可以被 * 匹配；未配置时返回 None
+    pub fn tag_glob(&self) -> anyhow::Result<Option<globset::GlobMatcher>> {
+        let Some(pattern) = &self.tag else {
+            return Ok(None);
+        };
+        let glob = globset::Glob::new(pattern)
+            .with_context(|| format!("Invalid github.tag pattern '{}' ({})", pattern, self.key()))?;
+        Ok(Some(glob.compile_matcher()))
+    }
+
+    // webhook 推送的引用是否为跟踪的分支，或设置 tag 时名称匹配的标签
+    pub fn tracks_ref(&self, git_ref: &str) -> bool {
+        match self.tag_glob() {
+            Ok(Some(glob)) => git_ref.strip_prefix("refs/tags/").is_some_and(|name| glob.is_match(name)),
+            _ => git_ref == format!("refs/heads/{}", self.branch),
+        }
+    }
+
+    pub fn webhook_enabled(&self) -> bool {
+        self.webhook_secret.as_deref().is_some_and(|secret| !secret.is_empty())
+    }
+
+    // 实际使用的轮询间隔
+    pub fn poll_interval(&self) -> Duration {
+        if self.webhook_enabled() {
+            self.check_interval.max(Duration::from_secs(self.webhook_poll_interval))
+        } else {
+            self.check_interval
+        }
+    }
 }
 
 #[derive(Debug, Clone, Deserialize)]
 pub struct BuildConfig {
     pub workspace_dir: String,
     pub binary_name: String,
-    pub build_timeout: u64,
+    // 构建和测试的超时，格式同 check_interval
+    #[serde(deserialize_with = "duration_format::deserialize")]
+    pub build_timeout: Duration,
+    // 单个 git 命令（clone、fetch、checkout）的超时，秒
+    #[serde(default = "default_git_timeout")]
+    pub git_timeout: u64,
+    // 克隆私有仓库使用的 SSH 私钥，设置后 git 通过 GIT_SSH_COMMAND 只使用该私钥
+    #[serde(default)]
+    pub ssh_key_path: Option<String>,
+    // 通过 https 克隆时把 GitHub 令牌作为 Authorization 请求头传给 git，用于没有配置 SSH 的私有仓库
+    #[serde(default)]
+    pub https_token_auth: bool,
+    // 浅克隆的深度，设置后克隆时只获取该分支最近的若干个提交，更新时按同样深度获取
+    #[serde(default)]
+    pub clone_depth: Option<u32>,
+    // 克隆时不下载文件内容（--filter=blob:none），检出时再按需获取
+    #[serde(default)]
+    pub filter_blobs: bool,
+    // 克隆时同时克隆子模块，每次检出后执行 git submodule update --init --recursive
+    #[serde(default)]
+    pub submodules: bool,
+    // 构建前按仓库的 rust-toolchain.toml 通过 rustup 安装缺少的工具链
+    #[serde(default)]
+    pub auto_install_toolchain: bool,
+    // 单次构建日志文件的大小上限，字节
+    #[serde(default = "default_max_log_bytes")]
+    pub max_log_bytes: u64,
+    // 工作区所在文件系统的可用空间低于该值（MiB）时不开始构建，0 表示不检查
+    #[serde(default)]
+    pub min_free_space_mb: u64,
+    // 可用空间低于该值（MiB，应小于 min_free_space_mb）时先执行 cargo clean 再检查，0 表示不清理
+    #[serde(default)]
+    pub clean_below_free_space_mb: u64,
+    // 在仓库目录中执行的构建命令及参数
+    #[serde(default = "default_build_command")]
+    pub build_command: String,
+    #[serde(default = "default_build_args", alias = "cargo_args")]
+    pub build_args: Vec<String>,
+    // 要启用的 cargo features，非空时追加 --features
+    #[serde(default)]
+    pub features: Vec<String>,
+    // 构建产物相对仓库目录的路径，默认为 target/release/<binary_name>
+    #[serde(default)]
+    pub binary_path: Option<String>,
+    // 构建期间到达的新提交的处理方式
+    #[serde(default)]
+    pub queue_policy: QueuePolicy,
+    #[serde(default = "default_max_queue_depth")]
+    pub max_queue_depth: usize,
+    // 构建成功后运行测试，测试通过才重启服务
+    #[serde(default)]
+    pub run_tests: bool,
+    #[serde(default = "default_build_command")]
+    pub test_command: String,
+    #[serde(default = "default_test_args")]
+    pub test_args: Vec<String>,
+    // 测试超时，秒
+    #[serde(default = "default_test_timeout")]
+    pub test_timeout: u64,
+    // 构建（和测试）成功后、启动服务前在仓库目录中通过 sh -c 执行的命令，例如复制配置文件或迁移数据库；
+    // 失败时构建记为失败，不启动新版本
+    #[serde(default)]
+    pub post_build_command: Option<String>,
+    // 保留在 workspace/artifacts 中的可用版本数量，用于启动失败时回滚，0 表示不保留
+    #[serde(default = "default_max_artifacts")]
+    pub max_artifacts: usize,
+    // 使用 cargo 构建时输出 JSON 格式的诊断信息，解析后保存到构建记录
+    #[serde(default = "default_json_diagnostics")]
+    pub json_diagnostics: bool,
+    // 非编译错误导致的构建失败（如下载依赖时网络中断）的重试次数，0 表示不重试
+    #[serde(default)]
+    pub max_build_retries: u32,
+    // 第一次重试前的等待时间，秒，之后每次翻倍
+    #[serde(default = "default_build_retry_delay")]
+    pub build_retry_delay: u64,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum QueuePolicy {
+    // 只构建最新的提交，队列中较早的提交记为跳过
+    #[default]
+    Coalesce,
+    // 按顺序构建每个提交
+    Sequential,
+}
+
+fn default_max_queue_depth() -> usize {
+    10
+}
+
+fn default_max_log_bytes() -> u64 {
+    10 * 1024 * 1024
+}
+
+fn default_build_command() -> String {
+    "cargo".to_string()
+}
+
+fn default_build_args() -> Vec<String> {
+    vec!["build".to_string(), "--release".to_string()]
+}
+
+fn default_test_args() -> Vec<String> {
+    vec!["test".to_string(), "--release".to_string()]
+}
+
+fn default_test_timeout() -> u64 {
+    1800
+}
+
+fn default_git_timeout() -> u64 {
+    600
+}
+
+fn default_max_artifacts() -> usize {
+    3
+}
+
+fn default_json_diagnostics() -> bool {
+    true
+}
+
+fn default_build_retry_delay() -> u64 {
+    10
+}
+
+impl BuildConfig {
+    // 完整的构建参数，包括由 features 生成的 --features
+    pub fn command_args(&self) -> Vec<String> {
+        let mut args = self.with_features(&self.build_args);
+        if self.uses_json_diagnostics() {
+            // 放在子命令之后，避免落在 -- 之后被传给 rustc
+            let index = args.len().min(1);
+            args.insert(index, "--message-format=json-diagnostic-rendered-ansi".to_string());
+        }
+        args
+    }
+
+    // 只对 cargo 生效，用户已经指定 --message-format 时保持不变
+    pub fn uses_json_diagnostics(&self) -> bool {
+        self.json_diagnostics
+            && Path::new(&self.build_command).file_stem().is_some_and(|name| name == "cargo")
+            && !self.build_args.iter().any(|arg| arg.starts_with("--message-format"))
+    }
+
+    pub fn command_line(&self) -> String {
+        Self::join_command(&self.build_command, self.command_args())
+    }
+
+    // 测试使用与构建相同的 features
+    pub fn test_command_args(&self) -> Vec<String> {
+        self.with_features(&self.test_args)
+    }
+
+    pub fn test_command_line(&self) -> String {
+        Self::join_command(&self.test_command, self.test_command_args())
+    }
+
+    fn with_features(&self, args: &[String]) -> Vec<String> {
+        let mut args = args.to_vec();
+        if !self.features.is_empty() {
+            args.push("--features".to_string());
+            args.push(self.features.join(","));
+        }
+        args
+    }
+
+    fn join_command(command: &str, args: Vec<String>) -> String {
+        std::iter::once(command.to_string())
+            .chain(args)
+            .collect::<Vec<_>>()
+            .join(" ")
+    }
+
+    fn validate(&self) -> anyhow::Result<()> {
+        if self.build_command.trim().is_empty() {
+            anyhow::bail!("build.build_command must not be empty");
+        }
+        if self.run_tests && self.test_command.trim().is_empty() {
+            anyhow::bail!("build.test_command must not be empty when run_tests is enabled");
+        }
+
+        let has_release = self.build_args.iter().any(|arg| arg == "--release" || arg == "-r");
+        let has_profile = self.build_args.iter().any(|arg| arg == "--profile" || arg.starts_with("--profile="));
+        if has_release && has_profile {
+            anyhow::bail!("build.build_args cannot combine --release with --profile; use --profile release instead");
+        }
+
+        if self.clone_depth == Some(0) {
+            anyhow::bail!("build.clone_depth must be >= 1 when set");
+        }
+        if self.clean_below_free_space_mb > 0 && self.clean_below_free_space_mb >= self.min_free_space_mb {
+            anyhow::bail!("build.clean_below_free_space_mb must be lower than build.min_free_space_mb");
+        }
+        if let Some(key_path) = &self.ssh_key_path {
+            Self::check_ssh_key(Path::new(key_path))?;
+        }
+        Ok(())
+    }
+
+    // 私钥必须存在，并且其他用户不可读，否则 ssh 会拒绝使用
+    fn check_ssh_key(key_path: &Path) -> anyhow::Result<()> {
+        let metadata = std::fs::metadata(key_path)
+            .with_context(|| format!("build.ssh_key_path {} cannot be read", key_path.display()))?;
+        if !metadata.is_file() {
+            anyhow::bail!("build.ssh_key_path {} is not a file", key_path.display());
+        }
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            let mode = metadata.permissions().mode() & 0o777;
+            if mode & 0o077 != 0 {
+                anyhow::bail!(
+                    "build.ssh_key_path {} is accessible by other users (mode {:o}), run `chmod 600 {}`",
+                    key_path.display(), mode, key_path.display()
+                );
+            }
+        }
+        Ok(())
+    }
+
+    pub fn binary_path(&self) -> PathBuf {
+        match &self.binary_path {
+            Some(path) => PathBuf::from(path),
+            None => Path::new("target").join("release").join(&self.binary_name),
+        }
+    }
 }
 
 #[derive(Debug, Clone, Deserialize)]
 pub struct RuntimeConfig {
-    pub restart_delay: u64,
+    // 停止旧进程后等待多久再启动新版本，格式同 check_interval
+    #[serde(deserialize_with = "duration_format::deserialize")]
+    pub restart_delay: Duration,
     pub max_retries: u32,
+    // 发送 SIGTERM 后等待服务退出的秒数，超时后强制结束
+    #[serde(default = "default_stop_timeout")]
+    pub stop_timeout: u64,
+    // 新版本启动后需要持续运行的秒数，期间退出视为启动失败并回滚
+    #[serde(default = "default_startup_grace_period")]
+    pub startup_grace_period: u64,
+    // 新提交构建失败时重新构建上次成功的提交，保证构建产物可用
+    #[serde(default)]
+    pub auto_rollback: bool,
+    // 工作区仓库有本地修改导致无法切换提交时，执行 git reset --hard 和 git clean -fd 后重试
+    #[serde(default)]
+    pub force_reset: bool,
+    // 有玩家在线时推迟替换服务，直到服务器连续 empty_grace_period 没有玩家，最多等待 max_wait；
+    // 玩家数通过 healthcheck.tcp_port 的 Server List Ping 查询
+    #[serde(default)]
+    pub wait_for_empty: bool,
+    #[serde(default = "default_empty_grace_period", deserialize_with = "duration_format::deserialize")]
+    pub empty_grace_period: Duration,
+    #[serde(default = "default_max_wait", deserialize_with = "duration_format::deserialize")]
+    pub max_wait: Duration,
+    // 服务输出日志 server.log 的大小上限，超过后轮换为 server.log.1；0 表示只在控制台显示，不写日志文件
+    #[serde(default = "default_max_service_log_bytes")]
+    pub max_service_log_bytes: u64,
+}
+
+fn default_max_service_log_bytes() -> u64 {
+    10 * 1024 * 1024
+}
+
+fn default_empty_grace_period() -> Duration {
+    Duration::from_secs(60)
+}
+
+fn default_max_wait() -> Duration {
+    Duration::from_secs(30 * 60)
+}
+
+fn default_stop_timeout() -> u64 {
+    30
+}
+
+fn default_startup_grace_period() -> u64 {
+    30
 }
 
 #[derive(Debug, Clone, Deserialize)]
 pub struct StorageConfig {
+    // 数据文件路径，相对于工作空间；使用 sqlite 时为数据库文件
     pub data_file: String,
+    #[serde(default)]
+    pub backend: StorageBackendKind,
+    // 最多保留的构建记录数量
+    #[serde(default = "default_max_builds")]
+    pub max_builds: usize,
+    // 构建记录的最长保留天数，未设置时不按时间清理
+    #[serde(default)]
+    pub max_age_days: Option<u64>,
+    // 只有 last_check 等时间字段变化时，最多每隔多少秒写一次磁盘
+    #[serde(default = "default_flush_interval")]
+    pub flush_interval: u64,
+}
+
+fn default_max_builds() -> usize {
+    100
+}
+
+fn default_flush_interval() -> u64 {
+    300
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum StorageBackendKind {
+    // 整个数据保存为一个 JSON 文件
+    #[default]
+    Json,
+    // 保存到 SQLite 数据库，每次只写入变化的记录
+    Sqlite,
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct NotificationConfig {
+    // Discord 或 Slack 的 incoming webhook 地址，未设置时不发送通知
+    #[serde(default)]
+    pub webhook_url: Option<String>,
+    #[serde(default)]
+    pub format: NotificationFormat,
+    // 通知中附带的仪表盘地址，例如 http://example.com:3000
+    #[serde(default)]
+    pub dashboard_url: Option<String>,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum NotificationFormat {
+    #[default]
+    Discord,
+    Slack,
+}
+
+// 启动服务进程时的环境变量和工作目录，未设置时继承监控程序的环境变量并在工作空间中运行
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct ServiceConfig {
+    // 在继承的环境变量之上追加或覆盖的变量，如 RUST_LOG
+    #[serde(default)]
+    pub env: std::collections::BTreeMap<String, String>,
+    // 服务进程的工作目录，相对路径以工作空间为基准
+    #[serde(default)]
+    pub working_dir: Option<String>,
+}
+
+// 定期清理工作区：过期的构建日志、超出 max_artifacts 的构建产物和过大的 target 目录
+#[derive(Debug, Clone, Deserialize)]
+pub struct MaintenanceConfig {
+    // 清理间隔，小时
+    #[serde(default = "default_maintenance_interval")]
+    pub interval_hours: u64,
+    // 删除修改时间早于该天数的构建日志，未设置时只随构建记录一起清理
+    #[serde(default)]
+    pub log_max_age_days: Option<u64>,
+    // 仓库的 target 目录超过该大小（MiB）时清理，未设置时不清理
+    #[serde(default)]
+    pub max_target_size_mb: Option<u64>,
+    #[serde(default)]
+    pub target_cleanup: TargetCleanup,
+}
+
+impl Default for MaintenanceConfig {
+    fn default() -> Self {
+        Self {
+            interval_hours: default_maintenance_interval(),
+            log_max_age_days: None,
+            max_target_size_mb: None,
+            target_cleanup: TargetCleanup::default(),
+        }
+    }
+}
+
+fn default_maintenance_interval() -> u64 {
+    24
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TargetCleanup {
+    // cargo clean -p <工作区中的 crate>，保留依赖的编译结果，下次构建不必从头编译
+    #[default]
+    WorkspaceCrates,
+    // 删除整个 target 目录
+    All,
+}
+
+// 进程存活但服务无响应时自动重启，未设置 tcp_port 和 http_url 时不检查
+#[derive(Debug, Clone, Deserialize)]
+pub struct HealthCheckConfig {
+    // 服务器端口，能建立 TCP 连接即视为正常
+    #[serde(default)]
+    pub tcp_port: Option<u16>,
+    // 返回 2xx 即视为正常，同时设置时优先使用
+    #[serde(default)]
+    pub http_url: Option<String>,
+    // 检查间隔，秒
+    #[serde(default = "default_healthcheck_interval")]
+    pub interval: u64,
+    // 单次检查的超时时间，秒
+    #[serde(default = "default_healthcheck_timeout")]
+    pub timeout: u64,
+    // 连续失败多少次后重启服务
+    #[serde(default = "default_healthcheck_failure_threshold")]
+    pub failure_threshold: u32,
+    // 新版本启动后等待端口响应的最长时间，超时视为启动失败并回滚；格式同 check_interval
+    #[serde(default = "default_healthcheck_startup_timeout", deserialize_with = "duration_format::deserialize")]
+    pub startup_timeout: Duration,
+    // 检查 tcp_port 时发送 Minecraft Server List Ping，收到状态响应才视为正常
+    #[serde(default)]
+    pub server_list_ping: bool,
+    // 通过 Server List Ping 查询在线玩家和 MOTD 的间隔，设置 tcp_port 时启用；格式同 check_interval
+    #[serde(default = "default_healthcheck_status_interval", deserialize_with = "duration_format::deserialize")]
+    pub status_interval: Duration,
+}
+
+impl Default for HealthCheckConfig {
+    fn default() -> Self {
+        Self {
+            tcp_port: None,
+            http_url: None,
+            interval: default_healthcheck_interval(),
+            timeout: default_healthcheck_timeout(),
+            failure_threshold: default_healthcheck_failure_threshold(),
+            startup_timeout: default_healthcheck_startup_timeout(),
+            server_list_ping: false,
+            status_interval: default_healthcheck_status_interval(),
+        }
+    }
+}
+
+impl HealthCheckConfig {
+    pub fn is_enabled(&self) -> bool {
+        self.tcp_port.is_some() || self.http_url.as_deref().is_some_and(|url| !url.is_empty())
+    }
+}
+
+fn default_healthcheck_interval() -> u64 {
+    10
+}
+
+fn default_healthcheck_timeout() -> u64 {
+    5
+}
+
+fn default_healthcheck_failure_threshold() -> u32 {
+    3
+}
+
+fn default_healthcheck_startup_timeout() -> Duration {
+    Duration::from_secs(120)
+}
+
+fn default_healthcheck_status_interval() -> Duration {
+    Duration::from_secs(30)
 }
 
 impl Config {
-    pub fn load() -> anyhow::Result<Self> {
-        let content = std::fs::read_to_string("config.toml")?;
-        let config: Config = toml::from_str(&content)?;
+    pub fn load_from(path: &Path) -> anyhow::Result<Self> {
+        let absolute_path = std::path::absolute(path).unwrap_or_else(|_| path.to_path_buf());
+        if !absolute_path.exists() {
+            anyhow::bail!("Config file not found: {}", absolute_path.display());
+        }
+
+        let content = std::fs::read_to_string(&absolute_path)
+            .with_context(|| format!("Failed to read config file {}", absolute_path.display()))?;
+        let mut table: toml::Table = toml::from_str(&content)
+            .with_context(|| format!("Failed to parse config file {}", absolute_path.display()))?;
+        // 容器部署时可用 PUMPKIN_MONITOR_* 环境变量覆盖配置项，映射规则见 config_env
+        // 没有覆盖项时直接解析原文，保留错误信息中的行号
+        let mut config: Config = if crate::config_env::apply_env_overrides(&mut table)? {
+            Config::deserialize(table)
+                .with_context(|| format!("Invalid config after applying {}* overrides", crate::config_env::ENV_PREFIX))?
+        } else {
+            toml::from_str(&content)
+                .with_context(|| format!("Failed to parse config file {}", absolute_path.display()))?
+        };
+        config.source_path = absolute_path;
+
+        config.validate()
+            .with_context(|| format!("Invalid config file {}", config.source_path.display()))?;
         Ok(config)
     }
+
+    // 检查取值范围和路径，避免错误配置在运行时才暴露（例如间隔为 0 导致空转）
+    pub fn validate(&self) -> anyhow::Result<()> {
+        if self.server.host.trim().is_empty() {
+            anyhow::bail!("server.host must not be empty");
+        }
+        if self.server.port == 0 {
+            anyhow::bail!("server.port must be between 1 and 65535");
+        }
+        if self.server.auth.protect_reads && self.server.auth.token().is_none() {
+            anyhow::bail!("server.auth.protect_reads requires server.auth.token to be set");
+        }
+        for origin in &self.server.allowed_origins {
+            let valid = reqwest::Url::parse(origin)
+                .is_ok_and(|url| url.origin().ascii_serialization() == origin.trim_end_matches('/'));
+            if !valid {
+                anyhow::bail!("server.allowed_origins entry '{}' must be an origin like https://example.com", origin);
+            }
+        }
+
+        if self.github.is_empty() {
+            anyhow::bail!("At least one [github] repository must be configured");
+        }
+        let mut keys = std::collections::HashSet::new();
+        for repo in &self.github {
+            let key = repo.key();
+            if key.is_empty() || key.contains(['/', '\\']) || key == "." || key == ".." {
+                anyhow::bail!("github.name '{}' must be a non-empty name without path separators", key);
+            }
+            if !keys.insert(key) {
+                anyhow::bail!("Duplicate repository name '{}', set a unique `name` for each [[github]] entry", key);
+            }
+            if repo.repo_owner.trim().is_empty() || repo.repo_name.trim().is_empty() {
+                anyhow::bail!("github.repo_owner and github.repo_name must not be empty ({})", key);
+            }
+            if repo.branch.trim().is_empty() {
+                anyhow::bail!("github.branch must not be empty ({})", key);
+            }
+            if repo.check_interval.is_zero() {
+                anyhow::bail!("github.check_interval must be greater than zero ({})", key);
+            }
+            if repo.webhook_enabled() && repo.webhook_poll_interval < 1 {
+                anyhow::bail!("github.webhook_poll_interval must be >= 1 ({})", key);
+            }
+            repo.watch_globs()?;
+            repo.ignore_globs()?;
+            if repo.ignore_authors.iter().any(|author| author.trim().is_empty()) {
+                anyhow::bail!("github.ignore_authors must not contain empty names ({})", key);
+            }
+            if repo.tag.as_deref().is_some_and(|tag| tag.trim().is_empty()) {
+                anyhow::bail!("github.tag must not be empty when set ({})", key);
+            }
+            repo.tag_glob()?;
+            repo.validate_urls()?;
+            if self.build.https_token_auth && repo.resolved_token().is_none() {
+                anyhow::bail!("build.https_token_auth requires github.token or GITHUB_TOKEN to be set ({})", key);
+            }
+        }
+
+        self.build.validate()?;
+        if self.build.binary_name.trim().is_empty() {
+            anyhow::bail!("build.binary_name must not be empty");
+        }
+        if self.build.build_timeout.is_zero() {
+            anyhow::bail!("build.build_timeout must be greater than zero");
+        }
+        if self.build.git_timeout < 1 {
+            anyhow::bail!("build.git_timeout must be >= 1");
+        }
+        Self::check_workspace_writable(Path::new(&self.build.workspace_dir))?;
+
+        if self.runtime.stop_timeout < 1 {
+            anyhow::bail!("runtime.stop_timeout must be >= 1");
+        }
+
+        if self.storage.data_file.trim().is_empty() {
+            anyhow::bail!("storage.data_file must not be empty");
+        }
+        if self.storage.max_builds < 1 {
+            anyhow::bail!("storage.max_builds must be >= 1");
+        }
+        if self.storage.flush_interval < 1 {
+            anyhow::bail!("storage.flush_interval must be >= 1");
+        }
+
+        if self.service.env.keys().any(|name| name.is_empty() || name.contains('=')) {
+            anyhow::bail!("service.env variable names must be non-empty and must not contain '='");
+        }
+        if self.service.working_dir.as_deref().is_some_and(|dir| dir.trim().is_empty()) {
+            anyhow::bail!("service.working_dir must not be empty when set");
+        }
+
+        if self.maintenance.interval_hours < 1 {
+            anyhow::bail!("maintenance.interval_hours must be >= 1");
+        }
+        if self.maintenance.log_max_age_days == Some(0) {
+            anyhow::bail!("maintenance.log_max_age_days must be >= 1 when set");
+        }
+
+        if self.healthcheck.interval < 1 {
+            anyhow::bail!("healthcheck.interval must be >= 1");
+        }
+        if self.healthcheck.timeout < 1 {
+            anyhow::bail!("healthcheck.timeout must be >= 1");
+        }
+        if self.healthcheck.failure_threshold < 1 {
+            anyhow::bail!("healthcheck.failure_threshold must be >= 1");
+        }
+        if self.healthcheck.startup_timeout.is_zero() {
+            anyhow::bail!("healthcheck.startup_timeout must be greater than 0");
+        }
+        if self.runtime.wait_for_empty && self.healthcheck.tcp_port.is_none() {
+            anyhow::bail!("runtime.wait_for_empty requires healthcheck.tcp_port to query the player count");
+        }
+        if self.healthcheck.status_interval.is_zero() {
+            anyhow::bail!("healthcheck.status_interval must be greater than 0");
+        }
+        Ok(())
+    }
+
+    // 工作空间不存在时由启动流程创建，这里检查最近的已存在上级目录是否可写
+    fn check_workspace_writable(workspace: &Path) -> anyhow::Result<()> {
+        if workspace.as_os_str().is_empty() {
+            anyhow::bail!("build.workspace_dir must not be empty");
+        }
+        let absolute = std::path::absolute(workspace).unwrap_or_else(|_| workspace.to_path_buf());
+        let existing = absolute
+            .ancestors()
+            .find(|dir| dir.exists())
+            .ok_or_else(|| anyhow::anyhow!("build.workspace_dir {} has no existing parent directory", absolute.display()))?;
+        if !existing.is_dir() {
+            anyhow::bail!("build.workspace_dir {} is not a directory ({} is a file)", absolute.display(), existing.display());
+        }
+
+        let probe = existing.join(format!(".pumpkin-monitor-write-test-{}", std::process::id()));
+        std::fs::write(&probe, b"")
+            .with_context(|| format!("build.workspace_dir {} is not writable", absolute.display()))?;
+        let _ = std::fs::remove_file(&probe);
+        Ok(())
+    }
+
+    // 静态文件目录的绝对路径
+    pub fn static_dir(&self) -> PathBuf {
+        let dir = Path::new(&self.server.static_dir);
+        let base = self.source_path.parent().unwrap_or(Path::new("."));
+        std::path::absolute(base.join(dir)).unwrap_or_else(|_| base.join(dir))
+    }
+
+    // 多仓库时每个仓库使用独立的子目录，单仓库时保持原有的工作空间布局
+    pub fn repo_workspace(&self, repo: &GitHubConfig) -> PathBuf {
+        let workspace = PathBuf::from(&self.build.workspace_dir);
+        if self.github.len() > 1 {
+            workspace.join(repo.key())
+        } else {
+            workspace
+        }
+    }
+}
+
+// git 的 SSH 简写地址，如 git@github.example.com:owner/repo.git
+fn is_scp_like_url(url: &str) -> bool {
+    match url.split_once(':') {
+        Some((host, path)) => !host.is_empty() && !host.contains('/') && !path.starts_with("//") && !path.is_empty(),
+        None => false,
+    }
+}
+
+// 取提交哈希的前 8 个字符，不足 8 个字符时返回整个字符串；按字符截取，不会切开多字节字符
+pub fn short_sha(sha: &str) -> &str {
+    match sha.char_indices().nth(8) {
+        Some((index, _)) => &sha[..index],
+        None => sha,
+    }
+}
+
+// 计算两个时间点之间的时长，系统时钟回拨导致结果为负时截断为零
+pub fn elapsed_between(
+    start: chrono::DateTime<chrono::Utc>,
+    end: chrono::DateTime<chrono::Utc>,
+) -> chrono::Duration {
+    let duration = end - start;
+    if duration < chrono::Duration::zero() {
+        // 统计接口会对每条记录调用这里，用 debug 级别避免刷屏
+        tracing::debug!(
+            "Clock skew detected: end time {} is before start time {}, clamping duration to zero",
+            end, start
+        );
+        chrono::Duration::zero()
+    } else {
+        duration
+    }
+}
+
+// Web 接口发给监控循环的控制命令
+#[derive(Debug)]
+pub enum ControlCommand {
+    // 构建并部署指定提交，sha 为 None 时使用分支最新提交
+    Build {
+        build_id: uuid::Uuid,
+        sha: Option<String>,
+    },
+    // webhook 推送的分支最新提交，直接构建无需再请求 GitHub API
+    Push {
+        build_id: uuid::Uuid,
+        commit: GitHubCommit,
+    },
+    // 从暂停中恢复，立即构建排队的最新提交
+    Resume,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -55,6 +990,22 @@ pub struct GitHubCommit {
     pub message: String,
     pub author: String,
     pub date: chrono::DateTime<chrono::Utc>,
+    // 提交在 GitHub 上的页面
+    #[serde(default)]
+    pub html_url: Option<String>,
+    // 父提交的 SHA，合并提交有多个；来自 webhook 的提交没有这项信息
+    #[serde(default)]
+    pub parents: Vec<String>,
+    // 相对上一个检查过的提交变更的文件，只在配置了 watch_paths 或 ignore_paths 时获取
+    #[serde(default)]
+    pub changed_files: Vec<String>,
+}
+
+// 构建过程中实时推送给前端的一行输出
+#[derive(Debug, Clone, Serialize)]
+pub struct BuildLogLine {
+    pub build_id: uuid::Uuid,
+    pub line: String,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -65,6 +1016,131 @@ pub struct BuildStatus {
     pub started_at: chrono::DateTime<chrono::Utc>,
     pub finished_at: Option<chrono::DateTime<chrono::Utc>>,
     pub error_message: Option<String>,
+    // 完整构建日志（stdout 与 stderr）的文件路径
+    #[serde(default)]
+    pub log_path: Option<String>,
+    // 构建日志的大小，字节
+    #[serde(default)]
+    pub log_size: Option<u64>,
+    // 实际执行的构建命令
+    #[serde(default)]
+    pub command: Option<String>,
+    // 构建耗时，秒；结束时根据开始和结束时间计算，进行中的构建在读取时填入到目前为止的时长
+    #[serde(default, alias = "duration_secs")]
+    pub duration_seconds: Option<i64>,
+    // 自动回滚时重新构建的记录，值为构建失败的提交
+    #[serde(default)]
+    pub rollback_of: Option<String>,
+    // 提交说明和作者，来自 GitHub
+    #[serde(default)]
+    pub commit_message: Option<String>,
+    #[serde(default)]
+    pub commit_author: Option<String>,
+    // 提交在 GitHub 上的页面，仪表盘中的提交 SHA 链接到这里
+    #[serde(default)]
+    pub commit_url: Option<String>,
+    // 从 cargo JSON 输出中解析的编译错误和警告
+    #[serde(default)]
+    pub diagnostics: Vec<Diagnostic>,
+    // 启用构建重试时记录最后一次尝试是第几次，以及最多尝试的次数
+    #[serde(default)]
+    pub attempt: Option<u32>,
+    #[serde(default)]
+    pub max_attempts: Option<u32>,
+    // 触发构建的来源；自动回滚沿用失败构建的来源
+    #[serde(default)]
+    pub trigger: BuildTrigger,
+    // 触发构建的提交变更的文件，来自 GitHubCommit::changed_files
+    #[serde(default)]
+    pub changed_files: Vec<String>,
+    // 仓库的 rust-toolchain.toml 固定的工具链，没有固定时为 None
+    #[serde(default)]
+    pub toolchain: Option<String>,
+    // true 表示没有可复用的编译结果（target 不存在或构建前执行了 cargo clean），false 为增量构建
+    #[serde(default)]
+    pub clean_build: Option<bool>,
+    // 通过 POST /api/builds/current/cancel 取消构建的人和时间
+    #[serde(default)]
+    pub cancelled_by: Option<String>,
+    #[serde(default)]
+    pub cancelled_at: Option<chrono::DateTime<chrono::Utc>>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Diagnostic {
+    // error 或 warning
+    pub level: String,
+    pub message: String,
+    pub file: Option<String>,
+    pub line: Option<u32>,
+    // 编译器渲染的完整诊断文本，已去除颜色
+    pub rendered: Option<String>,
+}
+
+impl BuildStatus {
+    pub fn for_commit(id: uuid::Uuid, commit: &GitHubCommit) -> Self {
+        Self {
+            commit_message: Some(commit.message.clone()),
+            commit_author: Some(commit.author.clone()),
+            commit_url: commit.html_url.clone(),
+            changed_files: commit.changed_files.clone(),
+            ..Self::new(id, commit.sha.clone())
+        }
+    }
+
+    // 同一提交的其他构建记录已经带有提交信息时直接沿用
+    pub fn copy_commit_info(&mut self, other: &BuildStatus) {
+        self.commit_message = other.commit_message.clone();
+        self.commit_author = other.commit_author.clone();
+        self.commit_url = other.commit_url.clone();
+        self.changed_files = other.changed_files.clone();
+    }
+
+    pub fn new(id: uuid::Uuid, commit_sha: String) -> Self {
+        Self {
+            id,
+            commit_sha,
+            status: BuildStatusType::Building,
+            started_at: chrono::Utc::now(),
+            finished_at: None,
+            error_message: None,
+            log_path: None,
+            log_size: None,
+            command: None,
+            duration_seconds: None,
+            rollback_of: None,
+            commit_message: None,
+            commit_author: None,
+            commit_url: None,
+            diagnostics: Vec::new(),
+            attempt: None,
+            max_attempts: None,
+            trigger: BuildTrigger::default(),
+            changed_files: Vec::new(),
+            toolchain: None,
+            clean_build: None,
+            cancelled_by: None,
+            cancelled_at: None,
+        }
+    }
+
+    // 记录结束时间并计算构建耗时
+    pub fn finish(&mut self) {
+        self.finish_at(chrono::Utc::now());
+    }
+
+    pub fn finish_at(&mut self, finished_at: chrono::DateTime<chrono::Utc>) {
+        self.finished_at = Some(finished_at);
+        self.duration_seconds = Some(elapsed_between(self.started_at, finished_at).num_seconds());
+    }
+
+    // 进行中的构建计算到目前为止的耗时
+    pub fn with_current_duration(mut self) -> Self {
+        if self.finished_at.is_none() && self.status == BuildStatusType::Building {
+            self.duration_seconds = Some(elapsed_between(self.started_at, chrono::Utc::now()).num_seconds());
+        }
+        self
+    }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
@@ -74,15 +1150,433 @@ pub enum BuildStatusType {
     Success,
     Failed,
     Stopped,
+    // 编译成功但测试未通过，旧版本服务继续运行
+    TestsFailed,
+    // 新版本启动失败，已恢复并启动之前的可用版本
+    RolledBack,
+    // 提交信息包含跳过标记，没有构建
+    Skipped,
+    // 已加入构建队列，等待前面的构建完成
+    Queued,
+    // 构建被手动取消，旧版本服务继续运行
+    Cancelled,
+    // 新版本已构建，等待服务器上没有玩家后再替换（runtime.wait_for_empty）
+    WaitingForPlayers,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+impl BuildStatusType {
+    pub const ALL: [BuildStatusType; 11] = [
+        BuildStatusType::Pending,
+        BuildStatusType::Building,
+        BuildStatusType::Success,
+        BuildStatusType::Failed,
+        BuildStatusType::Stopped,
+        BuildStatusType::TestsFailed,
+        BuildStatusType::RolledBack,
+        BuildStatusType::Skipped,
+        BuildStatusType::Queued,
+        BuildStatusType::Cancelled,
+        BuildStatusType::WaitingForPlayers,
+    ];
+
+    // 按名称查找，接受 as_str 的小写名称和 JSON 中的名称（如 tests_failed 或 TestsFailed），不区分大小写
+    pub fn from_name(name: &str) -> Option<Self> {
+        let name = name.replace('_', "").to_lowercase();
+        Self::ALL.into_iter().find(|status| status.as_str().replace('_', "") == name)
+    }
+
+    // 用于数据库和监控指标的小写名称
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            BuildStatusType::Pending => "pending",
+            BuildStatusType::Building => "building",
+            BuildStatusType::Success => "success",
+            BuildStatusType::Failed => "failed",
+            BuildStatusType::Stopped => "stopped",
+            BuildStatusType::TestsFailed => "tests_failed",
+            BuildStatusType::RolledBack => "rolled_back",
+            BuildStatusType::Skipped => "skipped",
+            BuildStatusType::Queued => "queued",
+            BuildStatusType::Cancelled => "cancelled",
+            BuildStatusType::WaitingForPlayers => "waiting_for_players",
+        }
+    }
+
+    pub fn is_failure(&self) -> bool {
+        matches!(self, BuildStatusType::Failed | BuildStatusType::TestsFailed | BuildStatusType::RolledBack)
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq)]
+pub enum BuildTrigger {
+    // 定时检查发现的新提交，以及缺少仓库或构建产物时的自动构建
+    #[default]
+    Poll,
+    Webhook,
+    // 通过 POST /api/build 手动触发
+    Manual,
+}
+
+impl BuildTrigger {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            BuildTrigger::Poll => "poll",
+            BuildTrigger::Webhook => "webhook",
+            BuildTrigger::Manual => "manual",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        [BuildTrigger::Poll, BuildTrigger::Webhook, BuildTrigger::Manual]
+            .into_iter()
+            .find(|trigger| trigger.as_str().eq_ignore_ascii_case(name))
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
 pub struct SystemStatus {
     pub current_commit: Option<String>,
     pub build_status: BuildStatusType,
     pub is_running: bool,
     pub last_check: chrono::DateTime<chrono::Utc>,
-    pub uptime: Option<chrono::Duration>,
+    // 运行时长，秒；读取状态时根据 started_at 重新计算，服务未运行时为 None
+    #[serde(default, alias = "uptime", deserialize_with = "legacy_uptime::deserialize")]
+    pub uptime_seconds: Option<i64>,
     pub started_at: Option<chrono::DateTime<chrono::Utc>>,
     pub process_pid: Option<u32>,
+    // 服务进程的启动时间，Unix 时间戳（秒），用于确认 process_pid 没有被其他进程复用
+    #[serde(default)]
+    pub process_started_at: Option<u64>,
+    #[serde(default)]
+    pub github_rate_limit: Option<RateLimitStatus>,
+    // 上一次检查更新失败的原因和时间，检查成功后清除
+    #[serde(default)]
+    pub github_error: Option<GitHubErrorStatus>,
+    // 上次停止服务时是否正常退出，None 表示尚未停止过
+    #[serde(default)]
+    pub last_stop_graceful: Option<bool>,
+    // 等待构建的提交数量及其 SHA，按构建顺序排列
+    #[serde(default)]
+    pub queue_depth: usize,
+    #[serde(default)]
+    pub queued_commits: Vec<String>,
+    // 最近几次成功构建的平均耗时，用于估算当前构建的剩余时间
+    #[serde(default)]
+    pub avg_build_duration_secs: Option<u64>,
+    // 健康检查结果，未启用健康检查或服务未运行时为 Unknown
+    #[serde(default)]
+    pub health: HealthState,
+    // 服务进程的常驻内存（字节）和 CPU 占用（百分比），由状态监控每隔几秒采样，服务未运行时为 None
+    #[serde(default)]
+    pub memory_bytes: Option<u64>,
+    #[serde(default)]
+    pub cpu_percent: Option<f32>,
+    // 工作区所在文件系统的可用空间，MiB，和资源占用一起采样；无法获取时为 None
+    #[serde(default)]
+    pub free_space_mb: Option<u64>,
+    // 工作区占用的空间，字节，每次定期清理后更新
+    #[serde(default)]
+    pub workspace_size_bytes: Option<u64>,
+    // Server List Ping 查询到的在线玩家和 MOTD，服务未响应时为 None
+    #[serde(default)]
+    pub server_info: Option<ServerListInfo>,
+    // 已构建、等待玩家离开后部署的提交，build_status 同时为 WaitingForPlayers
+    #[serde(default)]
+    pub pending_commit: Option<String>,
+    // 构建被取消的提交，手动构建或出现更新的提交之前不会自动重新构建
+    #[serde(default)]
+    pub cancelled_commit: Option<String>,
+    // 暂停自动部署：新提交只记录为排队的构建，不构建也不自动重启服务，
+    // 通过 /api/monitor/pause 和 /api/monitor/resume 切换
+    #[serde(default)]
+    pub paused: bool,
+    // 到达这个时间后自动恢复，None 表示一直暂停到手动恢复
+    #[serde(default)]
+    pub pause_until: Option<chrono::DateTime<chrono::Utc>>,
+}
+
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq)]
+pub enum HealthState {
+    #[default]
+    Unknown,
+    Healthy,
+    Unhealthy,
+}
+
+impl HealthState {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            HealthState::Unknown => "unknown",
+            HealthState::Healthy => "healthy",
+            HealthState::Unhealthy => "unhealthy",
+        }
+    }
+}
+
+// 配置中的时长：整数秒（兼容旧配置）、小数秒，或 humantime 格式的字符串，如 "30s"、"5m"、"1h 30m"
+mod duration_format {
+    use serde::de::Error;
+    use serde::{Deserialize, Deserializer};
+    use std::time::Duration;
+
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum Repr {
+        Secs(u64),
+        FractionalSecs(f64),
+        Text(String),
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Duration, D::Error> {
+        match Repr::deserialize(deserializer)? {
+            Repr::Secs(secs) => Ok(Duration::from_secs(secs)),
+            Repr::FractionalSecs(secs) => Duration::try_from_secs_f64(secs)
+                .map_err(|e| D::Error::custom(format!("invalid duration {}: {}", secs, e))),
+            Repr::Text(text) => humantime::parse_duration(text.trim())
+                .map_err(|e| D::Error::custom(format!("invalid duration '{}': {}", text, e))),
+        }
+    }
+}
+
+// 兼容旧数据文件中的 uptime 字段：整数秒或 chrono::Duration 的内部表示
+mod legacy_uptime {
+    use serde::{Deserialize, Deserializer};
+
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum Repr {
+        Secs(i64),
+        Tuple(Vec<i64>),
+        Map { secs: i64 },
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Option<i64>, D::Error> {
+        Ok(Option::<Repr>::deserialize(deserializer)?.map(|repr| match repr {
+            Repr::Secs(secs) | Repr::Map { secs } => secs,
+            Repr::Tuple(parts) => parts.first().copied().unwrap_or_default(),
+        }))
+    }
+}
+
+// Minecraft Server List Ping 状态响应中展示用的字段
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct ServerListInfo {
+    pub players_online: u32,
+    pub players_max: u32,
+    pub version: String,
+    // 去掉格式代码后的 MOTD
+    pub motd: String,
+}
+
+impl SystemStatus {
+    // 除检查时间和派生字段外状态是否相同，相同时无需立即写入磁盘
+    pub fn same_state(&self, other: &SystemStatus) -> bool {
+        let mut a = self.clone();
+        let mut b = other.clone();
+        a.last_check = b.last_check;
+        a.uptime_seconds = None;
+        b.uptime_seconds = None;
+        a.avg_build_duration_secs = None;
+        b.avg_build_duration_secs = None;
+        // 资源占用每次采样都会变化，不单独触发写入
+        a.memory_bytes = b.memory_bytes;
+        a.cpu_percent = b.cpu_percent;
+        a.free_space_mb = b.free_space_mb;
+        a.server_info = b.server_info.clone();
+        a == b
+    }
+
+    // 根据启动时间计算当前运行时长
+    pub fn with_current_uptime(mut self) -> Self {
+        self.uptime_seconds = self.started_at
+            .filter(|_| self.is_running)
+            .map(|started_at| elapsed_between(started_at, chrono::Utc::now()).num_seconds());
+        self
+    }
+}
+
+// 检查更新失败的原因
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq)]
+pub enum GitHubErrorKind {
+    // 网络不通、超时或 GitHub 返回 5xx，重试后仍然失败
+    Network,
+    RateLimited,
+    // 仓库或分支不存在，或令牌没有访问权限，需要修改配置
+    Config,
+    Other,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct GitHubErrorStatus {
+    pub kind: GitHubErrorKind,
+    pub message: String,
+    pub at: chrono::DateTime<chrono::Utc>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct RateLimitStatus {
+    pub limit: Option<u64>,
+    pub remaining: Option<u64>,
+    pub reset_at: Option<chrono::DateTime<chrono::Utc>>,
+}
+
+// 系统状态历史中的一条记录：运行状态、构建状态、部署的提交、健康状态或暂停状态发生变化的时间和新值
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct StatusTransition {
+    pub at: chrono::DateTime<chrono::Utc>,
+    pub is_running: bool,
+    pub build_status: BuildStatusType,
+    pub current_commit: Option<String>,
+    pub health: HealthState,
+    pub paused: bool,
+}
+
+impl StatusTransition {
+    pub fn from_status(status: &SystemStatus) -> Self {
+        Self {
+            at: chrono::Utc::now(),
+            is_running: status.is_running,
+            build_status: status.build_status.clone(),
+            current_commit: status.current_commit.clone(),
+            health: status.health,
+            paused: status.paused,
+        }
+    }
+
+    // 除时间外是否相同
+    pub fn same_state(&self, other: &StatusTransition) -> bool {
+        Self { at: other.at, ..self.clone() } == *other
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BuildStreak {
+    pub status: BuildStatusType,
+    pub count: usize,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BuildStats {
+    pub total_builds: usize,
+    pub success_count: usize,
+    pub failure_count: usize,
+    pub success_rate: Option<f64>,
+    pub avg_duration_secs: Option<f64>,
+    pub p95_duration_secs: Option<i64>,
+    pub current_streak: Option<BuildStreak>,
+    pub secs_since_last_failure: Option<i64>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // 以示例配置为基础，工作区放在临时目录中
+    fn example_config() -> Config {
+        let mut config: Config = toml::from_str(include_str!("../config.example.toml")).unwrap();
+        config.build.workspace_dir = std::env::temp_dir().join("pumpkin-monitor-test-workspace").to_string_lossy().into_owned();
+        config
+    }
+
+    fn validation_error(config: &Config) -> String {
+        config.validate().expect_err("config should be rejected").to_string()
+    }
+
+    #[test]
+    fn example_config_is_valid() {
+        example_config().validate().unwrap();
+    }
+
+    #[test]
+    fn rejects_zero_port() {
+        let mut config = example_config();
+        config.server.port = 0;
+        assert_eq!(validation_error(&config), "server.port must be between 1 and 65535");
+    }
+
+    #[test]
+    fn rejects_zero_check_interval() {
+        let mut config = example_config();
+        config.github[0].check_interval = Duration::ZERO;
+        assert_eq!(validation_error(&config), "github.check_interval must be greater than zero (Pumpkin)");
+    }
+
+    #[test]
+    fn rejects_empty_build_command() {
+        let mut config = example_config();
+        config.build.build_command = "  ".to_string();
+        assert_eq!(validation_error(&config), "build.build_command must not be empty");
+    }
+
+    // /proc 下即使是 root 也不能创建文件
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn rejects_unwritable_workspace() {
+        let mut config = example_config();
+        config.build.workspace_dir = "/proc/pumpkin-workspace".to_string();
+        assert_eq!(validation_error(&config), "build.workspace_dir /proc/pumpkin-workspace is not writable");
+    }
+
+    // 旧版本数据文件中的系统状态，uptime 为 chrono::Duration 的内部表示
+    fn legacy_status(uptime: serde_json::Value) -> SystemStatus {
+        serde_json::from_value(serde_json::json!({
+            "current_commit": "abc123",
+            "build_status": "Success",
+            "is_running": true,
+            "last_check": "2024-05-01T12:00:00Z",
+            "uptime": uptime,
+            "started_at": "2024-05-01T11:58:30Z",
+            "process_pid": 1234,
+        }))
+        .unwrap()
+    }
+
+    #[test]
+    fn reads_legacy_uptime() {
+        assert_eq!(legacy_status(serde_json::json!({ "secs": 90, "nanos": 500 })).uptime_seconds, Some(90));
+        assert_eq!(legacy_status(serde_json::json!([90, 500])).uptime_seconds, Some(90));
+        assert_eq!(legacy_status(serde_json::json!(90)).uptime_seconds, Some(90));
+        assert_eq!(legacy_status(serde_json::Value::Null).uptime_seconds, None);
+    }
+
+    // /api/status 返回的 uptime_seconds 是整数秒
+    #[test]
+    fn serializes_uptime_as_integer() {
+        let status = legacy_status(serde_json::json!({ "secs": 90, "nanos": 500 }));
+        let json = serde_json::to_value(&status).unwrap();
+        assert_eq!(json["uptime_seconds"], serde_json::json!(90));
+        assert!(json["uptime_seconds"].is_i64());
+        assert!(json.get("uptime").is_none());
+    }
+
+    #[test]
+    fn short_sha_keeps_whole_characters() {
+        assert_eq!(short_sha("6dcb09b5b57875f3"), "6dcb09b5");
+        assert_eq!(short_sha("abcd"), "abcd");
+        assert_eq!(short_sha(""), "");
+        // 按字符而不是字节截取，不会在多字节字符中间切开
+        assert_eq!(short_sha("提交哈希一二三四五六"), "提交哈希一二三四");
+        assert_eq!(short_sha("ééééééééé"), "éééééééé");
+    }
+
+    // [github] 中的字段错误要指出具体字段，而不是只报告整个表无法解析
+    fn github_error(replace: (&str, &str)) -> String {
+        let example = include_str!("../config.example.toml");
+        assert!(example.contains(replace.0));
+        toml::from_str::<Config>(&example.replacen(replace.0, replace.1, 1))
+            .expect_err("config should be rejected")
+            .to_string()
+    }
+
+    #[test]
+    fn reports_missing_github_field() {
+        let error = github_error(("branch = \"main\"\n", ""));
+        assert!(error.contains("missing field `branch`"), "{}", error);
+    }
+
+    #[test]
+    fn reports_invalid_check_interval() {
+        let error = github_error(("check_interval = 300", "check_interval = \"abc\""));
+        assert!(error.contains("invalid duration 'abc'"), "{}", error);
+    }
 }
@@ -0,0 +1,28 @@
+use serde::Serialize;
+
+use crate::types::elapsed_between;
+
+// 监控程序自身的版本，Cargo.toml 中的 version
+pub const VERSION: &str = env!("CARGO_PKG_VERSION");
+// 构建监控程序时的 git 提交，由 build.rs 写入；不在 git 仓库中构建时为 unknown
+pub const GIT_COMMIT: &str = env!("PUMPKIN_MONITOR_GIT_COMMIT");
+
+// /api/version 的返回内容，用于区分是监控程序还是被监控的服务版本过旧
+#[derive(Debug, Clone, Serialize)]
+pub struct VersionInfo {
+    pub version: &'static str,
+    pub git_commit: &'static str,
+    pub started_at: chrono::DateTime<chrono::Utc>,
+    pub uptime_secs: u64,
+}
+
+impl VersionInfo {
+    pub fn new(started_at: chrono::DateTime<chrono::Utc>) -> Self {
+        Self {
+            version: VERSION,
+            git_commit: GIT_COMMIT,
+            started_at,
+            uptime_secs: elapsed_between(started_at, chrono::Utc::now()).num_seconds() as u64,
+        }
+    }
+}
@@ -1,37 +1,185 @@
 use anyhow::Result;
 use axum::{
-    extract::{Query, State},
-    http::StatusCode,
-    response::{Html, Json},
+    body::{Body, Bytes},
+    extract::{
+        ws::{CloseFrame, Message, WebSocket, WebSocketUpgrade},
+        Path, Query, Request, State,
+    },
+    http::{header, HeaderMap, HeaderName, HeaderValue, Method, StatusCode},
+    middleware::{self, Next},
+    response::{
+        sse::{Event, KeepAlive, Sse},
+        Html, IntoResponse, Json, Response,
+    },
     routing::{get, post},
     Router,
 };
+use hmac::{Hmac, Mac};
 use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::BTreeMap;
+use std::convert::Infallible;
+use std::path::PathBuf;
 use std::sync::Arc;
-use tokio::sync::RwLock;
-use tower_http::{cors::CorsLayer, services::ServeDir};
+use tokio::sync::{broadcast, mpsc, watch};
+use tokio_stream::{wrappers::WatchStream, Stream, StreamExt, StreamMap};
+use tower_http::{cors::{AllowOrigin, CorsLayer}, services::ServeDir};
+use tracing::{info, warn};
 
-use crate::storage::Storage;
-use crate::types::SystemStatus;
+use crate::console::{read_tail, Console};
+use crate::build::{BuildCanceller, DeployGate};
+use crate::i18n::{Labels, LANGUAGES};
+use crate::metrics::Metrics;
+use crate::storage::{BuildFilter, SharedStore, StorageData};
+use crate::types::{AuthConfig, BuildLogLine, BuildStats, BuildStatus, BuildStatusType, BuildTrigger, ControlCommand, GitHubCommit, GitHubConfig, StatusTransition, SystemStatus, short_sha};
+use crate::version::VersionInfo;
 
 pub struct WebServer {
     app: Router,
 }
 
+// 单个被监控仓库在 Web 端使用的句柄
+#[derive(Clone)]
+pub struct RepoState {
+    pub name: String,
+    pub storage: SharedStore,
+    pub console: Console,
+    pub github: GitHubConfig,
+    pub control_tx: mpsc::Sender<ControlCommand>,
+    pub build_output: broadcast::Sender<BuildLogLine>,
+    pub build_canceller: BuildCanceller,
+    pub deploy_gate: DeployGate,
+}
+
 #[derive(Clone)]
 pub struct AppState {
-    pub storage: Arc<RwLock<Storage>>,
+    pub repos: Arc<Vec<RepoState>>,
+    pub console_token: Option<String>,
+    pub auth: AuthConfig,
+    pub metrics: Metrics,
+    // 以 --dry-run 启动时不接受构建请求，首页显示提示
+    pub dry_run: bool,
+    // 监控程序的启动时间
+    pub started_at: chrono::DateTime<chrono::Utc>,
+}
+
+impl AppState {
+    // 按名称查找仓库，未指定时使用第一个仓库，兼容单仓库时的接口
+    fn repo(&self, name: Option<&str>) -> Result<&RepoState, (StatusCode, String)> {
+        match name {
+            Some(name) => self.repos
+                .iter()
+                .find(|repo| repo.name == name)
+                .ok_or((StatusCode::NOT_FOUND, format!("Unknown repo '{}'", name))),
+            None => Ok(&self.repos[0]),
+        }
+    }
+}
+
+#[derive(Deserialize)]
+pub struct RepoQuery {
+    repo: Option<String>,
 }
 
 #[derive(Deserialize)]
 pub struct LogQuery {
     limit: Option<usize>,
-    lang: Option<String>,
+    offset: Option<usize>,
+    // 只返回指定状态的构建，如 failed 或 tests_failed
+    status: Option<String>,
+    // 只返回提交 SHA 以此开头的构建
+    commit: Option<String>,
+}
+
+// 一页构建历史及符合条件的记录总数
+#[derive(Serialize)]
+pub struct BuildPage {
+    builds: Vec<crate::types::BuildStatus>,
+    total: usize,
+    offset: usize,
+    limit: usize,
+}
+
+#[derive(Deserialize)]
+pub struct ServerLogQuery {
+    lines: Option<usize>,
+}
+
+#[derive(Deserialize)]
+pub struct StatusHistoryQuery {
+    // RFC 3339 时间，只返回此后的状态变化
+    since: Option<chrono::DateTime<chrono::Utc>>,
+}
+
+#[derive(Deserialize)]
+pub struct StatsQuery {
+    // 统计窗口，单位为小时
+    window: Option<i64>,
+}
+
+#[derive(Deserialize, Default)]
+pub struct BuildRequest {
+    sha: Option<String>,
+}
+
+#[derive(Deserialize)]
+pub struct PauseRequest {
+    // 到达这个时间后自动恢复
+    until: Option<chrono::DateTime<chrono::Utc>>,
+}
+
+#[derive(Deserialize)]
+pub struct CancelRequest {
+    // 记录在构建记录中的取消者，默认为 "api"
+    by: Option<String>,
+}
+
+// GitHub push 事件中用到的字段
+#[derive(Deserialize)]
+pub struct PushEvent {
+    #[serde(rename = "ref")]
+    git_ref: String,
+    repository: PushRepository,
+    #[serde(default)]
+    deleted: bool,
+    head_commit: Option<PushCommit>,
+}
+
+#[derive(Deserialize)]
+pub struct PushRepository {
+    full_name: String,
+}
+
+#[derive(Deserialize)]
+pub struct PushCommit {
+    id: String,
+    message: String,
+    timestamp: chrono::DateTime<chrono::Utc>,
+    url: String,
+    author: PushAuthor,
+}
+
+#[derive(Deserialize)]
+pub struct PushAuthor {
+    name: String,
+}
+
+// WebSocket 和 EventSource 无法设置请求头，GET 请求可改用查询参数传递接口令牌
+#[derive(Deserialize)]
+pub struct ApiTokenQuery {
+    api_token: Option<String>,
+}
+
+#[derive(Deserialize)]
+pub struct ConsoleQuery {
+    token: Option<String>,
 }
 
 #[derive(Deserialize)]
 pub struct IndexQuery {
     lang: Option<String>,
+    // light 或 dark，未指定时由页面脚本读取上次的选择
+    theme: Option<String>,
 }
 
 #[derive(Serialize)]
@@ -42,16 +190,65 @@ pub struct ApiResponse<T> {
 }
 
 impl WebServer {
-    pub fn new(storage: Arc<RwLock<Storage>>) -> Result<Self> {
-        let state = AppState { storage };
+    #[allow(clippy::too_many_arguments)]
+    pub fn new(
+        repos: Vec<RepoState>,
+        console_token: Option<String>,
+        auth: AuthConfig,
+        allowed_origins: &[String],
+        static_dir: PathBuf,
+        metrics: Metrics,
+        dry_run: bool,
+        started_at: chrono::DateTime<chrono::Utc>,
+    ) -> Result<Self> {
+        if repos.is_empty() {
+            anyhow::bail!("WebServer requires at least one repository");
+        }
+        if auth.token().is_none() {
+            warn!("server.api_key is not set, POST endpoints are open to anyone who can reach the server");
+        }
+        let state = AppState { repos: Arc::new(repos), console_token, auth, metrics, dry_run, started_at };
+        let auth_layer = middleware::from_fn_with_state(state.clone(), require_auth);
 
-        let app = Router::new()
+        let mut app = Router::new()
             .route("/", get(index))
             .route("/api/status", get(get_status))
+            .route("/api/status/history", get(get_status_history))
+            .route("/api/events", get(status_events))
             .route("/api/builds", get(get_builds))
+            .route("/api/builds/:id/log", get(get_build_log))
+            .route("/api/stats", get(get_stats))
+            .route("/api/version", get(get_version))
             .route("/api/restart", post(restart_service))
-            .nest_service("/static", ServeDir::new("static"))
-            .layer(CorsLayer::permissive())
+            .route("/api/build", post(trigger_build))
+            .route("/api/builds/current/cancel", post(cancel_build))
+            .route("/api/deploy/now", post(deploy_now))
+            .route("/api/monitor/pause", post(pause_monitoring))
+            .route("/api/monitor/resume", post(resume_monitoring))
+            .route("/api/pause", post(pause_monitoring))
+            .route("/api/resume", post(resume_monitoring))
+            .route("/api/logs", get(get_server_log))
+            .route("/api/logs/download", get(download_server_log))
+            .route("/api/service/log", get(get_server_log))
+            .route("/api/server/console", get(console_ws))
+            .route("/ws/service/log", get(service_log_ws))
+            .route("/ws/build", get(build_output_ws))
+            .route("/ws", get(live_ws))
+            .layer(auth_layer.clone());
+
+        // 仪表盘页面是内联的，静态目录不存在时只是不提供 /static
+        if static_dir.is_dir() {
+            info!("Serving static files from {}", static_dir.display());
+            app = app.nest_service("/static", ServeDir::new(static_dir));
+        } else {
+            warn!("Static directory {} not found, /static will not be served", static_dir.display());
+        }
+        // /metrics 由 Prometheus 抓取，webhook 由 GitHub 调用，都不需要 CORS
+        let app = Router::new()
+            .route("/metrics", get(get_metrics))
+            .route("/webhook/github", post(github_webhook))
+            .layer(auth_layer)
+            .merge(app.layer(cors_layer(allowed_origins)?))
             .with_state(state);
 
         Ok(Self { app })
@@ -65,44 +262,178 @@ impl WebServer {
 async fn index(
     State(state): State<AppState>,
     Query(params): Query<IndexQuery>,
+    Query(repo): Query<RepoQuery>,
 ) -> Result<Html<String>, (StatusCode, String)> {
-    let storage = state.storage.read().await;
-    let status = storage.get_system_status();
-    let builds = storage.get_latest_builds(10);
+    let repo = state.repo(repo.repo.as_deref())?;
+    // 读取接口受保护时首页不包含数据，由页面脚本携带令牌加载
+    let (status, builds) = if state.auth.protect_reads {
+        (StorageData::default().system_status, Vec::new())
+    } else {
+        let storage = repo.storage.read().await;
+        (storage.get_system_status(), storage.get_latest_builds(10))
+    };
     
-    let lang = params.lang.as_deref().unwrap_or("zh");
+    let labels = crate::i18n::labels(params.lang.as_deref().unwrap_or("zh"));
+    let theme = match params.theme.as_deref() {
+        Some("dark") => "dark",
+        _ => "light",
+    };
+    let repo_names: Vec<&str> = state.repos.iter().map(|repo| repo.name.as_str()).collect();
 
-    let html = create_html_page(&status, &builds, lang);
+    let html = create_html_page(&status, &builds, labels, theme, &repo.name, &repo_names, state.dry_run);
     Ok(Html(html))
 }
 
-async fn get_status(State(state): State<AppState>) -> Result<Json<ApiResponse<SystemStatus>>, (StatusCode, String)> {
-    let storage = state.storage.read().await;
-    let status = storage.get_system_status();
+// Prometheus 文本格式的监控指标
+async fn get_metrics(State(state): State<AppState>) -> impl IntoResponse {
+    (
+        [(header::CONTENT_TYPE, "text/plain; version=0.0.4; charset=utf-8")],
+        state.metrics.render(),
+    )
+}
+
+// 返回所有仓库的状态，以仓库名称为键
+async fn get_status(State(state): State<AppState>) -> Result<Json<ApiResponse<BTreeMap<String, SystemStatus>>>, (StatusCode, String)> {
+    let mut statuses = BTreeMap::new();
+    for repo in state.repos.iter() {
+        let storage = repo.storage.read().await;
+        statuses.insert(repo.name.clone(), storage.get_system_status());
+    }
+
+    Ok(Json(ApiResponse {
+        success: true,
+        data: Some(statuses),
+        error: None,
+    }))
+}
+
+// 仓库系统状态的变化历史，按时间从旧到新排列
+async fn get_status_history(
+    State(state): State<AppState>,
+    Query(params): Query<StatusHistoryQuery>,
+    Query(repo): Query<RepoQuery>,
+) -> Result<Json<ApiResponse<Vec<StatusTransition>>>, (StatusCode, String)> {
+    let repo = state.repo(repo.repo.as_deref())?;
+    let history = repo.storage.read().await.get_status_history(params.since);
 
     Ok(Json(ApiResponse {
         success: true,
-        data: Some(status),
+        data: Some(history),
         error: None,
     }))
 }
 
+#[derive(Serialize)]
+struct RepoStatusEvent {
+    repo: String,
+    status: SystemStatus,
+}
+
+// 任一仓库的系统状态变化时推送 status 事件，连接建立时先推送一次所有仓库的当前状态
+async fn status_events(State(state): State<AppState>) -> Sse<impl Stream<Item = Result<Event, Infallible>>> {
+    let mut streams = StreamMap::new();
+    for repo in state.repos.iter() {
+        let status_rx = repo.storage.read().await.subscribe_status();
+        streams.insert(repo.name.clone(), WatchStream::new(status_rx));
+    }
+
+    let stream = streams.filter_map(|(repo, status)| {
+        Event::default()
+            .event("status")
+            .json_data(RepoStatusEvent { repo, status: status.with_current_uptime() })
+            .ok()
+            .map(Ok)
+    });
+
+    Sse::new(stream).keep_alive(KeepAlive::default())
+}
+
 async fn get_builds(
     State(state): State<AppState>,
     Query(params): Query<LogQuery>,
-) -> Result<Json<ApiResponse<Vec<crate::types::BuildStatus>>>, (StatusCode, String)> {
-    let limit = params.limit.unwrap_or(50).min(100);
+    Query(repo): Query<RepoQuery>,
+) -> Result<Json<ApiResponse<BuildPage>>, (StatusCode, String)> {
+    let status = match params.status.as_deref().filter(|status| !status.is_empty()) {
+        Some(name) => Some(BuildStatusType::from_name(name)
+            .ok_or_else(|| (StatusCode::BAD_REQUEST, format!("Unknown build status '{}'", name)))?),
+        None => None,
+    };
+    let filter = BuildFilter {
+        status,
+        commit_prefix: params.commit.map(|commit| commit.to_lowercase()).filter(|commit| !commit.is_empty()),
+        offset: params.offset.unwrap_or(0),
+        limit: params.limit.unwrap_or(50).min(100),
+    };
     
-    let storage = state.storage.read().await;
-    let builds = storage.get_latest_builds(limit);
+    let repo = state.repo(repo.repo.as_deref())?;
+    let storage = repo.storage.read().await;
+    let (builds, total) = storage.query_builds(&filter);
+
+    Ok(Json(ApiResponse {
+        success: true,
+        data: Some(BuildPage { builds, total, offset: filter.offset, limit: filter.limit }),
+        error: None,
+    }))
+}
+
+// 以流的方式返回完整构建日志，避免把大文件读入内存；构建 ID 全局唯一，因此在所有仓库中查找
+async fn get_build_log(
+    State(state): State<AppState>,
+    Path(id): Path<uuid::Uuid>,
+) -> Result<Response, (StatusCode, String)> {
+    let mut build = None;
+    for repo in state.repos.iter() {
+        build = repo.storage.read().await.get_build(id);
+        if build.is_some() {
+            break;
+        }
+    }
+    let log_path = build
+        .ok_or((StatusCode::NOT_FOUND, "Build not found".to_string()))?
+        .log_path
+        .ok_or((StatusCode::NOT_FOUND, "No log recorded for this build".to_string()))?;
+
+    let file = tokio::fs::File::open(&log_path)
+        .await
+        .map_err(|_| (StatusCode::NOT_FOUND, "Log file is no longer available".to_string()))?;
+    let body = Body::from_stream(tokio_util::io::ReaderStream::new(file));
+
+    Ok(([(header::CONTENT_TYPE, "text/plain; charset=utf-8")], body).into_response())
+}
+
+async fn get_stats(
+    State(state): State<AppState>,
+    Query(params): Query<StatsQuery>,
+    Query(repo): Query<RepoQuery>,
+) -> Result<Json<ApiResponse<BuildStats>>, (StatusCode, String)> {
+    let since = match params.window {
+        Some(hours) if hours <= 0 => {
+            return Err((StatusCode::BAD_REQUEST, "window must be a positive number of hours".to_string()));
+        }
+        Some(hours) => Some(chrono::Utc::now() - chrono::Duration::hours(hours)),
+        None => None,
+    };
+
+    let repo = state.repo(repo.repo.as_deref())?;
+    let storage = repo.storage.read().await;
+    let stats = storage.get_build_stats(since);
 
     Ok(Json(ApiResponse {
         success: true,
-        data: Some(builds),
+        data: Some(stats),
         error: None,
     }))
 }
 
+// 监控程序自身的版本、构建提交和启动时间
+async fn get_version(State(state): State<AppState>) -> Json<ApiResponse<VersionInfo>> {
+    Json(ApiResponse {
+        success: true,
+        data: Some(VersionInfo::new(state.started_at)),
+        error: None,
+    })
+}
+
 async fn restart_service(State(_state): State<AppState>) -> Result<Json<ApiResponse<String>>, (StatusCode, String)> {
     // 这里应该触发重启逻辑，暂时返回成功
     Ok(Json(ApiResponse {
@@ -112,6 +443,719 @@ async fn restart_service(State(_state): State<AppState>) -> Result<Json<ApiRespo
     }))
 }
 
+async fn trigger_build(
+    State(state): State<AppState>,
+    Query(repo): Query<RepoQuery>,
+    body: Bytes,
+) -> Result<Json<ApiResponse<uuid::Uuid>>, (StatusCode, String)> {
+    let repo = state.repo(repo.repo.as_deref())?;
+    // 只有空请求体表示构建最新提交，无法解析的请求体不能当作构建最新提交
+    let request: BuildRequest = if body.trim_ascii().is_empty() {
+        BuildRequest::default()
+    } else {
+        serde_json::from_slice(&body)
+            .map_err(|e| (StatusCode::BAD_REQUEST, format!("Invalid build request: {}", e)))?
+    };
+    let sha = request.sha
+        .map(|sha| sha.trim().to_string())
+        .filter(|sha| !sha.is_empty());
+
+    if let Some(ref sha) = sha {
+        if !sha.chars().all(|c| c.is_ascii_hexdigit()) {
+            return Err((StatusCode::BAD_REQUEST, "sha must be a hexadecimal commit hash".to_string()));
+        }
+    }
+
+    if state.dry_run {
+        return Err((StatusCode::CONFLICT, "Builds are disabled in dry-run mode".to_string()));
+    }
+
+    // 构建进行中时拒绝，避免手动构建排在当前构建之后才执行造成误解
+    let build_status = repo.storage.read().await.get_system_status().build_status;
+    if matches!(build_status, BuildStatusType::Building | BuildStatusType::WaitingForPlayers) {
+        return Err((StatusCode::CONFLICT, "A build is already running".to_string()));
+    }
+
+    let build_id = uuid::Uuid::new_v4();
+    repo.control_tx
+        .send(ControlCommand::Build { build_id, sha })
+        .await
+        .map_err(|_| (StatusCode::SERVICE_UNAVAILABLE, "Monitor is not running".to_string()))?;
+
+    Ok(Json(ApiResponse {
+        success: true,
+        data: Some(build_id),
+        error: None,
+    }))
+}
+
+// 取消正在进行的构建，旧版本服务保持运行；已经开始替换服务时无法取消
+async fn cancel_build(
+    State(state): State<AppState>,
+    Query(repo): Query<RepoQuery>,
+    body: Option<Json<CancelRequest>>,
+) -> Result<Json<ApiResponse<uuid::Uuid>>, (StatusCode, String)> {
+    let repo = state.repo(repo.repo.as_deref())?;
+    let by = body
+        .and_then(|Json(request)| request.by)
+        .map(|by| by.trim().to_string())
+        .filter(|by| !by.is_empty())
+        .unwrap_or_else(|| "api".to_string());
+
+    let (build_id, sha) = repo.build_canceller
+        .cancel(&by)
+        .ok_or((StatusCode::CONFLICT, "No build is running".to_string()))?;
+    info!("Cancelling build {} of {} for {} (requested by {})", build_id, sha, repo.name, by);
+
+    Ok(Json(ApiResponse {
+        success: true,
+        data: Some(build_id),
+        error: None,
+    }))
+}
+
+// 跳过 runtime.wait_for_empty 的等待，立即替换服务
+async fn deploy_now(
+    State(state): State<AppState>,
+    Query(repo): Query<RepoQuery>,
+) -> Result<Json<ApiResponse<String>>, (StatusCode, String)> {
+    let repo = state.repo(repo.repo.as_deref())?;
+    let sha = repo.deploy_gate
+        .deploy_now()
+        .ok_or((StatusCode::CONFLICT, "No deployment is waiting for players to leave".to_string()))?;
+    info!("Deploying {} for {} now, skipping the wait for players to leave", sha, repo.name);
+
+    Ok(Json(ApiResponse {
+        success: true,
+        data: Some(sha),
+        error: None,
+    }))
+}
+
+// 暂停后新提交只记录为排队的构建，不构建也不自动重启服务；手动构建不受影响
+async fn pause_monitoring(
+    State(state): State<AppState>,
+    Query(repo): Query<RepoQuery>,
+    body: Option<Json<PauseRequest>>,
+) -> Result<Json<ApiResponse<bool>>, (StatusCode, String)> {
+    let until = body.and_then(|Json(request)| request.until);
+    if until.is_some_and(|until| until <= chrono::Utc::now()) {
+        return Err((StatusCode::BAD_REQUEST, "until must be in the future".to_string()));
+    }
+    set_paused(&state, repo, true, until).await
+}
+
+async fn resume_monitoring(
+    State(state): State<AppState>,
+    Query(repo): Query<RepoQuery>,
+) -> Result<Json<ApiResponse<bool>>, (StatusCode, String)> {
+    set_paused(&state, repo, false, None).await
+}
+
+async fn set_paused(
+    state: &AppState,
+    repo: RepoQuery,
+    paused: bool,
+    until: Option<chrono::DateTime<chrono::Utc>>,
+) -> Result<Json<ApiResponse<bool>>, (StatusCode, String)> {
+    let repo = state.repo(repo.repo.as_deref())?;
+    repo.storage.write().await.set_paused(paused, until).await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, format!("Failed to save status: {}", e)))?;
+    match until {
+        Some(until) => info!("Deployments paused for {} until {}", repo.name, until.to_rfc3339()),
+        None => info!("Deployments {} for {}", if paused { "paused" } else { "resumed" }, repo.name),
+    }
+    // 恢复后立即构建排队的最新提交，不必等到下次检查
+    if !paused {
+        let _ = repo.control_tx.send(ControlCommand::Resume).await;
+    }
+
+    Ok(Json(ApiResponse {
+        success: true,
+        data: Some(paused),
+        error: None,
+    }))
+}
+
+fn server_log_not_found(repo: &RepoState) -> (StatusCode, String) {
+    (
+        StatusCode::NOT_FOUND,
+        format!("No server log at {:?} yet; it is created when the server is first started", repo.console.log_path()),
+    )
+}
+
+async fn get_server_log(
+    State(state): State<AppState>,
+    Query(params): Query<ServerLogQuery>,
+    Query(repo): Query<RepoQuery>,
+) -> Result<Json<ApiResponse<Vec<String>>>, (StatusCode, String)> {
+    let repo = state.repo(repo.repo.as_deref())?;
+    let count = params.lines.unwrap_or(200).min(5000);
+    let log_path = repo.console.log_path().to_path_buf();
+    if !log_path.exists() {
+        return Err(server_log_not_found(repo));
+    }
+
+    let lines = tokio::task::spawn_blocking(move || read_tail(&log_path, count))
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, format!("Failed to read server log: {}", e)))?;
+
+    Ok(Json(ApiResponse {
+        success: true,
+        data: Some(lines),
+        error: None,
+    }))
+}
+
+async fn download_server_log(
+    State(state): State<AppState>,
+    Query(repo): Query<RepoQuery>,
+) -> Result<Response, (StatusCode, String)> {
+    let repo = state.repo(repo.repo.as_deref())?;
+    let file = tokio::fs::File::open(repo.console.log_path())
+        .await
+        .map_err(|_| server_log_not_found(repo))?;
+    let body = Body::from_stream(tokio_util::io::ReaderStream::new(file));
+
+    Ok((
+        [
+            (header::CONTENT_TYPE, "text/plain; charset=utf-8"),
+            (header::CONTENT_DISPOSITION, "attachment; filename=\"server.log\""),
+        ],
+        body,
+    ).into_response())
+}
+
+// 接收 GitHub push 事件，按仓库全名找到配置了 webhook 的仓库，签名校验通过且是监控的分支时立即触发构建
+async fn github_webhook(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    body: Bytes,
+) -> Result<Json<ApiResponse<Vec<uuid::Uuid>>>, (StatusCode, String)> {
+    #[derive(Deserialize)]
+    struct Envelope {
+        repository: Option<PushRepository>,
+    }
+
+    // 仅用于找到对应仓库的密钥，签名校验通过前不会据此执行任何操作
+    let full_name = serde_json::from_slice::<Envelope>(&body)
+        .ok()
+        .and_then(|envelope| envelope.repository)
+        .map(|repository| repository.full_name)
+        .unwrap_or_default();
+    let candidates: Vec<&RepoState> = state.repos
+        .iter()
+        .filter(|repo| repo.github.webhook_enabled())
+        .filter(|repo| {
+            let name = format!("{}/{}", repo.github.repo_owner, repo.github.repo_name);
+            name.eq_ignore_ascii_case(&full_name)
+        })
+        .collect();
+    if candidates.is_empty() {
+        return Err((StatusCode::NOT_FOUND, "Webhook is not configured".to_string()));
+    }
+
+    let signature = headers
+        .get("x-hub-signature-256")
+        .and_then(|v| v.to_str().ok());
+    let verified: Vec<&RepoState> = candidates
+        .into_iter()
+        .filter(|repo| {
+            let secret = repo.github.webhook_secret.as_deref().unwrap_or_default();
+            verify_webhook_signature(secret, &body, signature)
+        })
+        .collect();
+    if verified.is_empty() {
+        warn!("Rejected webhook request with invalid signature");
+        return Err((StatusCode::UNAUTHORIZED, "Invalid signature".to_string()));
+    }
+
+    let ignored = |reason: String| {
+        info!("Ignoring webhook: {}", reason);
+        Ok(Json(ApiResponse {
+            success: true,
+            data: None,
+            error: Some(reason),
+        }))
+    };
+
+    let event = headers
+        .get("x-github-event")
+        .and_then(|v| v.to_str().ok())
+        .unwrap_or_default();
+    // GitHub 创建 webhook 时会先发送 ping，用于确认地址和密钥配置正确
+    if event == "ping" {
+        info!("Received GitHub webhook ping for {}", full_name);
+        return Ok(Json(ApiResponse {
+            success: true,
+            data: Some(Vec::new()),
+            error: None,
+        }));
+    }
+    if event != "push" {
+        return ignored(format!("event '{}' is not a push", event));
+    }
+
+    let payload: PushEvent = serde_json::from_slice(&body)
+        .map_err(|e| (StatusCode::BAD_REQUEST, format!("Invalid push payload: {}", e)))?;
+    let targets: Vec<&RepoState> = verified
+        .into_iter()
+        .filter(|repo| repo.github.tracks_ref(&payload.git_ref))
+        .collect();
+    if targets.is_empty() {
+        return ignored(format!("push to {} of {} is not monitored", payload.git_ref, payload.repository.full_name));
+    }
+    let Some(head) = payload.head_commit.filter(|_| !payload.deleted) else {
+        return ignored("push has no head commit".to_string());
+    };
+
+    let commit = GitHubCommit {
+        sha: head.id,
+        message: head.message,
+        author: head.author.name,
+        date: head.timestamp,
+        html_url: Some(head.url),
+        parents: Vec::new(),
+        changed_files: Vec::new(),
+    };
+    let mut build_ids = Vec::new();
+    for repo in targets {
+        let build_id = uuid::Uuid::new_v4();
+        // 不等待队列空位，避免监控循环忙于构建时 GitHub 的请求超时
+        repo.control_tx
+            .try_send(ControlCommand::Push { build_id, commit: commit.clone() })
+            .map_err(|e| match e {
+                mpsc::error::TrySendError::Full(_) => {
+                    (StatusCode::SERVICE_UNAVAILABLE, format!("Control queue for {} is full", repo.name))
+                }
+                mpsc::error::TrySendError::Closed(_) => {
+                    (StatusCode::SERVICE_UNAVAILABLE, format!("Monitor for {} is not running", repo.name))
+                }
+            })?;
+        build_ids.push(build_id);
+    }
+
+    Ok(Json(ApiResponse {
+        success: true,
+        data: Some(build_ids),
+        error: None,
+    }))
+}
+
+// 未配置 allowed_origins 时允许任意来源，与之前的行为一致
+fn cors_layer(allowed_origins: &[String]) -> Result<CorsLayer> {
+    if allowed_origins.is_empty() {
+        return Ok(CorsLayer::permissive());
+    }
+    let origins = allowed_origins
+        .iter()
+        .map(|origin| HeaderValue::from_str(origin.trim_end_matches('/')))
+        .collect::<Result<Vec<_>, _>>()?;
+    Ok(CorsLayer::new()
+        .allow_origin(AllowOrigin::list(origins))
+        .allow_methods([Method::GET, Method::POST])
+        .allow_headers([header::AUTHORIZATION, header::CONTENT_TYPE, HeaderName::from_static("x-api-key")]))
+}
+
+// 修改类请求（POST 等）需要接口令牌，开启 protect_reads 后 GET 接口和 WebSocket 也需要；
+// 未设置令牌时不做校验，GitHub webhook 使用自己的签名校验，首页和静态文件始终公开
+async fn require_auth(State(state): State<AppState>, request: Request, next: Next) -> Response {
+    let Some(expected) = state.auth.token() else {
+        return next.run(request).await;
+    };
+
+    let path = request.uri().path();
+    let method = request.method();
+    let is_read = method == Method::GET || method == Method::HEAD;
+    let public = method == Method::OPTIONS
+        || path == "/webhook/github"
+        || (is_read && (!state.auth.protect_reads || path == "/" || path.starts_with("/static/")));
+    if public || request_token(&request).is_some_and(|token| tokens_match(expected, &token)) {
+        return next.run(request).await;
+    }
+
+    (
+        StatusCode::UNAUTHORIZED,
+        [(header::WWW_AUTHENTICATE, "Bearer")],
+        Json(ApiResponse::<()> {
+            success: false,
+            data: None,
+            error: Some("Missing or invalid API token".to_string()),
+        }),
+    )
+        .into_response()
+}
+
+// 优先读取 Authorization: Bearer 头，其次是 X-API-Key 头，GET 请求也接受 api_token 查询参数
+fn request_token(request: &Request) -> Option<String> {
+    let headers = request.headers();
+    let bearer = headers
+        .get(header::AUTHORIZATION)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.strip_prefix("Bearer "))
+        .or_else(|| headers.get("x-api-key").and_then(|value| value.to_str().ok()));
+    if let Some(token) = bearer {
+        return Some(token.trim().to_string());
+    }
+    if request.method() != Method::GET {
+        return None;
+    }
+    Query::<ApiTokenQuery>::try_from_uri(request.uri()).ok()?.0.api_token
+}
+
+// 比较两个令牌的 SHA-256 摘要，耗时与令牌内容和长度无关
+fn tokens_match(expected: &str, provided: &str) -> bool {
+    let expected = Sha256::digest(expected.as_bytes());
+    let provided = Sha256::digest(provided.as_bytes());
+    expected.iter().zip(provided.iter()).fold(0u8, |diff, (a, b)| diff | (a ^ b)) == 0
+}
+
+// 校验 X-Hub-Signature-256，verify_slice 使用常量时间比较
+fn verify_webhook_signature(secret: &str, body: &[u8], signature: Option<&str>) -> bool {
+    let Some(expected) = signature
+        .and_then(|signature| signature.strip_prefix("sha256="))
+        .and_then(|signature| hex::decode(signature).ok())
+    else {
+        return false;
+    };
+    let Ok(mut mac) = Hmac::<Sha256>::new_from_slice(secret.as_bytes()) else {
+        return false;
+    };
+    mac.update(body);
+    mac.verify_slice(&expected).is_ok()
+}
+
+// 控制台连接没有写权限时使用的关闭码，4000-4999 留给应用自定义
+const CONSOLE_READ_ONLY: u16 = 4003;
+
+async fn console_ws(
+    ws: WebSocketUpgrade,
+    State(state): State<AppState>,
+    Query(params): Query<ConsoleQuery>,
+    Query(repo): Query<RepoQuery>,
+) -> Response {
+    let console = match state.repo(repo.repo.as_deref()) {
+        Ok(repo) => repo.console.clone(),
+        Err(e) => return e.into_response(),
+    };
+    // 只有携带正确令牌的客户端才能向服务器发送命令
+    let can_write = matches!(
+        (&state.console_token, &params.token),
+        (Some(expected), Some(token)) if tokens_match(expected, token)
+    );
+    ws.on_upgrade(move |socket| handle_console_socket(socket, console, can_write))
+}
+
+async fn handle_console_socket(mut socket: WebSocket, console: Console, can_write: bool) {
+    let mut output_rx = console.subscribe();
+
+    loop {
+        tokio::select! {
+            line = output_rx.recv() => {
+                match line {
+                    Ok(line) => {
+                        if socket.send(Message::Text(line)).await.is_err() {
+                            break;
+                        }
+                    }
+                    Err(broadcast::error::RecvError::Lagged(skipped)) => {
+                        warn!("Console client lagged behind, skipped {} lines", skipped);
+                    }
+                    Err(broadcast::error::RecvError::Closed) => break,
+                }
+            }
+            message = socket.recv() => {
+                match message {
+                    Some(Ok(Message::Text(command))) => {
+                        // 令牌缺失或错误时以 CONSOLE_READ_ONLY 关闭连接，页面据此清除保存的令牌并重新询问
+                        if !can_write {
+                            let _ = socket.send(Message::Text("[monitor] Console is read-only without a valid token".to_string())).await;
+                            let _ = socket.send(Message::Close(Some(CloseFrame {
+                                code: CONSOLE_READ_ONLY,
+                                reason: "read-only".into(),
+                            }))).await;
+                            break;
+                        }
+                        info!("Forwarding console command: {}", command);
+                        if let Err(e) = console.send_command(&command) {
+                            let _ = socket.send(Message::Text(format!("[monitor] Failed to send command: {}", e))).await;
+                        }
+                    }
+                    Some(Ok(Message::Close(_))) | Some(Err(_)) | None => break,
+                    Some(Ok(_)) => {}
+                }
+            }
+        }
+    }
+}
+
+// 只推送服务输出，不接受命令，即使配置了 console_token
+async fn service_log_ws(
+    ws: WebSocketUpgrade,
+    State(state): State<AppState>,
+    Query(repo): Query<RepoQuery>,
+) -> Response {
+    let console = match state.repo(repo.repo.as_deref()) {
+        Ok(repo) => repo.console.clone(),
+        Err(e) => return e.into_response(),
+    };
+    ws.on_upgrade(move |socket| handle_service_log_socket(socket, console))
+}
+
+// 客户端发送的消息全部忽略，只用于发现连接关闭
+async fn handle_service_log_socket(mut socket: WebSocket, console: Console) {
+    let mut output_rx = console.subscribe();
+
+    loop {
+        tokio::select! {
+            line = output_rx.recv() => {
+                match line {
+                    Ok(line) => {
+                        if socket.send(Message::Text(line)).await.is_err() {
+                            break;
+                        }
+                    }
+                    Err(broadcast::error::RecvError::Lagged(skipped)) => {
+                        warn!("Service log client lagged behind, skipped {} lines", skipped);
+                    }
+                    Err(broadcast::error::RecvError::Closed) => break,
+                }
+            }
+            message = socket.recv() => {
+                match message {
+                    Some(Ok(Message::Close(_))) | Some(Err(_)) | None => break,
+                    Some(Ok(_)) => {}
+                }
+            }
+        }
+    }
+}
+
+#[derive(Serialize)]
+#[serde(tag = "type", rename_all = "lowercase")]
+enum LiveEvent {
+    Status { status: SystemStatus },
+    Build { build: BuildStatus },
+}
+
+// 推送单个仓库的系统状态变化和构建记录的创建、更新
+async fn live_ws(
+    ws: WebSocketUpgrade,
+    State(state): State<AppState>,
+    Query(repo): Query<RepoQuery>,
+) -> Response {
+    let repo = match state.repo(repo.repo.as_deref()) {
+        Ok(repo) => repo,
+        Err(e) => return e.into_response(),
+    };
+    let storage = repo.storage.read().await;
+    let status_rx = storage.subscribe_status();
+    let build_rx = storage.subscribe_builds();
+    drop(storage);
+    ws.on_upgrade(move |socket| handle_live_socket(socket, status_rx, build_rx))
+}
+
+// 连接建立时先推送当前状态；状态只推送最新值，客户端跟不上时跳过中间的构建记录
+async fn handle_live_socket(
+    mut socket: WebSocket,
+    mut status_rx: watch::Receiver<SystemStatus>,
+    mut build_rx: broadcast::Receiver<BuildStatus>,
+) {
+    let status = status_rx.borrow_and_update().clone();
+    let mut event = Some(LiveEvent::Status { status: status.with_current_uptime() });
+    loop {
+        if let Some(event) = event.take() {
+            let Ok(frame) = serde_json::to_string(&event) else {
+                continue;
+            };
+            if socket.send(Message::Text(frame)).await.is_err() {
+                break;
+            }
+        }
+
+        tokio::select! {
+            changed = status_rx.changed() => {
+                if changed.is_err() {
+                    break;
+                }
+                let status = status_rx.borrow_and_update().clone();
+                event = Some(LiveEvent::Status { status: status.with_current_uptime() });
+            }
+            build = build_rx.recv() => {
+                match build {
+                    Ok(build) => event = Some(LiveEvent::Build { build: build.with_current_duration() }),
+                    Err(broadcast::error::RecvError::Lagged(skipped)) => {
+                        warn!("Live update client lagged behind, skipped {} builds", skipped);
+                    }
+                    Err(broadcast::error::RecvError::Closed) => break,
+                }
+            }
+            message = socket.recv() => {
+                match message {
+                    Some(Ok(Message::Close(_))) | Some(Err(_)) | None => break,
+                    Some(Ok(_)) => {}
+                }
+            }
+        }
+    }
+}
+
+async fn build_output_ws(
+    ws: WebSocketUpgrade,
+    State(state): State<AppState>,
+    Query(repo): Query<RepoQuery>,
+) -> Response {
+    let output_rx = match state.repo(repo.repo.as_deref()) {
+        Ok(repo) => repo.build_output.subscribe(),
+        Err(e) => return e.into_response(),
+    };
+    ws.on_upgrade(move |socket| handle_build_output_socket(socket, output_rx))
+}
+
+// 中途连接的客户端只能收到之后的输出，完整日志可通过 /api/builds/:id/log 获取
+async fn handle_build_output_socket(mut socket: WebSocket, mut output_rx: broadcast::Receiver<BuildLogLine>) {
+    loop {
+        tokio::select! {
+            line = output_rx.recv() => {
+                match line {
+                    Ok(line) => {
+                        let Ok(frame) = serde_json::to_string(&line) else {
+                            continue;
+                        };
+                        if socket.send(Message::Text(frame)).await.is_err() {
+                            break;
+                        }
+                    }
+                    Err(broadcast::error::RecvError::Lagged(skipped)) => {
+                        warn!("Build output client lagged behind, skipped {} lines", skipped);
+                    }
+                    Err(broadcast::error::RecvError::Closed) => break,
+                }
+            }
+            message = socket.recv() => {
+                match message {
+                    Some(Ok(Message::Close(_))) | Some(Err(_)) | None => break,
+                    Some(Ok(_)) => {}
+                }
+            }
+        }
+    }
+}
+
+// 与页面脚本中的显示方式相同：版本和 MOTD 以 · 分隔，空的部分省略
+fn server_motd_text(version: &str, motd: &str) -> String {
+    [version, motd].into_iter().filter(|part| !part.is_empty()).collect::<Vec<_>>().join(" · ")
+}
+
+// 以 MiB 显示内存占用，超过 1 GiB 时以 GiB 显示
+fn format_memory(bytes: u64) -> String {
+    let mib = bytes as f64 / (1024.0 * 1024.0);
+    if mib >= 1024.0 {
+        format!("{:.2} GiB", mib / 1024.0)
+    } else {
+        format!("{:.1} MiB", mib)
+    }
+}
+
+// 将秒数格式化为 "12m 34s" 形式
+fn format_duration(secs: u64) -> String {
+    if secs >= 3600 {
+        format!("{}h {}m", secs / 3600, secs % 3600 / 60)
+    } else if secs >= 60 {
+        format!("{}m {}s", secs / 60, secs % 60)
+    } else {
+        format!("{}s", secs)
+    }
+}
+
+// 构建的触发来源
+fn trigger_text(trigger: BuildTrigger, labels: &Labels) -> String {
+    let text = match trigger {
+        BuildTrigger::Poll => labels.trigger_poll,
+        BuildTrigger::Webhook => labels.trigger_webhook,
+        BuildTrigger::Manual => labels.trigger_manual,
+    };
+    format!(" · {}", text)
+}
+
+// 重试过的构建显示最后一次是第几次尝试
+fn attempt_text(build: &crate::types::BuildStatus, labels: &Labels) -> String {
+    match (build.attempt, build.max_attempts) {
+        (Some(attempt), Some(max)) if attempt > 1 => format!(
+            " · {}",
+            labels.attempt
+                .replace("{attempt}", &attempt.to_string())
+                .replace("{max}", &max.to_string())
+        ),
+        _ => String::new(),
+    }
+}
+
+fn status_label(status: &BuildStatusType, labels: &Labels) -> &'static str {
+    match status {
+        BuildStatusType::Building => labels.building,
+        BuildStatusType::Success => labels.success,
+        BuildStatusType::Failed => labels.failed,
+        BuildStatusType::Pending => labels.pending,
+        BuildStatusType::Stopped => labels.stopped,
+        BuildStatusType::TestsFailed => labels.tests_failed,
+        BuildStatusType::RolledBack => labels.rolled_back,
+        BuildStatusType::Skipped => labels.skipped,
+        BuildStatusType::Queued => labels.queued,
+        BuildStatusType::Cancelled => labels.cancelled,
+        BuildStatusType::WaitingForPlayers => labels.waiting_for_players,
+    }
+}
+
+// 编译错误和警告的数量，展开后显示每条诊断
+fn diagnostics_html(diagnostics: &[crate::types::Diagnostic], labels: &Labels) -> String {
+    if diagnostics.is_empty() {
+        return String::new();
+    }
+    let errors = diagnostics.iter().filter(|d| d.level == "error").count();
+    let warnings = diagnostics.len() - errors;
+    let summary = labels.diagnostics_summary(errors, warnings);
+    let items = diagnostics.iter().map(|d| {
+        let text = d.rendered.clone().unwrap_or_else(|| match (&d.file, d.line) {
+            (Some(file), Some(line)) => format!("{}: {} ({}:{})", d.level, d.message, file, line),
+            _ => format!("{}: {}", d.level, d.message),
+        });
+        format!(r#"<pre class="diagnostic diagnostic-{}">{}</pre>"#, html_escape(&d.level), html_escape(&text))
+    }).collect::<String>();
+    format!(r#"<details class="build-diagnostics"><summary>{}</summary>{}</details>"#, summary, items)
+}
+
+// 有提交页面地址时链接到 GitHub
+fn commit_sha_html(build: &crate::types::BuildStatus) -> String {
+    let sha = html_escape(short_sha(&build.commit_sha));
+    match &build.commit_url {
+        Some(url) => format!(r#"<a class="commit-sha" href="{}" target="_blank" rel="noopener">{}</a>"#, html_escape(url), sha),
+        None => format!(r#"<span class="commit-sha">{}</span>"#, sha),
+    }
+}
+
+fn changed_files_html(files: &[String], labels: &Labels) -> String {
+    if files.is_empty() {
+        return String::new();
+    }
+    format!(
+        r#"<details class="build-changed-files"><summary>{}</summary><pre class="changed-files">{}</pre></details>"#,
+        labels.changed_files.replace("{}", &files.len().to_string()),
+        html_escape(&files.join("\n"))
+    )
+}
+
+// 构建卡片只显示提交说明的第一行
+fn commit_title(message: &str) -> String {
+    const MAX_CHARS: usize = 100;
+    let line = message.lines().next().unwrap_or_default();
+    match line.char_indices().nth(MAX_CHARS) {
+        Some((index, _)) => format!("{}…", &line[..index]),
+        None => line.to_string(),
+    }
+}
+
 fn html_escape(input: &str) -> String {
     input
         .replace('&', "&amp;")
@@ -124,91 +1168,207 @@ fn html_escape(input: &str) -> String {
 fn create_html_page(
     status: &crate::types::SystemStatus,
     builds: &[crate::types::BuildStatus],
-    lang: &str,
+    labels: &Labels,
+    theme: &str,
+    current_repo: &str,
+    repo_names: &[&str],
+    dry_run: bool,
 ) -> String {
-    let is_chinese = lang == "zh";
-    
-    // Language strings
-    let (title, subtitle, server_info, running_status_label, build_status_label, current_commit_label, uptime_label, 
-         build_history_label, refresh_btn_text, auto_refresh_text, no_builds_text, lang_switch_text,
-         running_text, stopped_text, building_text, success_text, failed_text, pending_text) = if is_chinese {
-        ("Pumpkin Monitor", "自动化部署监控系统", "测试用 Minecraft 服务器 - 连接地址: slv4.starlight.cool:3082", "运行状态", "构建状态", "当前提交", "运行时长", 
-         "构建历史", "刷新状态", "自动刷新已启用", "暂无构建记录", "English",
-         "运行中", "已停止", "构建中", "成功", "失败", "等待中")
-    } else {
-        ("Pumpkin Monitor", "Automated Deployment Monitoring System", "Test Pumpkin Minecraft Server Hosted by zly2006 - Connect to: slv4.starlight.cool:3082", "Running Status", "Build Status", "Current Commit", "Uptime",
-         "Build History", "Refresh Status", "Auto refresh enabled", "No build records", "中文",
-         "Running", "Stopped", "Building", "Success", "Failed", "Pending")
-    };
-    
+    let title = "Pumpkin Monitor";
     let running_class = if status.is_running { "status-running" } else { "status-stopped" };
     let build_class = format!("status-{:?}", status.build_status).to_lowercase();
     
-    let running_status_text = if status.is_running { running_text } else { stopped_text };
-    let build_status_text = match status.build_status {
-        crate::types::BuildStatusType::Building => building_text,
-        crate::types::BuildStatusType::Success => success_text,
-        crate::types::BuildStatusType::Failed => failed_text,
-        crate::types::BuildStatusType::Pending => pending_text,
-        crate::types::BuildStatusType::Stopped => stopped_text,
+    let running_status_text = if status.is_running { labels.running } else { labels.stopped };
+    let health_text = match status.health {
+        crate::types::HealthState::Healthy => labels.healthy,
+        crate::types::HealthState::Unhealthy => labels.unhealthy,
+        crate::types::HealthState::Unknown => "",
     };
+    let build_status_text = status_label(&status.build_status, labels);
     
-    let current_commit = status.current_commit.as_deref().unwrap_or("Unknown")[..8].to_string();
-    let uptime = if let Some(uptime) = status.uptime {
+    // 构建中时根据平均构建耗时估算剩余时间
+    let building = builds.iter().find(|b| b.status == crate::types::BuildStatusType::Building);
+    let build_estimate = match (&status.build_status, status.avg_build_duration_secs, building) {
+        (crate::types::BuildStatusType::WaitingForPlayers, _, _) => status.pending_commit
+            .as_deref()
+            .map(|sha| labels.pending_deploy.replace("{}", short_sha(sha)))
+            .unwrap_or_default(),
+        (crate::types::BuildStatusType::Building, Some(avg), Some(build)) => {
+            let elapsed = crate::types::elapsed_between(build.started_at, chrono::Utc::now()).num_seconds() as u64;
+            let minutes = avg.saturating_sub(elapsed).div_ceil(60).max(1);
+            labels.remaining.replace("{}", &minutes.to_string())
+        }
+        _ => String::new(),
+    };
+
+    let current_commit = short_sha(status.current_commit.as_deref().unwrap_or("Unknown")).to_string();
+    let uptime = if let Some(secs) = status.uptime_seconds {
         format!("{}d {}h {}m", 
-            uptime.num_days(), 
-            uptime.num_hours() % 24, 
-            uptime.num_minutes() % 60)
+            secs / 86400, 
+            secs % 86400 / 3600, 
+            secs % 3600 / 60)
     } else {
         "Unknown".to_string()
     };
+    let memory_usage = status.memory_bytes.map(format_memory).unwrap_or_else(|| "-".to_string());
+    let cpu_usage = status.cpu_percent.map(|cpu| format!("{:.1}%", cpu)).unwrap_or_else(|| "-".to_string());
+    let workspace_size = status.workspace_size_bytes.map(format_memory).unwrap_or_else(|| "-".to_string());
+    let (players, server_motd) = match &status.server_info {
+        Some(info) => (
+            format!("{} / {}", info.players_online, info.players_max),
+            html_escape(&server_motd_text(&info.version, &info.motd)),
+        ),
+        None => ("-".to_string(), String::new()),
+    };
     
     let builds_html = if builds.is_empty() {
-        format!(r#"<p style="text-align: center; color: #666; padding: 40px;">{}</p>"#, no_builds_text)
+        format!(r#"<p style="text-align: center; color: var(--muted); padding: 40px;">{}</p>"#, labels.no_builds)
     } else {
         builds.iter().map(|build| {
-            let status_text = match build.status {
-                crate::types::BuildStatusType::Building => building_text,
-                crate::types::BuildStatusType::Success => success_text,
-                crate::types::BuildStatusType::Failed => failed_text,
-                crate::types::BuildStatusType::Pending => pending_text,
-                crate::types::BuildStatusType::Stopped => stopped_text,
-            };
+            let status_text = status_label(&build.status, labels);
             let status_class = format!("status-{:?}", build.status).to_lowercase();
-            let error_html = if let Some(ref error) = build.error_message {
-                format!(r#"<div class="error-message">{}</div>"#, html_escape(error))
+            let item_class = if build.status == BuildStatusType::Skipped { "build-item build-skipped" } else { "build-item" };
+            // 有编译错误的诊断时不再显示原始输出，原始输出仍可通过接口和构建日志查看
+            let has_error_diagnostics = build.diagnostics.iter().any(|d| d.level == "error");
+            let error_html = match build.error_message {
+                Some(ref error) if !has_error_diagnostics => {
+                    format!(r#"<div class="error-message">{}</div>"#, html_escape(error))
+                }
+                _ => String::new(),
+            };
+            let diagnostics_html = diagnostics_html(&build.diagnostics, labels);
+            let changed_files_html = changed_files_html(&build.changed_files, labels);
+            let command_html = if let Some(ref command) = build.command {
+                format!(r#"<div class="build-command">$ {}</div>"#, html_escape(command))
+            } else {
+                String::new()
+            };
+            let commit_html = match (&build.commit_message, &build.commit_author) {
+                (Some(message), author) => format!(
+                    r#"<div class="build-commit">{}{}</div>"#,
+                    html_escape(&commit_title(message)),
+                    author.as_ref().map(|author| format!(" — {}", html_escape(author))).unwrap_or_default()
+                ),
+                (None, _) => String::new(),
+            };
+            let rollback_html = if let Some(ref failed) = build.rollback_of {
+                let text = labels.rolled_back_after
+                    .replace("{failed}", short_sha(failed))
+                    .replace("{sha}", short_sha(&build.commit_sha));
+                format!(r#"<div class="build-rollback">↩ {}</div>"#, html_escape(&text))
             } else {
                 String::new()
             };
             
             format!(r#"
-                <div class="build-item">
+                <div class="{}">
                     <div class="build-header">
-                        <span class="commit-sha">{}</span>
+                        {}
                         <span class="build-status {}">{}</span>
                     </div>
-                    <div class="build-time">{}</div>
+                    {}
+                    <div class="build-time">{}{}{}{}</div>
+                    {}
+                    {}
+                    {}
+                    {}
                     {}
                 </div>
             "#, 
-            &build.commit_sha[..8], 
+            item_class,
+            commit_sha_html(build), 
             status_class, 
             status_text,
+            commit_html,
             build.started_at.format("%Y-%m-%d %H:%M:%S UTC"),
+            build.duration_seconds.map(|secs| format!(" · {}", format_duration(secs as u64))).unwrap_or_default(),
+            trigger_text(build.trigger, labels),
+            attempt_text(build, labels),
+            rollback_html,
+            changed_files_html,
+            command_html,
+            diagnostics_html,
             error_html)
         }).collect::<String>()
     };
     
-    let other_lang = if is_chinese { "en" } else { "zh" };
-    let lang_attr = if is_chinese { "zh-CN" } else { "en" };
+    // 按钮显示切换后的主题
+    let theme_toggle_text = if theme == "dark" { labels.theme_light } else { labels.theme_dark };
+    let lang = labels.code;
+    let lang_options = LANGUAGES.iter().map(|option| {
+        let selected = if option.code == lang { " selected" } else { "" };
+        format!(r#"<option value="{}"{}>{}</option>"#, option.code, selected, option.name)
+    }).collect::<String>();
+    // 标签对象直接作为页面脚本的 translations
+    let translations_js = serde_json::to_string(labels).unwrap_or_else(|_| "{}".to_string());
+
+    // 只有一个仓库时不显示切换标签
+    let repo_tabs_html = if repo_names.len() > 1 {
+        let tabs = repo_names.iter().map(|name| {
+            let active = if *name == current_repo { " active" } else { "" };
+            format!(r#"<a href="/?repo={}&lang={}" class="repo-tab{}">{}</a>"#,
+                html_escape(name), lang, active, html_escape(name))
+        }).collect::<String>();
+        format!(r#"<div class="repo-tabs">{}</div>"#, tabs)
+    } else {
+        String::new()
+    };
+    let dry_run_html = if dry_run {
+        format!(r#"<div class="dry-run-banner">⚠️ {}</div>"#, labels.dry_run_banner)
+    } else {
+        String::new()
+    };
+    // 暂停时显示横幅，由页面脚本根据状态切换
+    let paused_style = if status.paused { "" } else { r#" style="display: none;""# };
+    let paused_html = format!(
+        r#"<div class="dry-run-banner" id="paused-banner"{}>⏸️ {} <span id="paused-until"></span></div>"#,
+        paused_style, labels.paused_banner
+    );
+    let pause_button_text = if status.paused { labels.resume_monitoring } else { labels.pause_monitoring };
+    // dry-run 时不显示构建按钮
+    let build_button_style = if dry_run { r#" style="display: none;""# } else { "" };
+    let current_repo_js = serde_json::to_string(current_repo).unwrap_or_else(|_| "\"\"".to_string());
 
     format!(r#"<!DOCTYPE html>
-<html lang="{}">
+<html lang="{}" data-theme="{}">
 <head>
     <meta charset="UTF-8">
     <meta name="viewport" content="width=device-width, initial-scale=1.0">
     <title>{}</title>
+    <script>
+        // Apply the saved theme before the page renders to avoid a flash
+        (function() {{
+            const theme = new URLSearchParams(location.search).get('theme') || localStorage.getItem('theme');
+            if (theme === 'dark' || theme === 'light') {{
+                document.documentElement.dataset.theme = theme;
+            }}
+        }})();
+    </script>
     <style>
+        :root {{
+            --page-bg: linear-gradient(135deg, #667eea 0%, #764ba2 100%);
+            --card-bg: white;
+            --item-bg: linear-gradient(145deg, #f0f0f0, #ffffff);
+            --build-bg: #f8f9fa;
+            --code-bg: #e9ecef;
+            --text: #333;
+            --muted: #666;
+            --command: #555;
+            --input-border: #ced4da;
+        }}
+
+        [data-theme="dark"] {{
+            --page-bg: linear-gradient(135deg, #1a1b2e 0%, #2b1f3d 100%);
+            --card-bg: #1f2230;
+            --item-bg: linear-gradient(145deg, #252938, #2c3143);
+            --build-bg: #262a39;
+            --code-bg: #363b4e;
+            --text: #e2e5ec;
+            --muted: #9aa1ae;
+            --command: #b4bac6;
+            --input-border: #464c60;
+        }}
+
         * {{
             margin: 0;
             padding: 0;
@@ -217,9 +1377,9 @@ fn create_html_page(
 
         body {{
             font-family: -apple-system, BlinkMacSystemFont, 'Segoe UI', Roboto, 'Helvetica Neue', Arial, sans-serif;
-            background: linear-gradient(135deg, #667eea 0%, #764ba2 100%);
+            background: var(--page-bg);
             min-height: 100vh;
-            color: #333;
+            color: var(--text);
         }}
 
         .container {{
@@ -264,10 +1424,15 @@ fn create_html_page(
             text-shadow: 1px 1px 2px rgba(0,0,0,0.5);
         }}
 
-        .lang-switch {{
+        .header-actions {{
             position: absolute;
             top: 0;
             right: 0;
+            display: flex;
+            gap: 10px;
+        }}
+
+        .lang-switch {{
             background: rgba(255,255,255,0.2);
             border: 1px solid rgba(255,255,255,0.3);
             color: white;
@@ -275,17 +1440,43 @@ fn create_html_page(
             border-radius: 20px;
             cursor: pointer;
             text-decoration: none;
+            font-family: inherit;
             font-size: 0.9rem;
             transition: all 0.3s;
         }}
 
+        .lang-switch option {{
+            color: #333;
+        }}
+
         .lang-switch:hover {{
             background: rgba(255,255,255,0.3);
             transform: translateY(-2px);
         }}
 
+        .repo-tabs {{
+            display: flex;
+            flex-wrap: wrap;
+            gap: 10px;
+            margin-bottom: 20px;
+        }}
+
+        .repo-tab {{
+            background: rgba(255,255,255,0.2);
+            border: 1px solid rgba(255,255,255,0.3);
+            color: white;
+            padding: 8px 16px;
+            border-radius: 20px;
+            text-decoration: none;
+        }}
+
+        .repo-tab.active {{
+            background: var(--card-bg);
+            color: #667eea;
+        }}
+
         .status-card {{
-            background: white;
+            background: var(--card-bg);
             border-radius: 20px;
             padding: 30px;
             margin-bottom: 30px;
@@ -303,13 +1494,13 @@ fn create_html_page(
         .status-item {{
             text-align: center;
             padding: 20px;
-            background: linear-gradient(145deg, #f0f0f0, #ffffff);
+            background: var(--item-bg);
             border-radius: 15px;
             box-shadow: 5px 5px 15px rgba(0,0,0,0.1);
         }}
 
         .status-item h3 {{
-            color: #666;
+            color: var(--muted);
             font-size: 0.9rem;
             text-transform: uppercase;
             letter-spacing: 1px;
@@ -327,10 +1518,16 @@ fn create_html_page(
         .status-building {{ color: #ffc107; }}
         .status-success {{ color: #28a745; }}
         .status-failed {{ color: #dc3545; }}
+        .status-testsfailed {{ color: #fd7e14; }}
+        .status-rolledback {{ color: #fd7e14; }}
         .status-pending {{ color: #6c757d; }}
+        .status-skipped {{ color: #6c757d; }}
+        .status-queued {{ color: #17a2b8; }}
+        .status-cancelled {{ color: #6c757d; }}
+        .status-waitingforplayers {{ color: #fd7e14; }}
 
         .builds-section {{
-            background: white;
+            background: var(--card-bg);
             border-radius: 20px;
             padding: 30px;
             box-shadow: 0 10px 30px rgba(0,0,0,0.1);
@@ -338,13 +1535,13 @@ fn create_html_page(
 
         .builds-section h2 {{
             margin-bottom: 20px;
-            color: #333;
+            color: var(--text);
             border-bottom: 2px solid #667eea;
             padding-bottom: 10px;
         }}
 
         .build-item {{
-            background: #f8f9fa;
+            background: var(--build-bg);
             border-radius: 10px;
             padding: 15px;
             margin-bottom: 15px;
@@ -356,6 +1553,11 @@ fn create_html_page(
             transform: translateX(5px);
         }}
 
+        .build-item.build-skipped {{
+            opacity: 0.55;
+            border-left-color: #6c757d;
+        }}
+
         .build-header {{
             display: flex;
             justify-content: space-between;
@@ -365,15 +1567,43 @@ fn create_html_page(
 
         .commit-sha {{
             font-family: 'Monaco', 'Menlo', 'Ubuntu Mono', monospace;
-            background: #e9ecef;
+            background: var(--code-bg);
             padding: 2px 8px;
             border-radius: 4px;
             font-size: 0.9rem;
         }}
 
+        a.commit-sha {{
+            color: inherit;
+            text-decoration: none;
+        }}
+
+        a.commit-sha:hover {{
+            text-decoration: underline;
+        }}
+
         .build-time {{
-            color: #666;
+            color: var(--muted);
+            font-size: 0.9rem;
+        }}
+
+        .build-commit {{
+            margin-bottom: 5px;
+            overflow: hidden;
+            text-overflow: ellipsis;
+            white-space: nowrap;
+        }}
+
+        .build-rollback {{
+            color: #fd7e14;
+            font-size: 0.9rem;
+            margin-top: 5px;
+        }}
+
+        .build-estimate {{
+            color: var(--muted);
             font-size: 0.9rem;
+            margin-top: 5px;
         }}
 
         .build-status {{
@@ -384,6 +1614,72 @@ fn create_html_page(
             text-transform: uppercase;
         }}
 
+        .build-command {{
+            color: var(--command);
+            margin-top: 5px;
+            font-family: monospace;
+            font-size: 0.85rem;
+        }}
+
+        .build-diagnostics {{
+            margin-top: 10px;
+            font-size: 0.9rem;
+        }}
+
+        .build-diagnostics summary {{
+            cursor: pointer;
+            color: #721c24;
+        }}
+
+        .build-changed-files {{
+            margin-top: 10px;
+            font-size: 0.9rem;
+        }}
+
+        .build-changed-files summary {{
+            cursor: pointer;
+            color: var(--muted);
+        }}
+
+        .changed-files {{
+            padding: 8px;
+            margin: 5px 0 0;
+            border-radius: 5px;
+            font-size: 0.85rem;
+            background: var(--code-bg);
+            overflow-x: auto;
+        }}
+
+        .diagnostic {{
+            padding: 8px;
+            margin: 5px 0 0;
+            border-radius: 5px;
+            font-size: 0.85rem;
+            white-space: pre-wrap;
+            overflow-x: auto;
+        }}
+
+        .diagnostic-error {{
+            background: #f8d7da;
+            color: #721c24;
+        }}
+
+        .diagnostic-warning {{
+            background: #fff3cd;
+            color: #856404;
+        }}
+
+        .dry-run-banner {{
+            background: #fff3cd;
+            color: #856404;
+            border: 1px solid #ffeeba;
+            padding: 12px 20px;
+            border-radius: 10px;
+            margin-bottom: 20px;
+            text-align: center;
+            font-weight: bold;
+        }}
+
         .error-message {{
             background: #f8d7da;
             color: #721c24;
@@ -422,7 +1718,7 @@ fn create_html_page(
         .auto-refresh {{
             text-align: center;
             margin-top: 20px;
-            color: #666;
+            color: var(--muted);
         }}
 
         .refresh-indicator {{
@@ -445,6 +1741,51 @@ fn create_html_page(
             animation: pulse 2s infinite;
         }}
 
+        .console-section {{
+            background: var(--card-bg);
+            border-radius: 20px;
+            padding: 30px;
+            margin-top: 30px;
+            box-shadow: 0 10px 30px rgba(0,0,0,0.1);
+        }}
+
+        .console-section h2 {{
+            margin-bottom: 20px;
+            color: var(--text);
+            border-bottom: 2px solid #667eea;
+            padding-bottom: 10px;
+        }}
+
+        .console-output {{
+            background: #1e1e1e;
+            color: #d4d4d4;
+            font-family: 'Monaco', 'Menlo', 'Ubuntu Mono', monospace;
+            font-size: 0.85rem;
+            padding: 15px;
+            border-radius: 10px;
+            height: 300px;
+            overflow-y: auto;
+            white-space: pre-wrap;
+            word-break: break-all;
+        }}
+
+        .build-output-id {{
+            font-family: 'Monaco', 'Menlo', 'Ubuntu Mono', monospace;
+            font-size: 0.9rem;
+            color: var(--muted);
+        }}
+
+        .console-input {{
+            width: 100%;
+            margin-top: 10px;
+            padding: 10px;
+            border: 1px solid var(--input-border);
+            background: var(--build-bg);
+            color: var(--text);
+            border-radius: 8px;
+            font-family: 'Monaco', 'Menlo', 'Ubuntu Mono', monospace;
+        }}
+
         @media (max-width: 768px) {{
             .header h1 {{
                 font-size: 2rem;
@@ -460,10 +1801,10 @@ fn create_html_page(
                 gap: 10px;
             }}
 
-            .lang-switch {{
+            .header-actions {{
                 position: static;
+                justify-content: center;
                 margin-bottom: 20px;
-                display: inline-block;
             }}
         }}
     </style>
@@ -471,7 +1812,10 @@ fn create_html_page(
 <body>
     <div class="container">
         <div class="header">
-            <a href="/?lang={}" class="lang-switch">{}</a>
+            <div class="header-actions">
+                <button class="lang-switch" id="theme-toggle" onclick="toggleTheme()">{}</button>
+                <select class="lang-switch" id="lang-select" onchange="switchLanguage(this.value)">{}</select>
+            </div>
             <h1>🎃 {}</h1>
             <p>{}</p>
             <div class="server-info">
@@ -479,6 +1823,12 @@ fn create_html_page(
             </div>
         </div>
 
+        {}
+
+        {}
+
+        {}
+
         <div class="status-card">
             <div class="status-grid">
                 <div class="status-item">
@@ -486,6 +1836,7 @@ fn create_html_page(
                     <div class="status-value {}" id="running-status">
                         {}
                     </div>
+                    <div class="build-estimate" id="health-status">{}</div>
                 </div>
                 
                 <div class="status-item">
@@ -493,6 +1844,7 @@ fn create_html_page(
                     <div class="status-value {}" id="build-status">
                         {}
                     </div>
+                    <div class="build-estimate" id="build-estimate">{}</div>
                 </div>
                 
                 <div class="status-item">
@@ -508,10 +1860,41 @@ fn create_html_page(
                         {}
                     </div>
                 </div>
+
+                <div class="status-item">
+                    <h3>{}</h3>
+                    <div class="status-value" id="memory-usage">
+                        {}
+                    </div>
+                </div>
+
+                <div class="status-item">
+                    <h3>{}</h3>
+                    <div class="status-value" id="cpu-usage">
+                        {}
+                    </div>
+                </div>
+
+                <div class="status-item">
+                    <h3>{}</h3>
+                    <div class="status-value" id="workspace-size">
+                        {}
+                    </div>
+                </div>
+
+                <div class="status-item">
+                    <h3>{}</h3>
+                    <div class="status-value" id="players">
+                        {}
+                    </div>
+                    <div class="build-estimate" id="server-motd">{}</div>
+                </div>
             </div>
             
             <div style="text-align: center;">
                 <button class="refresh-btn" id="refresh-btn" onclick="refreshData()">{}</button>
+                <button class="refresh-btn" id="build-btn" onclick="triggerBuild()"{}>{}</button>
+                <button class="refresh-btn" id="pause-btn" onclick="togglePause()">{}</button>
                 <span class="auto-refresh" id="auto-refresh-status">
                     {}<span class="refresh-indicator"></span>
                 </span>
@@ -524,41 +1907,77 @@ fn create_html_page(
                 {}
             </div>
         </div>
+
+        <div class="console-section">
+            <h2>🔨 {} <span class="build-output-id" id="build-output-id"></span></h2>
+            <div class="console-output" id="build-output"></div>
+        </div>
+
+        <div class="console-section">
+            <h2>🖥️ {}</h2>
+            <div class="console-output" id="console-output"></div>
+            <input class="console-input" id="console-input" type="text" placeholder="&gt;" autocomplete="off">
+        </div>
     </div>
 
     <script>
-        let refreshInterval;
-        let currentLang = '{}';
-        
-        const translations = {{
-            'zh': {{
-                'running': '运行中',
-                'stopped': '已停止',
-                'building': '构建中',
-                'success': '成功',
-                'failed': '失败',
-                'pending': '等待中',
-                'refresh_status': '刷新状态',
-                'refreshing': '刷新中...',
-                'auto_refresh_enabled': '自动刷新已启用',
-                'no_builds': '暂无构建记录'
-            }},
-            'en': {{
-                'running': 'Running',
-                'stopped': 'Stopped',
-                'building': 'Building',
-                'success': 'Success',
-                'failed': 'Failed',
-                'pending': 'Pending',
-                'refresh_status': 'Refresh Status',
-                'refreshing': 'Refreshing...',
-                'auto_refresh_enabled': 'Auto refresh enabled',
-                'no_builds': 'No build records'
-            }}
-        }};
-        
+        const currentRepo = {};
+        const repoParam = 'repo=' + encodeURIComponent(currentRepo);
+
+        // Labels for the current language, generated from the server-side locale table
+        const translations = {};
+
         function t(key) {{
-            return translations[currentLang][key] || key;
+            return translations[key] || key;
+        }}
+
+        // BuildStatusType names such as TestsFailed map to label keys such as tests_failed
+        function statusKey(status) {{
+            return status.replace(/([a-z])([A-Z])/g, '$1_$2').toLowerCase();
+        }}
+
+        function switchLanguage(lang) {{
+            location.href = `/?lang=${{encodeURIComponent(lang)}}&${{repoParam}}`;
+        }}
+
+        // Protected endpoints: send the API token saved in localStorage and ask for it on 401
+        let tokenPromptDeclined = false;
+
+        function authQuery() {{
+            const token = localStorage.getItem('api_token');
+            return token ? '&api_token=' + encodeURIComponent(token) : '';
+        }}
+
+        async function apiFetch(url, options = {{}}) {{
+            const token = localStorage.getItem('api_token');
+            const headers = Object.assign({{}}, options.headers, token ? {{ 'Authorization': 'Bearer ' + token }} : {{}});
+            const response = await fetch(url, Object.assign({{}}, options, {{ headers }}));
+            if (response.status === 401 && !tokenPromptDeclined) {{
+                const entered = prompt(t('enter_api_token'));
+                if (entered) {{
+                    localStorage.setItem('api_token', entered);
+                    return apiFetch(url, options);
+                }}
+                tokenPromptDeclined = true;
+            }}
+            return response;
+        }}
+
+        async function triggerBuild() {{
+            const response = await apiFetch(`/api/build?${{repoParam}}`, {{ method: 'POST' }});
+            if (!response.ok) {{
+                alert(t('build_request_failed') + ': ' + await response.text());
+            }}
+        }}
+
+        async function togglePause() {{
+            const action = lastStatus && lastStatus.paused ? 'resume' : 'pause';
+            const response = await apiFetch(`/api/monitor/${{action}}?${{repoParam}}`, {{ method: 'POST' }});
+            if (!response.ok) {{
+                alert(t('build_request_failed') + ': ' + await response.text());
+                return;
+            }}
+            refreshData();
         }}
 
         async function refreshData() {{
@@ -568,16 +1987,16 @@ fn create_html_page(
             
             try {{
                 // Fetch status
-                const statusResponse = await fetch('/api/status');
+                const statusResponse = await apiFetch('/api/status');
                 const statusData = await statusResponse.json();
                 
                 // Fetch builds
-                const buildsResponse = await fetch('/api/builds?limit=10');
+                const buildsResponse = await apiFetch(`/api/builds?limit=10&${{repoParam}}`);
                 const buildsData = await buildsResponse.json();
                 
                 if (statusData.success && buildsData.success) {{
-                    updateStatus(statusData.data);
-                    updateBuilds(buildsData.data);
+                    updateStatus(statusData.data[currentRepo]);
+                    updateBuilds(buildsData.data.builds);
                 }}
             }} catch (error) {{
                 console.error('Refresh failed:', error);
@@ -587,7 +2006,40 @@ fn create_html_page(
             }}
         }}
         
+        let lastStatus = null;
+        let latestBuilds = [];
+
+        function formatDuration(secs) {{
+            if (secs >= 3600) {{
+                return `${{Math.floor(secs / 3600)}}h ${{Math.floor(secs % 3600 / 60)}}m`;
+            }}
+            if (secs >= 60) {{
+                return `${{Math.floor(secs / 60)}}m ${{secs % 60}}s`;
+            }}
+            return `${{secs}}s`;
+        }}
+
+        // 构建中时根据平均构建耗时估算剩余时间
+        function updateBuildEstimate() {{
+            const estimate = document.getElementById('build-estimate');
+            const building = latestBuilds.find(build => build.status === 'Building');
+            if (lastStatus && lastStatus.build_status === 'WaitingForPlayers' && lastStatus.pending_commit) {{
+                estimate.textContent = t('pending_deploy').replace('{{}}', lastStatus.pending_commit.substring(0, 8));
+                return;
+            }}
+            if (!lastStatus || lastStatus.build_status !== 'Building' || !lastStatus.avg_build_duration_secs || !building) {{
+                estimate.textContent = '';
+                return;
+            }}
+            const elapsed = (Date.now() - new Date(building.started_at).getTime()) / 1000;
+            const minutes = Math.max(1, Math.ceil((lastStatus.avg_build_duration_secs - elapsed) / 60));
+            estimate.textContent = t('remaining').replace('{{}}', minutes);
+        }}
+
+        setInterval(updateBuildEstimate, 30000);
+
         function updateStatus(status) {{
+            lastStatus = status;
             const runningStatus = document.getElementById('running-status');
             const buildStatus = document.getElementById('build-status');
             const currentCommit = document.getElementById('current-commit');
@@ -596,9 +2048,15 @@ fn create_html_page(
             // Update running status
             runningStatus.textContent = status.is_running ? t('running') : t('stopped');
             runningStatus.className = 'status-value ' + (status.is_running ? 'status-running' : 'status-stopped');
+            document.getElementById('paused-banner').style.display = status.paused ? '' : 'none';
+            document.getElementById('paused-until').textContent = status.pause_until ?
+                t('paused_until').replace('{{}}', new Date(status.pause_until).toLocaleString()) : '';
+            document.getElementById('pause-btn').textContent = status.paused ? t('resume_monitoring') : t('pause_monitoring');
+            document.getElementById('health-status').textContent =
+                status.health && status.health !== 'Unknown' ? t(status.health.toLowerCase()) : '';
             
             // Update build status
-            const buildStatusText = t(status.build_status.toLowerCase());
+            const buildStatusText = t(statusKey(status.build_status));
             buildStatus.textContent = buildStatusText;
             buildStatus.className = 'status-value status-' + status.build_status.toLowerCase();
             
@@ -606,8 +2064,8 @@ fn create_html_page(
             currentCommit.textContent = status.current_commit ? status.current_commit.substring(0, 8) : 'Unknown';
             
             // Update uptime
-            if (status.uptime) {{
-                const secs = new Date(status.uptime).getTime() / 1000;
+            if (status.uptime_seconds != null) {{
+                const secs = status.uptime_seconds;
                 const days = Math.floor(secs / 86400);
                 const hours = Math.floor((secs % 86400) / 3600);
                 const minutes = Math.floor((secs % 3600) / 60);
@@ -615,30 +2073,111 @@ fn create_html_page(
             }} else {{
                 uptime.textContent = 'Unknown';
             }}
+
+            document.getElementById('memory-usage').textContent =
+                status.memory_bytes != null ? formatMemory(status.memory_bytes) : '-';
+            document.getElementById('cpu-usage').textContent =
+                status.cpu_percent != null ? `${{status.cpu_percent.toFixed(1)}}%` : '-';
+            document.getElementById('workspace-size').textContent =
+                status.workspace_size_bytes != null ? formatMemory(status.workspace_size_bytes) : '-';
+            const serverInfo = status.server_info;
+            document.getElementById('players').textContent =
+                serverInfo ? `${{serverInfo.players_online}} / ${{serverInfo.players_max}}` : '-';
+            document.getElementById('server-motd').textContent =
+                serverInfo ? [serverInfo.version, serverInfo.motd].filter(Boolean).join(' · ') : '';
+
+            updateBuildEstimate();
         }}
         
+        // Same format as format_memory on the server side
+        function formatMemory(bytes) {{
+            const mib = bytes / (1024 * 1024);
+            return mib >= 1024 ? `${{(mib / 1024).toFixed(2)}} GiB` : `${{mib.toFixed(1)}} MiB`;
+        }}
+
+        function escapeHtml(text) {{
+            const div = document.createElement('div');
+            div.textContent = text;
+            return div.innerHTML;
+        }}
+
+        function renderDiagnostics(diagnostics) {{
+            if (diagnostics.length === 0) {{
+                return '';
+            }}
+            const errors = diagnostics.filter(d => d.level === 'error').length;
+            const warnings = diagnostics.length - errors;
+            const noun = (nouns, count) => nouns[count === 1 ? 0 : 1];
+            const summary = `${{errors}} ${{noun(t('error_noun'), errors)}}${{t('list_separator')}}${{warnings}} ${{noun(t('warning_noun'), warnings)}}`;
+            const items = diagnostics.map(d => {{
+                const location = d.file && d.line != null ? ` (${{d.file}}:${{d.line}})` : '';
+                const text = d.rendered || `${{d.level}}: ${{d.message}}${{location}}`;
+                return `<pre class="diagnostic diagnostic-${{escapeHtml(d.level)}}">${{escapeHtml(text)}}</pre>`;
+            }}).join('');
+            return `<details class="build-diagnostics"><summary>${{summary}}</summary>${{items}}</details>`;
+        }}
+
+        function renderChangedFiles(files) {{
+            if (files.length === 0) {{
+                return '';
+            }}
+            const summary = t('changed_files').replace('{{}}', files.length);
+            return `<details class="build-changed-files"><summary>${{summary}}</summary><pre class="changed-files">${{escapeHtml(files.join('\n'))}}</pre></details>`;
+        }}
+
+        function commitTitle(message) {{
+            const line = message.split('\n')[0];
+            return line.length > 100 ? line.substring(0, 100) + '…' : line;
+        }}
+
         function updateBuilds(builds) {{
+            latestBuilds = builds || [];
+            updateBuildEstimate();
             const container = document.getElementById('builds-container');
             
             if (!builds || builds.length === 0) {{
-                container.innerHTML = `<p style="text-align: center; color: #666; padding: 40px;">${{t('no_builds')}}</p>`;
+                container.innerHTML = `<p style="text-align: center; color: var(--muted); padding: 40px;">${{t('no_builds')}}</p>`;
                 return;
             }}
             
             const buildsHtml = builds.map(build => {{
-                const statusText = t(build.status.toLowerCase());
+                const statusText = t(statusKey(build.status));
                 const statusClass = 'status-' + build.status.toLowerCase();
-                const errorHtml = build.error_message ? 
-                    `<div class="error-message">${{build.error_message}}</div>` : '';
-                const buildTime = new Date(build.started_at).toLocaleString();
+                const itemClass = build.status === 'Skipped' ? 'build-item build-skipped' : 'build-item';
+                const diagnostics = build.diagnostics || [];
+                const hasErrorDiagnostics = diagnostics.some(d => d.level === 'error');
+                const errorHtml = build.error_message && !hasErrorDiagnostics ?
+                    `<div class="error-message">${{escapeHtml(build.error_message)}}</div>` : '';
+                const diagnosticsHtml = renderDiagnostics(diagnostics);
+                const changedFilesHtml = renderChangedFiles(build.changed_files || []);
+                const shortSha = escapeHtml(build.commit_sha.substring(0, 8));
+                const shaHtml = build.commit_url ?
+                    `<a class="commit-sha" href="${{escapeHtml(build.commit_url)}}" target="_blank" rel="noopener">${{shortSha}}</a>` :
+                    `<span class="commit-sha">${{shortSha}}</span>`;
+                const commitHtml = build.commit_message ?
+                    `<div class="build-commit">${{escapeHtml(commitTitle(build.commit_message))}}${{build.commit_author ? ' — ' + escapeHtml(build.commit_author) : ''}}</div>` : '';
+                const rollbackHtml = build.rollback_of ?
+                    `<div class="build-rollback">↩ ${{escapeHtml(t('rolled_back_after').replace('{{sha}}', build.commit_sha.substring(0, 8)).replace('{{failed}}', build.rollback_of.substring(0, 8)))}}</div>` : '';
+                const commandHtml = build.command ?
+                    `<div class="build-command">$ ${{escapeHtml(build.command)}}</div>` : '';
+                const duration = build.duration_seconds != null ? ` · ${{formatDuration(build.duration_seconds)}}` : '';
+                const attempt = build.attempt > 1 && build.max_attempts ?
+                    ' · ' + t('attempt').replace('{{attempt}}', build.attempt).replace('{{max}}', build.max_attempts) : '';
+                const trigger = ' · ' + t('trigger_' + (build.trigger || 'Poll').toLowerCase());
+                const buildTime = new Date(build.started_at).toLocaleString() + duration + trigger + attempt;
                 
                 return `
-                    <div class="build-item">
+                    <div class="${{itemClass}}">
                         <div class="build-header">
-                            <span class="commit-sha">${{build.commit_sha.substring(0, 8)}}</span>
+                            ${{shaHtml}}
                             <span class="build-status ${{statusClass}}">${{statusText}}</span>
                         </div>
+                        ${{commitHtml}}
                         <div class="build-time">${{buildTime}}</div>
+                        ${{rollbackHtml}}
+                        ${{changedFilesHtml}}
+                        ${{commandHtml}}
+                        ${{diagnosticsHtml}}
                         ${{errorHtml}}
                     </div>
                 `;
@@ -647,13 +2186,184 @@ fn create_html_page(
             container.innerHTML = buildsHtml;
         }}
         
-        // Start auto refresh
-        function startAutoRefresh() {{
-            refreshInterval = setInterval(refreshData, 30000);
+        // Server console over WebSocket
+        let consoleSocket;
+        // Command to send once reconnected with a (new) console token
+        let pendingCommand = null;
+        // The server closes the socket right after rejecting a command, so this is the rejected one
+        let lastConsoleCommand = null;
+
+        function promptConsoleToken() {{
+            const token = prompt('Console token');
+            if (token) {{
+                localStorage.setItem('console_token', token);
+            }} else {{
+                pendingCommand = null;
+            }}
+            return !!token;
         }}
-        
+
+        function sendConsoleCommand(command) {{
+            lastConsoleCommand = command;
+            consoleSocket.send(command);
+            const input = document.getElementById('console-input');
+            if (input.value === command) {{
+                input.value = '';
+            }}
+        }}
+
+        function connectConsole() {{
+            const protocol = location.protocol === 'https:' ? 'wss:' : 'ws:';
+            const token = localStorage.getItem('console_token') || '';
+            consoleSocket = new WebSocket(`${{protocol}}//${{location.host}}/api/server/console?token=${{encodeURIComponent(token)}}&${{repoParam}}${{authQuery()}}`);
+            consoleSocket.onopen = function() {{
+                if (pendingCommand !== null && localStorage.getItem('console_token')) {{
+                    const command = pendingCommand;
+                    pendingCommand = null;
+                    sendConsoleCommand(command);
+                }}
+            }};
+            consoleSocket.onmessage = function(event) {{
+                const output = document.getElementById('console-output');
+                const atBottom = output.scrollTop + output.clientHeight >= output.scrollHeight - 5;
+                output.textContent += event.data + '\n';
+                if (output.textContent.length > 200000) {{
+                    output.textContent = output.textContent.slice(-100000);
+                }}
+                if (atBottom) {{
+                    output.scrollTop = output.scrollHeight;
+                }}
+            }};
+            consoleSocket.onclose = function(event) {{
+                // Rejected as read-only: the saved token is missing or wrong, ask again and resend the command
+                if (event.code === {read_only_code}) {{
+                    localStorage.removeItem('console_token');
+                    pendingCommand = lastConsoleCommand;
+                    if (promptConsoleToken()) {{
+                        connectConsole();
+                        return;
+                    }}
+                }}
+                setTimeout(connectConsole, 5000);
+            }};
+        }}
+
+        document.getElementById('console-input').addEventListener('keydown', function(event) {{
+            if (event.key !== 'Enter' || !this.value) {{
+                return;
+            }}
+            if (!localStorage.getItem('console_token')) {{
+                pendingCommand = this.value;
+                if (promptConsoleToken()) {{
+                    // Reconnect with the token, onopen sends the pending command
+                    consoleSocket.onclose = null;
+                    consoleSocket.close();
+                    connectConsole();
+                }}
+                return;
+            }}
+            if (consoleSocket && consoleSocket.readyState === WebSocket.OPEN) {{
+                sendConsoleCommand(this.value);
+            }}
+        }});
+
+        connectConsole();
+
+        // Live build output over WebSocket
+        let currentBuildId = null;
+
+        function connectBuildOutput() {{
+            const protocol = location.protocol === 'https:' ? 'wss:' : 'ws:';
+            const socket = new WebSocket(`${{protocol}}//${{location.host}}/ws/build?${{repoParam}}${{authQuery()}}`);
+            socket.onmessage = function(event) {{
+                const frame = JSON.parse(event.data);
+                const output = document.getElementById('build-output');
+                if (frame.build_id !== currentBuildId) {{
+                    currentBuildId = frame.build_id;
+                    output.textContent = '';
+                    document.getElementById('build-output-id').textContent = frame.build_id.substring(0, 8);
+                }}
+                const atBottom = output.scrollTop + output.clientHeight >= output.scrollHeight - 5;
+                output.textContent += frame.line + '\n';
+                if (output.textContent.length > 200000) {{
+                    output.textContent = output.textContent.slice(-100000);
+                }}
+                if (atBottom) {{
+                    output.scrollTop = output.scrollHeight;
+                }}
+            }};
+            socket.onclose = function() {{
+                setTimeout(connectBuildOutput, 5000);
+            }};
+        }}
+
+        connectBuildOutput();
+
+        // Live status and build updates over WebSocket, polling while disconnected
+        let pollTimer = null;
+
+        function startPolling() {{
+            if (pollTimer === null) {{
+                pollTimer = setInterval(refreshData, 30000);
+            }}
+        }}
+
+        function stopPolling() {{
+            if (pollTimer !== null) {{
+                clearInterval(pollTimer);
+                pollTimer = null;
+            }}
+        }}
+
+        function upsertBuild(build) {{
+            const builds = latestBuilds.filter(b => b.id !== build.id);
+            builds.push(build);
+            builds.sort((a, b) => new Date(b.started_at) - new Date(a.started_at));
+            updateBuilds(builds.slice(0, 10));
+        }}
+
+        function connectLive() {{
+            const protocol = location.protocol === 'https:' ? 'wss:' : 'ws:';
+            const socket = new WebSocket(`${{protocol}}//${{location.host}}/ws?${{repoParam}}${{authQuery()}}`);
+            socket.onopen = function() {{
+                stopPolling();
+                // Pick up builds that changed while disconnected
+                refreshData();
+            }};
+            socket.onmessage = function(event) {{
+                const data = JSON.parse(event.data);
+                if (data.type === 'status') {{
+                    updateStatus(data.status);
+                }} else if (data.type === 'build') {{
+                    upsertBuild(data.build);
+                }}
+            }};
+            socket.onclose = function() {{
+                startPolling();
+                setTimeout(connectLive, 5000);
+            }};
+        }}
+
+        // Theme: ?theme= on the URL wins over the choice saved in localStorage
+        function applyTheme(theme) {{
+            document.documentElement.dataset.theme = theme;
+            document.getElementById('theme-toggle').textContent = theme === 'dark' ? t('theme_light') : t('theme_dark');
+        }}
+
+        function toggleTheme() {{
+            const theme = document.documentElement.dataset.theme === 'dark' ? 'light' : 'dark';
+            localStorage.setItem('theme', theme);
+            applyTheme(theme);
+        }}
+
+        const themeParam = new URLSearchParams(location.search).get('theme');
+        if (themeParam === 'dark' || themeParam === 'light') {{
+            localStorage.setItem('theme', themeParam);
+        }}
+        applyTheme(document.documentElement.dataset.theme);
+
         // Initialize
-        startAutoRefresh();
+        connectLive();
         
         // Refresh on visibility change
         document.addEventListener('visibilitychange', function() {{
@@ -664,13 +2374,64 @@ fn create_html_page(
     </script>
 </body>
 </html>"#,
-        lang_attr, title, other_lang, lang_switch_text, title, subtitle, server_info,
-        running_status_label, running_class, running_status_text,
-        build_status_label, build_class, build_status_text,
-        current_commit_label, current_commit,
-        uptime_label, uptime,
-        refresh_btn_text, auto_refresh_text,
-        build_history_label, builds_html,
-        lang
+        labels.html_lang, theme, title, theme_toggle_text, lang_options, title, labels.subtitle, labels.server_info,
+        dry_run_html,
+        paused_html,
+        repo_tabs_html,
+        labels.running_status, running_class, running_status_text, health_text,
+        labels.build_status, build_class, build_status_text, build_estimate,
+        labels.current_commit, current_commit,
+        labels.uptime, uptime,
+        labels.memory_usage, memory_usage,
+        labels.cpu_usage, cpu_usage,
+        labels.workspace_size, workspace_size,
+        labels.players, players, server_motd,
+        labels.refresh_status, build_button_style, labels.build_now, pause_button_text, labels.auto_refresh_enabled,
+        labels.build_history, builds_html,
+        labels.build_output,
+        labels.console,
+        current_repo_js,
+        translations_js,
+        read_only_code = CONSOLE_READ_ONLY,
     )
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn render(current_commit: Option<&str>, builds: &[BuildStatus]) -> String {
+        let status = SystemStatus {
+            current_commit: current_commit.map(str::to_string),
+            ..StorageData::default().system_status
+        };
+        create_html_page(&status, builds, crate::i18n::labels("en"), "light", "Pumpkin", &["Pumpkin"], false)
+    }
+
+    fn build(sha: &str) -> BuildStatus {
+        let mut build = BuildStatus::for_commit(uuid::Uuid::new_v4(), &GitHubCommit {
+            sha: sha.to_string(),
+            message: "Fix all the bugs".to_string(),
+            author: "octocat".to_string(),
+            date: chrono::Utc::now(),
+            html_url: None,
+            parents: Vec::new(),
+            changed_files: Vec::new(),
+        });
+        build.status = BuildStatusType::Success;
+        build
+    }
+
+    #[test]
+    fn renders_page_without_current_commit() {
+        let html = render(None, &[]);
+        assert!(html.contains(r#"<span class="commit-sha" id="current-commit">Unknown</span>"#));
+    }
+
+    #[test]
+    fn renders_page_with_short_sha() {
+        let html = render(Some("abcd"), &[build("abcd")]);
+        assert!(html.contains(r#"<span class="commit-sha" id="current-commit">abcd</span>"#));
+        assert!(html.contains(r#"<span class="commit-sha">abcd</span>"#));
+    }
+}